@@ -0,0 +1,13 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPosition;
+
+uniform mat4 u_view_projection;
+
+// Direction from the cube center, used to sample or write the cube map
+out vec3 vDirection;
+
+void main() {
+    vDirection = aPosition;
+    gl_Position = u_view_projection * vec4(aPosition, 1.0);
+}
@@ -0,0 +1,105 @@
+#version 330 core
+
+// Ambient, image based and directional lighting of the deferred path, added to the light buffer
+
+// Must match MAX_LIGHTS_PER_OBJECT in light.rs
+#define MAX_LIGHTS 8
+
+in vec2 vTexCoord;
+
+out vec4 Color;
+
+// G-buffer, see deferred.rs
+uniform sampler2D u_albedo;
+uniform sampler2D u_normal;
+uniform sampler2D u_material;
+uniform sampler2D u_depth;
+uniform mat4 u_inverse_view_projection;
+uniform vec3 u_camera_position;
+uniform vec3 u_ambient;
+
+// Directional lights only
+uniform int u_light_count;
+uniform vec3 u_light_direction[MAX_LIGHTS];
+// Color multiplied by intensity
+uniform vec3 u_light_color[MAX_LIGHTS];
+
+// Image based lighting, see environment.rs
+uniform samplerCube u_irradiance_map;
+uniform samplerCube u_prefiltered_map;
+uniform sampler2D u_brdf_lut;
+uniform float u_prefilter_mips;
+uniform float u_ibl_intensity;
+
+const float PI = 3.14159265359;
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+float geometry_schlick_ggx(float n_dot_x, float roughness) {
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(u_depth, texel, 0).r;
+    // Nothing was drawn here
+    if (depth >= 1.0) {
+        discard;
+    }
+    vec4 world = u_inverse_view_projection * vec4(vTexCoord * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+    vec3 position = world.xyz / world.w;
+
+    vec3 base_color = texelFetch(u_albedo, texel, 0).rgb;
+    vec3 normal = normalize(texelFetch(u_normal, texel, 0).xyz);
+    vec3 material = texelFetch(u_material, texel, 0).rgb;
+    float metallic = material.r;
+    float roughness = material.g;
+    float occlusion = material.b;
+
+    vec3 view = normalize(u_camera_position - position);
+    float n_dot_v = max(dot(normal, view), 0.0001);
+    vec3 f0 = mix(vec3(0.04), base_color, metallic);
+
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < u_light_count && i < MAX_LIGHTS; i++) {
+        vec3 to_light = -u_light_direction[i];
+        float n_dot_l = max(dot(normal, to_light), 0.0);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+        vec3 halfway = normalize(to_light + view);
+        float d = distribution_ggx(max(dot(normal, halfway), 0.0), roughness);
+        float g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+        vec3 f = fresnel_schlick(max(dot(halfway, view), 0.0), f0);
+        vec3 specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 diffuse = (1.0 - f) * (1.0 - metallic) * base_color / PI;
+        color += (diffuse + specular) * u_light_color[i] * n_dot_l;
+    }
+
+    vec3 ambient = u_ambient * base_color;
+    if (u_ibl_intensity > 0.0) {
+        vec3 f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+        vec3 diffuse = texture(u_irradiance_map, normal).rgb * base_color * (1.0 - f) * (1.0 - metallic);
+        vec3 reflected = reflect(-view, normal);
+        vec3 prefiltered = textureLod(u_prefiltered_map, reflected, roughness * (u_prefilter_mips - 1.0)).rgb;
+        vec2 brdf = texture(u_brdf_lut, vec2(n_dot_v, roughness)).rg;
+        vec3 specular = prefiltered * (f * brdf.x + brdf.y);
+        ambient += (diffuse + specular) * u_ibl_intensity;
+    }
+    Color = vec4(color + ambient * occlusion, 1.0);
+}
@@ -0,0 +1,88 @@
+#version 330 core
+
+// Point and spot lights of the deferred path, added to the light buffer inside their volume
+
+#define LIGHT_SPOT 2
+
+flat in vec4 vLight0;
+flat in vec4 vLight1;
+flat in vec4 vLight2;
+flat in vec4 vLight3;
+
+out vec4 Color;
+
+// G-buffer, see deferred.rs
+uniform sampler2D u_albedo;
+uniform sampler2D u_normal;
+uniform sampler2D u_material;
+uniform sampler2D u_depth;
+uniform mat4 u_inverse_view_projection;
+uniform vec3 u_camera_position;
+uniform vec2 u_screen_size;
+
+const float PI = 3.14159265359;
+
+// Same falloff as attenuation() in light.rs
+float attenuation(float distance, float range) {
+    float window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+float geometry_schlick_ggx(float n_dot_x, float roughness) {
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(u_depth, texel, 0).r;
+    // Nothing was drawn here
+    if (depth >= 1.0) {
+        discard;
+    }
+    vec2 ndc = gl_FragCoord.xy / u_screen_size * 2.0 - 1.0;
+    vec4 world = u_inverse_view_projection * vec4(ndc, depth * 2.0 - 1.0, 1.0);
+    vec3 position = world.xyz / world.w;
+
+    vec3 offset = vLight0.xyz - position;
+    float distance = length(offset);
+    vec3 to_light = offset / max(distance, 1e-4);
+    float strength = attenuation(distance, vLight0.w);
+    if (int(vLight1.w) == LIGHT_SPOT) {
+        float cos_angle = dot(-to_light, vLight2.xyz);
+        strength *= smoothstep(vLight3.x, vLight2.w, cos_angle);
+    }
+    vec3 normal = normalize(texelFetch(u_normal, texel, 0).xyz);
+    float n_dot_l = max(dot(normal, to_light), 0.0);
+    if (strength <= 0.0 || n_dot_l <= 0.0) {
+        discard;
+    }
+
+    vec3 base_color = texelFetch(u_albedo, texel, 0).rgb;
+    vec3 material = texelFetch(u_material, texel, 0).rgb;
+    float metallic = material.r;
+    float roughness = material.g;
+
+    vec3 view = normalize(u_camera_position - position);
+    float n_dot_v = max(dot(normal, view), 0.0001);
+    vec3 f0 = mix(vec3(0.04), base_color, metallic);
+    vec3 halfway = normalize(to_light + view);
+    float d = distribution_ggx(max(dot(normal, halfway), 0.0), roughness);
+    float g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+    vec3 f = fresnel_schlick(max(dot(halfway, view), 0.0), f0);
+    vec3 specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * base_color / PI;
+    Color = vec4((diffuse + specular) * vLight1.rgb * strength * n_dot_l, 1.0);
+}
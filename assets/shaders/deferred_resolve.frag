@@ -0,0 +1,25 @@
+#version 330 core
+
+// Tone maps the light buffer of the deferred path into the target and writes the scene depth
+
+in vec2 vTexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_light;
+uniform sampler2D u_depth;
+uniform float u_exposure;
+
+void main() {
+    ivec2 texel = ivec2(vTexCoord * vec2(textureSize(u_depth, 0)));
+    float depth = texelFetch(u_depth, texel, 0).r;
+    // Keep what the target already has where nothing was drawn
+    if (depth >= 1.0) {
+        discard;
+    }
+    vec3 color = texelFetch(u_light, texel, 0).rgb;
+    // Exposure tone mapping, then back to sRGB
+    color = vec3(1.0) - exp(-color * u_exposure);
+    Color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+    gl_FragDepth = depth;
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec3 vDirection;
+
+out vec4 Color;
+
+uniform sampler2D u_equirect;
+
+// 1 / (2 pi), 1 / pi
+const vec2 INV_ATAN = vec2(0.1591, 0.3183);
+
+void main() {
+    vec3 direction = normalize(vDirection);
+    vec2 uv = vec2(atan(direction.z, direction.x), asin(direction.y)) * INV_ATAN + 0.5;
+    Color = vec4(texture(u_equirect, uv).rgb, 1.0);
+}
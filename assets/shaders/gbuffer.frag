@@ -0,0 +1,53 @@
+#version 330 core
+
+// Geometry pass of the deferred path, takes the same parameters as pbr.frag
+
+in vec3 vWorldPosition;
+in vec2 vTexCoord;
+in mat3 vTBN;
+
+// Base color, linear
+layout (location = 0) out vec4 Albedo;
+// World space normal
+layout (location = 1) out vec4 Normal;
+// Metallic, roughness, occlusion
+layout (location = 2) out vec4 Material;
+// Light accumulation, starts with the emission
+layout (location = 3) out vec4 Light;
+
+uniform vec4 u_base_color_factor;
+uniform float u_metallic_factor;
+uniform float u_roughness_factor;
+uniform float u_normal_scale;
+uniform float u_occlusion_strength;
+uniform vec3 u_emissive_factor;
+// sRGB
+uniform sampler2D u_base_color_map;
+// Roughness in green, metallic in blue
+uniform sampler2D u_metallic_roughness_map;
+uniform sampler2D u_normal_map;
+// Occlusion in red
+uniform sampler2D u_occlusion_map;
+// sRGB
+uniform sampler2D u_emissive_map;
+
+vec3 srgb_to_linear(vec3 color) {
+    return pow(color, vec3(2.2));
+}
+
+void main() {
+    vec4 base_sample = texture(u_base_color_map, vTexCoord);
+    vec3 base_color = u_base_color_factor.rgb * srgb_to_linear(base_sample.rgb);
+    vec4 metallic_roughness = texture(u_metallic_roughness_map, vTexCoord);
+    float metallic = clamp(u_metallic_factor * metallic_roughness.b, 0.0, 1.0);
+    float roughness = clamp(u_roughness_factor * metallic_roughness.g, 0.04, 1.0);
+    float occlusion = mix(1.0, texture(u_occlusion_map, vTexCoord).r, u_occlusion_strength);
+
+    vec3 tangent_normal = texture(u_normal_map, vTexCoord).xyz * 2.0 - 1.0;
+    tangent_normal.xy *= u_normal_scale;
+
+    Albedo = vec4(base_color, 1.0);
+    Normal = vec4(normalize(vTBN * tangent_normal), 0.0);
+    Material = vec4(metallic, roughness, occlusion, 1.0);
+    Light = vec4(srgb_to_linear(texture(u_emissive_map, vTexCoord).rgb) * u_emissive_factor, 1.0);
+}
@@ -0,0 +1,30 @@
+#version 330 core
+
+in vec3 vDirection;
+
+out vec4 Color;
+
+uniform samplerCube u_environment;
+
+const float PI = 3.14159265359;
+
+// Cosine weighted integral of the environment over the hemisphere around the normal
+void main() {
+    vec3 normal = normalize(vDirection);
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, normal));
+    up = normalize(cross(normal, right));
+
+    vec3 irradiance = vec3(0.0);
+    float samples = 0.0;
+    float step_size = 0.025;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += step_size) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += step_size) {
+            vec3 tangent_sample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 direction = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * normal;
+            irradiance += texture(u_environment, direction).rgb * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+    Color = vec4(PI * irradiance / samples, 1.0);
+}
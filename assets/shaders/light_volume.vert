@@ -0,0 +1,30 @@
+#version 330 core
+
+// Sphere around a point or spot light of the deferred path, one instance per light
+
+layout (location = 0) in vec3 aPosition;
+// Position and range
+layout (location = 4) in vec4 aLight0;
+// Color multiplied by intensity, and the type
+layout (location = 5) in vec4 aLight1;
+// Direction and cosine of the inner angle
+layout (location = 6) in vec4 aLight2;
+// Cosine of the outer angle
+layout (location = 7) in vec4 aLight3;
+
+uniform mat4 u_view_projection;
+
+flat out vec4 vLight0;
+flat out vec4 vLight1;
+flat out vec4 vLight2;
+flat out vec4 vLight3;
+
+void main() {
+    // The sphere mesh has a diameter of 1 and its faces dip inside it, grow it past the range
+    vec3 world = aLight0.xyz + aPosition * aLight0.w * 2.1;
+    vLight0 = aLight0;
+    vLight1 = aLight1;
+    vLight2 = aLight2;
+    vLight3 = aLight3;
+    gl_Position = u_view_projection * vec4(world, 1.0);
+}
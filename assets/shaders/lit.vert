@@ -0,0 +1,36 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPosition;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec2 aTexCoord;
+layout (location = 3) in vec4 aTangent;
+// Model matrix rows, one matrix per instance
+layout (location = 4) in vec4 aModel0;
+layout (location = 5) in vec4 aModel1;
+layout (location = 6) in vec4 aModel2;
+layout (location = 7) in vec4 aModel3;
+
+uniform mat4 u_view_projection;
+
+out vec3 vWorldPosition;
+out vec2 vTexCoord;
+out mat3 vTBN;
+
+void main() {
+    mat4 model = transpose(mat4(aModel0, aModel1, aModel2, aModel3));
+    vec4 world = model * vec4(aPosition, 1.0);
+
+    vec3 normal = normalize(transpose(inverse(mat3(model))) * aNormal);
+    vec3 tangent = mat3(model) * aTangent.xyz;
+    // Meshes without tangents get an arbitrary one, their normal maps are flat anyway
+    if (dot(tangent, tangent) < 1e-8) {
+        tangent = abs(normal.y) < 0.99 ? cross(vec3(0.0, 1.0, 0.0), normal) : vec3(1.0, 0.0, 0.0);
+    }
+    tangent = normalize(tangent - dot(tangent, normal) * normal);
+    float handedness = aTangent.w < 0.0 ? -1.0 : 1.0;
+    vTBN = mat3(tangent, cross(normal, tangent) * handedness, normal);
+
+    vWorldPosition = world.xyz;
+    vTexCoord = aTexCoord;
+    gl_Position = u_view_projection * world;
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec3 vNormal;
+in vec2 vTexCoord;
+
+uniform vec4 u_color;
+
+out vec4 Color;
+
+void main() {
+    Color = u_color;
+}
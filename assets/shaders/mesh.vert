@@ -0,0 +1,22 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPosition;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec2 aTexCoord;
+// Model matrix rows, one matrix per instance
+layout (location = 4) in vec4 aModel0;
+layout (location = 5) in vec4 aModel1;
+layout (location = 6) in vec4 aModel2;
+layout (location = 7) in vec4 aModel3;
+
+uniform mat4 u_view_projection;
+
+out vec3 vNormal;
+out vec2 vTexCoord;
+
+void main() {
+    mat4 model = transpose(mat4(aModel0, aModel1, aModel2, aModel3));
+    gl_Position = u_view_projection * model * vec4(aPosition, 1.0);
+    vNormal = mat3(model) * aNormal;
+    vTexCoord = aTexCoord;
+}
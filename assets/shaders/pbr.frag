@@ -0,0 +1,235 @@
+#version 330 core
+
+// Metallic-roughness shading following the glTF material model
+
+// Must match MAX_LIGHTS_PER_OBJECT in light.rs
+#define MAX_LIGHTS 8
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+// Must match MAX_CASCADES and MAX_SHADOW_VIEWS in shadow.rs
+#define MAX_CASCADES 4
+#define MAX_SHADOW_VIEWS 16
+
+in vec3 vWorldPosition;
+in vec2 vTexCoord;
+in mat3 vTBN;
+
+out vec4 Color;
+
+uniform vec3 u_camera_position;
+uniform vec3 u_ambient;
+uniform float u_exposure;
+
+uniform int u_light_count;
+uniform int u_light_type[MAX_LIGHTS];
+uniform vec3 u_light_position[MAX_LIGHTS];
+uniform vec3 u_light_direction[MAX_LIGHTS];
+// Color multiplied by intensity
+uniform vec3 u_light_color[MAX_LIGHTS];
+uniform float u_light_range[MAX_LIGHTS];
+// Cosines of the inner and outer cone angles
+uniform vec2 u_light_cone[MAX_LIGHTS];
+
+// First shadow view of each light, -1 without shadows
+uniform int u_light_shadow[MAX_LIGHTS];
+
+// Shadow atlas, see shadow.rs
+uniform sampler2D u_shadow_atlas;
+// World space to atlas coordinates and depth of each view
+uniform mat4 u_shadow_matrices[MAX_SHADOW_VIEWS];
+// Centers of the edge texels of each view: min x, min y, max x, max y
+uniform vec4 u_shadow_tiles[MAX_SHADOW_VIEWS];
+// World size of a texel: constant part and growth with the distance to the light
+uniform vec2 u_shadow_texels[MAX_SHADOW_VIEWS];
+// Far distance of each cascade along u_camera_forward
+uniform vec4 u_cascade_splits;
+uniform int u_cascade_count;
+uniform vec3 u_camera_forward;
+uniform float u_shadow_bias;
+uniform float u_shadow_normal_bias;
+uniform int u_pcf_radius;
+uniform int u_shadow_show_cascades;
+
+// Image based lighting, see environment.rs
+uniform samplerCube u_irradiance_map;
+uniform samplerCube u_prefiltered_map;
+uniform sampler2D u_brdf_lut;
+uniform float u_prefilter_mips;
+uniform float u_ibl_intensity;
+
+uniform vec4 u_base_color_factor;
+uniform float u_metallic_factor;
+uniform float u_roughness_factor;
+uniform float u_normal_scale;
+uniform float u_occlusion_strength;
+uniform vec3 u_emissive_factor;
+// sRGB
+uniform sampler2D u_base_color_map;
+// Roughness in green, metallic in blue
+uniform sampler2D u_metallic_roughness_map;
+uniform sampler2D u_normal_map;
+// Occlusion in red
+uniform sampler2D u_occlusion_map;
+// sRGB
+uniform sampler2D u_emissive_map;
+
+const float PI = 3.14159265359;
+
+vec3 srgb_to_linear(vec3 color) {
+    return pow(color, vec3(2.2));
+}
+
+// Same falloff as attenuation() in light.rs
+float attenuation(float distance, float range) {
+    float window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+// Cascade of the directional light covering the fragment, -1 past the last one
+int cascade_index() {
+    float depth = dot(vWorldPosition - u_camera_position, u_camera_forward);
+    for (int i = 0; i < u_cascade_count && i < MAX_CASCADES; i++) {
+        if (depth <= u_cascade_splits[i]) {
+            return i;
+        }
+    }
+    return -1;
+}
+
+// Fraction of light `light` reaching the fragment, averaged over (2 * u_pcf_radius + 1)^2 texels
+float shadow_factor(int light, vec3 normal) {
+    int view = u_light_shadow[light];
+    if (view < 0) {
+        return 1.0;
+    }
+    float light_distance = 0.0;
+    if (u_light_type[light] == LIGHT_DIRECTIONAL) {
+        int cascade = cascade_index();
+        if (cascade < 0) {
+            return 1.0;
+        }
+        view += cascade;
+    } else {
+        light_distance = length(u_light_position[light] - vWorldPosition);
+    }
+
+    // Look up from a texel or so above the surface, it would shadow itself otherwise
+    vec2 texel = u_shadow_texels[view];
+    vec3 position = vWorldPosition + normal * (texel.x + texel.y * light_distance) * u_shadow_normal_bias;
+    vec4 projected = u_shadow_matrices[view] * vec4(position, 1.0);
+    vec3 coords = projected.xyz / projected.w;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    vec4 tile = u_shadow_tiles[view];
+    vec2 atlas_texel = 1.0 / vec2(textureSize(u_shadow_atlas, 0));
+    float lit = 0.0;
+    for (int x = -u_pcf_radius; x <= u_pcf_radius; x++) {
+        for (int y = -u_pcf_radius; y <= u_pcf_radius; y++) {
+            vec2 uv = clamp(coords.xy + vec2(x, y) * atlas_texel, tile.xy, tile.zw);
+            lit += coords.z - u_shadow_bias > texture(u_shadow_atlas, uv).r ? 0.0 : 1.0;
+        }
+    }
+    float size = float(2 * u_pcf_radius + 1);
+    return lit / (size * size);
+}
+
+// Color of each cascade in the debug view
+vec3 cascade_tint() {
+    const vec3 tints[MAX_CASCADES] = vec3[MAX_CASCADES](
+        vec3(1.0, 0.4, 0.4), vec3(0.4, 1.0, 0.4), vec3(0.4, 0.4, 1.0), vec3(1.0, 1.0, 0.4)
+    );
+    int cascade = cascade_index();
+    return cascade < 0 ? vec3(1.0) : tints[cascade];
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+float geometry_schlick_ggx(float n_dot_x, float roughness) {
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    vec4 base_sample = texture(u_base_color_map, vTexCoord);
+    vec4 base_color = u_base_color_factor * vec4(srgb_to_linear(base_sample.rgb), base_sample.a);
+    vec4 metallic_roughness = texture(u_metallic_roughness_map, vTexCoord);
+    float metallic = clamp(u_metallic_factor * metallic_roughness.b, 0.0, 1.0);
+    float roughness = clamp(u_roughness_factor * metallic_roughness.g, 0.04, 1.0);
+
+    vec3 tangent_normal = texture(u_normal_map, vTexCoord).xyz * 2.0 - 1.0;
+    tangent_normal.xy *= u_normal_scale;
+    vec3 normal = normalize(vTBN * tangent_normal);
+    vec3 view = normalize(u_camera_position - vWorldPosition);
+    float n_dot_v = max(dot(normal, view), 0.0001);
+    vec3 surface_normal = normalize(vTBN[2]);
+    vec3 f0 = mix(vec3(0.04), base_color.rgb, metallic);
+
+    vec3 direct = vec3(0.0);
+    for (int i = 0; i < u_light_count && i < MAX_LIGHTS; i++) {
+        vec3 to_light;
+        float strength = 1.0;
+        if (u_light_type[i] == LIGHT_DIRECTIONAL) {
+            to_light = -u_light_direction[i];
+        } else {
+            vec3 offset = u_light_position[i] - vWorldPosition;
+            float distance = length(offset);
+            to_light = offset / max(distance, 1e-4);
+            strength = attenuation(distance, u_light_range[i]);
+            if (u_light_type[i] == LIGHT_SPOT) {
+                float cos_angle = dot(-to_light, u_light_direction[i]);
+                strength *= smoothstep(u_light_cone[i].y, u_light_cone[i].x, cos_angle);
+            }
+        }
+
+        float n_dot_l = max(dot(normal, to_light), 0.0);
+        if (n_dot_l <= 0.0 || strength <= 0.0) {
+            continue;
+        }
+        strength *= shadow_factor(i, surface_normal);
+        vec3 halfway = normalize(to_light + view);
+        float d = distribution_ggx(max(dot(normal, halfway), 0.0), roughness);
+        float g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+        vec3 f = fresnel_schlick(max(dot(halfway, view), 0.0), f0);
+        vec3 specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        vec3 diffuse = (1.0 - f) * (1.0 - metallic) * base_color.rgb / PI;
+        direct += (diffuse + specular) * u_light_color[i] * strength * n_dot_l;
+    }
+
+    vec3 ambient = u_ambient * base_color.rgb;
+    if (u_ibl_intensity > 0.0) {
+        vec3 f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+        vec3 diffuse = texture(u_irradiance_map, normal).rgb * base_color.rgb * (1.0 - f) * (1.0 - metallic);
+        vec3 reflected = reflect(-view, normal);
+        vec3 prefiltered = textureLod(u_prefiltered_map, reflected, roughness * (u_prefilter_mips - 1.0)).rgb;
+        vec2 brdf = texture(u_brdf_lut, vec2(n_dot_v, roughness)).rg;
+        vec3 specular = prefiltered * (f * brdf.x + brdf.y);
+        ambient += (diffuse + specular) * u_ibl_intensity;
+    }
+    float occlusion = mix(1.0, texture(u_occlusion_map, vTexCoord).r, u_occlusion_strength);
+    vec3 emissive = srgb_to_linear(texture(u_emissive_map, vTexCoord).rgb) * u_emissive_factor;
+
+    vec3 color = ambient * occlusion + direct + emissive;
+    if (u_shadow_show_cascades != 0) {
+        color *= cascade_tint();
+    }
+    // Exposure tone mapping, then back to sRGB
+    color = vec3(1.0) - exp(-color * u_exposure);
+    Color = vec4(pow(color, vec3(1.0 / 2.2)), base_color.a);
+}
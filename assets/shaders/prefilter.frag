@@ -0,0 +1,73 @@
+#version 330 core
+
+in vec3 vDirection;
+
+out vec4 Color;
+
+uniform samplerCube u_environment;
+uniform float u_roughness;
+// Face size of the environment map
+uniform float u_resolution;
+
+const float PI = 3.14159265359;
+const uint SAMPLE_COUNT = 1024u;
+
+float radical_inverse(uint bits) {
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint count) {
+    return vec2(float(i) / float(count), radical_inverse(i));
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 halfway = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+// Radiance seen in a mirror direction for `u_roughness`, the view is assumed to be the normal
+void main() {
+    vec3 normal = normalize(vDirection);
+    vec3 view = normal;
+
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 halfway = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, u_roughness);
+        vec3 light = normalize(2.0 * dot(view, halfway) * halfway - view);
+        float n_dot_l = max(dot(normal, light), 0.0);
+        if (n_dot_l > 0.0) {
+            // Sample a blurrier mip where samples are sparse to avoid bright dots
+            float n_dot_h = max(dot(normal, halfway), 0.0);
+            float h_dot_v = max(dot(halfway, view), 0.0);
+            float pdf = distribution_ggx(n_dot_h, u_roughness) * n_dot_h / (4.0 * h_dot_v) + 0.0001;
+            float texel_angle = 4.0 * PI / (6.0 * u_resolution * u_resolution);
+            float sample_angle = 1.0 / (float(SAMPLE_COUNT) * pdf + 0.0001);
+            float level = u_roughness == 0.0 ? 0.0 : 0.5 * log2(sample_angle / texel_angle);
+
+            color += textureLod(u_environment, light, level).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+    Color = vec4(color / max(total_weight, 0.0001), 1.0);
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPosition;
+// Model matrix rows, one matrix per instance
+layout (location = 4) in vec4 aModel0;
+layout (location = 5) in vec4 aModel1;
+layout (location = 6) in vec4 aModel2;
+layout (location = 7) in vec4 aModel3;
+
+// Light view projection of the shadow view being rendered
+uniform mat4 u_view_projection;
+
+void main() {
+    mat4 model = transpose(mat4(aModel0, aModel1, aModel2, aModel3));
+    gl_Position = u_view_projection * model * vec4(aPosition, 1.0);
+}
@@ -0,0 +1,34 @@
+#version 330 core
+
+in vec2 vTexCoord;
+in vec4 vColor;
+flat in int vTexIndex;
+
+out vec4 Color;
+
+// Must match MAX_TEXTURE_SLOTS in sprite_batch.rs
+uniform sampler2D u_textures[16];
+
+void main() {
+    // Sampler arrays can only be indexed with constant expressions in 330
+    switch (vTexIndex) {
+        case 0: Color = texture(u_textures[0], vTexCoord); break;
+        case 1: Color = texture(u_textures[1], vTexCoord); break;
+        case 2: Color = texture(u_textures[2], vTexCoord); break;
+        case 3: Color = texture(u_textures[3], vTexCoord); break;
+        case 4: Color = texture(u_textures[4], vTexCoord); break;
+        case 5: Color = texture(u_textures[5], vTexCoord); break;
+        case 6: Color = texture(u_textures[6], vTexCoord); break;
+        case 7: Color = texture(u_textures[7], vTexCoord); break;
+        case 8: Color = texture(u_textures[8], vTexCoord); break;
+        case 9: Color = texture(u_textures[9], vTexCoord); break;
+        case 10: Color = texture(u_textures[10], vTexCoord); break;
+        case 11: Color = texture(u_textures[11], vTexCoord); break;
+        case 12: Color = texture(u_textures[12], vTexCoord); break;
+        case 13: Color = texture(u_textures[13], vTexCoord); break;
+        case 14: Color = texture(u_textures[14], vTexCoord); break;
+        case 15: Color = texture(u_textures[15], vTexCoord); break;
+        default: Color = vec4(1.0, 0.0, 1.0, 1.0); break;
+    }
+    Color *= vColor;
+}
@@ -0,0 +1,19 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPosition;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec4 aColor;
+layout (location = 3) in float aTexIndex;
+
+uniform mat4 u_projection;
+
+out vec2 vTexCoord;
+out vec4 vColor;
+flat out int vTexIndex;
+
+void main() {
+    gl_Position = u_projection * vec4(aPosition, 1.0);
+    vTexCoord = aTexCoord;
+    vColor = aColor;
+    vTexIndex = int(aTexIndex + 0.5);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+in vec2 vTexCoord;
+in vec4 vColor;
+
+out vec4 Color;
+
+uniform sampler2D u_atlas;
+// 1 when the atlas holds signed distance fields instead of coverage
+uniform int u_sdf;
+
+void main() {
+    float alpha = texture(u_atlas, vTexCoord).a;
+    if (u_sdf == 1) {
+        // The outline sits at 0.5, smooth over about one screen pixel
+        float width = fwidth(alpha);
+        alpha = smoothstep(0.5 - width, 0.5 + width, alpha);
+    }
+    Color = vec4(vColor.rgb, vColor.a * alpha);
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPosition;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec4 aColor;
+
+uniform mat4 u_projection;
+
+out vec2 vTexCoord;
+out vec4 vColor;
+
+void main() {
+    gl_Position = u_projection * vec4(aPosition, 1.0);
+    vTexCoord = aTexCoord;
+    vColor = aColor;
+}
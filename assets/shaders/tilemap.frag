@@ -0,0 +1,13 @@
+#version 330 core
+
+in vec2 vTexCoord;
+
+out vec4 Color;
+
+uniform sampler2D u_tileset;
+uniform float u_opacity;
+
+void main() {
+    Color = texture(u_tileset, vTexCoord);
+    Color.a *= u_opacity;
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+layout (location = 0) in vec2 aPosition;
+layout (location = 1) in vec2 aTexCoord;
+
+uniform mat4 u_view_projection;
+
+out vec2 vTexCoord;
+
+void main() {
+    gl_Position = u_view_projection * vec4(aPosition, 0.0, 1.0);
+    vTexCoord = aTexCoord;
+}
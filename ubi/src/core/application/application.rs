@@ -1,6 +1,11 @@
 use crate::core::custom_error::UbiError;
 use crate::core::logger::init;
+use crate::core::time::Time;
+use crate::event::bus::EventBus;
 use crate::event::event::{Event, EventDispatcher};
+use crate::event::event_data::{WindowCloseEventData, WindowResizeEventData};
+use crate::event::input::Input;
+use crate::event::record::{Recording, Replay};
 use crate::graphics::render::Renderer;
 use crate::layer::{Layer, LayerStack};
 use crate::ubiinfo;
@@ -12,6 +17,13 @@ pub struct Application<W: UBIWindow> {
     running: bool,
     layer_stack: LayerStack,
     renderer: Renderer,
+    input: Input,
+    bus: EventBus,
+    time: Time,
+    // Recorded events and deltas played instead of the window ones
+    replay: Option<Replay>,
+    // Fed with the window events during a replay, back in use when it ends
+    live_input: Option<Input>,
 }
 
 // Specific SDL2 window
@@ -25,6 +37,11 @@ impl Application<SdlWindow> {
             running: false,
             layer_stack: LayerStack::new(),
             renderer: renderer,
+            input: Input::new(),
+            bus: EventBus::new(),
+            time: Time::new(),
+            replay: None,
+            live_input: None,
         }
     }
 }
@@ -38,6 +55,11 @@ impl<W: UBIWindow> Application<W> {
             running: false,
             layer_stack: LayerStack::new(),
             renderer: renderer,
+            input: Input::new(),
+            bus: EventBus::new(),
+            time: Time::new(),
+            replay: None,
+            live_input: None,
         }
     }
 
@@ -48,16 +70,66 @@ impl<W: UBIWindow> Application<W> {
             // clear events and screen
             events.clear();
             self.window.clear();
+            // Typed events of two frames ago are dropped
+            self.bus.update();
+
+            // A replay gives the delta and the events of the frame
+            let replay_frame = self.replay.as_mut().and_then(|replay| replay.next_frame());
+            if self.replay.is_some() && replay_frame.is_none() {
+                ubiinfo!("Replay finished");
+                self.replay = None;
+                if let Some(live_input) = self.live_input.take() {
+                    self.input = live_input;
+                }
+            }
+            match &replay_frame {
+                Some(frame) => self.time.advance(frame.delta()),
+                None => self.time.tick(),
+            }
+
+            // Queued draws first, overlays painting in on_update stay on top
+            for layer in self.layer_stack.iter_mut() {
+                layer.on_render(self.renderer.queue_mut());
+            }
+            self.renderer.execute_queue();
 
             // Forward update layer stack
             for layer in self.layer_stack.iter_mut() {
+                layer.on_time(&self.time);
+                layer.on_input(&self.input);
                 layer.on_update(&mut events);
+                layer.on_event_bus(&mut self.bus);
             }
 
+            self.input.begin_frame();
+            if let Some(live_input) = &mut self.live_input {
+                live_input.begin_frame();
+            }
+            let posted = events.len();
             match self.window.poll_events(&mut events) {
                 Ok(_) => {
+                    if let Some(frame) = &replay_frame {
+                        // Only the recorded events reach the layers and `Input`. The window
+                        // can still be closed and its size still sets the viewport.
+                        let polled = events.split_off(posted);
+                        events.extend(frame.events());
+                        for event in polled {
+                            if let Some(live_input) = &mut self.live_input {
+                                live_input.handle_event(&event);
+                            }
+                            match event {
+                                Event::WindowClose(_) => events.push(event),
+                                Event::WindowResize(data) => {
+                                    self.window.resize(data.get_width(), data.get_height())
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+
                     for event in &mut events {
                         self.on_event(event);
+                        self.input.handle_event(event);
                     }
                 }
                 Err(e) => {
@@ -71,6 +143,57 @@ impl<W: UBIWindow> Application<W> {
         Ok(())
     }
 
+    // Keyboard, mouse and gamepad state after the events of the last frame
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    pub fn time(&self) -> &Time {
+        &self.time
+    }
+
+    // Same delta every frame, e.g. 1.0 / 60.0, or measured with `None`
+    pub fn set_fixed_delta(&mut self, delta: Option<f32>) {
+        self.time.set_fixed_delta(delta);
+    }
+
+    // Play a recording from the next frame instead of the window events, with the
+    // recorded deltas. The window events come back when it ends, with what they changed
+    // in the meantime
+    pub fn replay(&mut self, recording: Recording) {
+        ubiinfo!(
+            "Replaying {} frames, {:.2}s",
+            recording.len(),
+            recording.duration()
+        );
+        // Nothing held and no gamepad from before the replay, the recording connects its own
+        let live_input = std::mem::take(&mut self.input);
+        self.live_input.get_or_insert(live_input);
+        self.replay = Some(Replay::new(recording));
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    // Typed events shared by the layers
+    pub fn event_bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    pub fn event_bus_mut(&mut self) -> &mut EventBus {
+        &mut self.bus
+    }
+
+    pub fn window(&self) -> &W {
+        &self.window
+    }
+
+    // For the gamepad rumble and mappings
+    pub fn window_mut(&mut self) -> &mut W {
+        &mut self.window
+    }
+
     pub fn push_layer(&mut self, mut layer: Box<dyn Layer>) {
         layer.on_attach();
         self.layer_stack.push_layer(layer);
@@ -81,6 +204,7 @@ impl<W: UBIWindow> Application<W> {
         self.layer_stack.push_overlay(layer);
     }
 
+    // Custom events posted by the layers in `on_update` come first, in the order they were pushed
     pub fn on_event(&mut self, event: &mut Event) {
         // Backward layer event handling
         for layer in self.layer_stack.iter_mut().rev() {
@@ -91,28 +215,23 @@ impl<W: UBIWindow> Application<W> {
             }
         }
 
-        if !event.handled() {
-            let mut dispatcher = EventDispatcher::new();
-            match event {
-                Event::WindowClose(_) => {
-                    dispatcher.dispatch(event, |e| {
-                        ubiinfo!("{}", e);
-                        self.running = false;
-                        true
-                    });
-                }
-                Event::WindowResize(_) => {
-                    dispatcher.dispatch(event, |e| {
-                        ubiinfo!("{}", e);
-                        if let Event::WindowResize(resize_data) = e {
-                            self.window
-                                .resize(resize_data.get_width(), resize_data.get_height());
-                        }
-                        true
-                    });
-                }
-                _ => {}
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.on::<WindowCloseEventData>(event, |_| {
+            ubiinfo!("Window closed");
+            self.running = false;
+            true
+        });
+        dispatcher.on::<WindowResizeEventData>(event, |data| {
+            ubiinfo!(
+                "Window resized to {}x{}",
+                data.get_width(),
+                data.get_height()
+            );
+            // A recorded size is not the one of the window, only the layers get it
+            if self.replay.is_none() {
+                self.window.resize(data.get_width(), data.get_height());
             }
-        }
+            true
+        });
     }
 }
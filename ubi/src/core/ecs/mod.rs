@@ -0,0 +1,4 @@
+pub mod entity;
+pub mod world;
+
+pub mod transform;
\ No newline at end of file
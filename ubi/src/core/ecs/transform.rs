@@ -0,0 +1,100 @@
+use crate::core::math::transform::Mat4;
+
+use super::entity::Entity;
+use super::world::World;
+
+// Local position, rotation and scale of an entity, relative to its `Parent` if it has one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    // Euler angles in radians, applied around x, then y, then z
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+impl Transform {
+    pub fn from_translation(x: f32, y: f32, z: f32) -> Self {
+        Self {
+            translation: [x, y, z],
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rotation(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.rotation = [x, y, z];
+        self
+    }
+
+    pub fn with_scale(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.scale = [x, y, z];
+        self
+    }
+
+    // Scale first, then rotate, then translate
+    pub fn matrix(&self) -> Mat4 {
+        let mut matrix = Mat4::new();
+        matrix.scale(self.scale[0], self.scale[1], self.scale[2]);
+        matrix.rotate_x(self.rotation[0]);
+        matrix.rotate_y(self.rotation[1]);
+        matrix.rotate_z(self.rotation[2]);
+        matrix.translate(
+            self.translation[0],
+            self.translation[1],
+            self.translation[2],
+        );
+        matrix
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+        }
+    }
+}
+
+// Entity whose transform the local `Transform` is relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+// World matrix of an entity, written by `propagate_transforms` and read by the renderers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub Mat4);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self(Mat4::new())
+    }
+}
+
+// Recompute the `GlobalTransform` of every entity with a `Transform`, following `Parent` links.
+// Call it once per frame after gameplay moved things and before rendering.
+pub fn propagate_transforms(world: &mut World) {
+    let globals: Vec<(Entity, Mat4)> = world
+        .query::<Transform>()
+        .map(|(entity, transform)| {
+            let mut matrix = transform.matrix();
+            let mut current = entity;
+            // Bounded walk so a parent cycle can not hang the frame
+            for _ in 0..MAX_HIERARCHY_DEPTH {
+                let Some(Parent(parent)) = world.get::<Parent>(current).copied() else {
+                    break;
+                };
+                if let Some(parent_transform) = world.get::<Transform>(parent) {
+                    matrix.mult(parent_transform.matrix());
+                }
+                current = parent;
+            }
+            (entity, matrix)
+        })
+        .collect();
+
+    for (entity, matrix) in globals {
+        world.insert(entity, GlobalTransform(matrix));
+    }
+}
+
+const MAX_HIERARCHY_DEPTH: usize = 64;
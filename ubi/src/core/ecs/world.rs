@@ -0,0 +1,227 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use super::entity::{Entity, EntityAllocator};
+
+// Type erased access to a component storage
+trait AnyStorage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+// Components packed in a dense array, `sparse` maps entity indices into it
+struct Storage<T> {
+    dense: Vec<T>,
+    owners: Vec<Entity>,
+    sparse: Vec<Option<usize>>,
+}
+
+impl<T> Storage<T> {
+    fn new() -> Self {
+        Self {
+            dense: Vec::new(),
+            owners: Vec::new(),
+            sparse: Vec::new(),
+        }
+    }
+
+    fn slot(&self, entity: Entity) -> Option<usize> {
+        let slot = (*self.sparse.get(entity.index() as usize)?)?;
+        (self.owners[slot] == entity).then_some(slot)
+    }
+
+    fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(slot) = self.slot(entity) {
+            return Some(std::mem::replace(&mut self.dense[slot], component));
+        }
+        let index = entity.index() as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.dense.len());
+        self.dense.push(component);
+        self.owners.push(entity);
+        None
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slot(entity)?;
+        self.sparse[entity.index() as usize] = None;
+        let component = self.dense.swap_remove(slot);
+        self.owners.swap_remove(slot);
+        if let Some(moved) = self.owners.get(slot) {
+            self.sparse[moved.index() as usize] = Some(slot);
+        }
+        Some(component)
+    }
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// Entities, their components and global resources
+#[derive(Default)]
+pub struct World {
+    entities: EntityAllocator,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    resources: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // let entity = world.spawn().with(Position(0.0)).with(Velocity(1.0)).build();
+    pub fn spawn(&mut self) -> EntityBuilder<'_> {
+        let entity = self.entities.allocate();
+        EntityBuilder {
+            world: self,
+            entity,
+        }
+    }
+
+    // Remove the entity with all its components, false if it was already gone
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter()
+    }
+
+    // Add or replace a component, returns the replaced one
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut_or_default::<T>().insert(entity, component)
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storage_mut::<T>()?.remove(entity)
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        let storage = self.storage::<T>()?;
+        storage.slot(entity).map(|slot| &storage.dense[slot])
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        let storage = self.storage_mut::<T>()?;
+        let slot = storage.slot(entity)?;
+        Some(&mut storage.dense[slot])
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    // Number of entities with a `T`
+    pub fn count<T: 'static>(&self) -> usize {
+        self.storage::<T>().map(|s| s.dense.len()).unwrap_or(0)
+    }
+
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.storage::<T>()
+            .into_iter()
+            .flat_map(|storage| storage.owners.iter().copied().zip(storage.dense.iter()))
+    }
+
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.storage_mut::<T>()
+            .into_iter()
+            .flat_map(|storage| storage.owners.iter().copied().zip(storage.dense.iter_mut()))
+    }
+
+    // Entities that have both an `A` and a `B`
+    pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        self.query::<A>()
+            .filter_map(|(entity, a)| self.get::<B>(entity).map(|b| (entity, a, b)))
+    }
+
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) -> Option<T> {
+        self.resources
+            .insert(TypeId::of::<T>(), Box::new(resource))
+            .and_then(|old| old.downcast::<T>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources
+            .remove(&TypeId::of::<T>())
+            .and_then(|old| old.downcast::<T>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.resources.get(&TypeId::of::<T>())?.downcast_ref::<T>()
+    }
+
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut::<T>()
+    }
+
+    fn storage<T: 'static>(&self) -> Option<&Storage<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<Storage<T>>()
+    }
+
+    fn storage_mut<T: 'static>(&mut self) -> Option<&mut Storage<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+    }
+
+    fn storage_mut_or_default<T: 'static>(&mut self) -> &mut Storage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+            .expect("component storage registered under the wrong type")
+    }
+}
+
+pub struct EntityBuilder<'a> {
+    world: &'a mut World,
+    entity: Entity,
+}
+
+impl EntityBuilder<'_> {
+    pub fn with<T: 'static>(self, component: T) -> Self {
+        self.world.insert(self.entity, component);
+        self
+    }
+
+    pub fn build(self) -> Entity {
+        self.entity
+    }
+}
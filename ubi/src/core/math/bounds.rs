@@ -0,0 +1,310 @@
+use super::transform::Mat4;
+use super::vector::{add, distance, dot, scale, sub};
+
+// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    pub fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        Self { min, max }
+    }
+
+    // Smallest box holding every point, None without points
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, point| {
+            aabb.union(&Self::new(point, point))
+        }))
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        scale(add(self.min, self.max), 0.5)
+    }
+
+    // Half of the size along each axis
+    pub fn extents(&self) -> [f32; 3] {
+        scale(sub(self.max, self.min), 0.5)
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: [0, 1, 2].map(|i| self.min[i].min(other.min[i])),
+            max: [0, 1, 2].map(|i| self.max[i].max(other.max[i])),
+        }
+    }
+
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|i| point[i] >= self.min[i] && point[i] <= self.max[i])
+    }
+
+    // Box holding the transformed box, larger than it under rotation
+    pub fn transform(&self, matrix: &Mat4) -> Aabb {
+        let m = matrix.as_array();
+        let center = matrix.transform_point(self.center());
+        let extents = self.extents();
+        let extents = [0, 1, 2].map(|row| {
+            (0..3)
+                .map(|column| m[row * 4 + column].abs() * extents[column])
+                .sum::<f32>()
+        });
+        Aabb {
+            min: sub(center, extents),
+            max: add(center, extents),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: [f32; 3],
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: [f32; 3], radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    // Sphere around the center of the points' box, None without points
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]> + Clone) -> Option<Self> {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .into_iter()
+            .map(|point| distance(point, center))
+            .fold(0.0, f32::max);
+        Some(Self { center, radius })
+    }
+
+    // The radius grows with the largest axis scale of `matrix`
+    pub fn transform(&self, matrix: &Mat4) -> BoundingSphere {
+        let m = matrix.as_array();
+        let scale = (0..3)
+            .map(|column| {
+                let axis = [m[column], m[4 + column], m[8 + column]];
+                dot(axis, axis)
+            })
+            .fold(0.0, f32::max)
+            .sqrt();
+        BoundingSphere {
+            center: matrix.transform_point(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+// Planes bounding what a view projection matrix sees, normals pointing inside
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    // a, b, c, d with a*x + b*y + c*z + d >= 0 inside: left, right, bottom, top, near, far
+    planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+    // Planes of the clip volume of `view_projection`, in world space
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let m = view_projection.as_array();
+        let row = |i: usize| [m[i * 4], m[i * 4 + 1], m[i * 4 + 2], m[i * 4 + 3]];
+        let w = row(3);
+        let plane = |r: [f32; 4], sign: f32| {
+            let plane = [0, 1, 2, 3].map(|i| w[i] + sign * r[i]);
+            let length = dot(
+                [plane[0], plane[1], plane[2]],
+                [plane[0], plane[1], plane[2]],
+            )
+            .sqrt();
+            if length > f32::EPSILON {
+                plane.map(|v| v / length)
+            } else {
+                plane
+            }
+        };
+        Self {
+            planes: [
+                plane(row(0), 1.0),
+                plane(row(0), -1.0),
+                plane(row(1), 1.0),
+                plane(row(1), -1.0),
+                plane(row(2), 1.0),
+                plane(row(2), -1.0),
+            ],
+        }
+    }
+
+    pub fn planes(&self) -> &[[f32; 4]; 6] {
+        &self.planes
+    }
+
+    pub fn contains_point(&self, point: [f32; 3]) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| signed_distance(plane, point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| signed_distance(plane, sphere.center) >= -sphere.radius)
+    }
+
+    // Conservative: boxes near the frustum corners may pass while outside
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner furthest along the plane normal
+            let corner = [0, 1, 2].map(|i| {
+                if plane[i] >= 0.0 {
+                    aabb.max[i]
+                } else {
+                    aabb.min[i]
+                }
+            });
+            signed_distance(plane, corner) >= 0.0
+        })
+    }
+}
+
+fn signed_distance(plane: &[f32; 4], point: [f32; 3]) -> f32 {
+    dot([plane[0], plane[1], plane[2]], point) + plane[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: [f32; 3], b: [f32; 3]) {
+        assert!(
+            (0..3).all(|i| (a[i] - b[i]).abs() < 1e-4),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    // Camera at the origin looking down -z, 90 degrees wide, from 1 to 100
+    fn perspective() -> Frustum {
+        let mut projection = Mat4::new();
+        projection.perspective(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 100.0);
+        Frustum::from_matrix(&projection)
+    }
+
+    #[test]
+    fn aabb_from_points_holds_every_point() {
+        let points = [[1.0, -2.0, 3.0], [-1.0, 4.0, 0.0], [0.5, 0.0, -3.0]];
+        let aabb = Aabb::from_points(points).unwrap();
+        assert_eq!(aabb, Aabb::new([-1.0, -2.0, -3.0], [1.0, 4.0, 3.0]));
+        assert!(points.iter().all(|point| aabb.contains(*point)));
+        assert!(!aabb.contains([0.0, 5.0, 0.0]));
+        assert_eq!(Aabb::from_points([]), None);
+    }
+
+    #[test]
+    fn aabb_center_extents_and_union() {
+        let a = Aabb::new([0.0, 0.0, 0.0], [2.0, 4.0, 6.0]);
+        let b = Aabb::new([-2.0, 1.0, 1.0], [1.0, 2.0, 8.0]);
+        assert_eq!(a.center(), [1.0, 2.0, 3.0]);
+        assert_eq!(a.extents(), [1.0, 2.0, 3.0]);
+        assert_eq!(a.union(&b), Aabb::new([-2.0, 0.0, 0.0], [2.0, 4.0, 8.0]));
+    }
+
+    #[test]
+    fn aabb_transform_translates_and_grows_under_rotation() {
+        let aabb = Aabb::new([-1.0, -2.0, -3.0], [1.0, 2.0, 3.0]);
+        let mut matrix = Mat4::new();
+        matrix.translate(10.0, 0.0, 0.0);
+        let moved = aabb.transform(&matrix);
+        assert_near(moved.min, [9.0, -2.0, -3.0]);
+        assert_near(moved.max, [11.0, 2.0, 3.0]);
+
+        let mut matrix = Mat4::new();
+        matrix.rotate_z(std::f32::consts::FRAC_PI_2);
+        let rotated = aabb.transform(&matrix);
+        assert_near(rotated.min, [-2.0, -1.0, -3.0]);
+        assert_near(rotated.max, [2.0, 1.0, 3.0]);
+    }
+
+    #[test]
+    fn sphere_from_points_reaches_the_furthest_point() {
+        let sphere =
+            BoundingSphere::from_points([[-1.0, 0.0, 0.0], [3.0, 0.0, 0.0], [1.0, 1.0, 0.0]])
+                .unwrap();
+        assert_near(sphere.center, [1.0, 0.5, 0.0]);
+        assert!((sphere.radius - 4.25f32.sqrt()).abs() < 1e-4);
+        assert_eq!(BoundingSphere::from_points(Vec::new()), None);
+    }
+
+    #[test]
+    fn sphere_transform_uses_the_largest_scale() {
+        let sphere = BoundingSphere::new([1.0, 0.0, 0.0], 2.0);
+        let mut matrix = Mat4::new();
+        matrix.scale(1.0, 3.0, 2.0);
+        matrix.translate(0.0, 5.0, 0.0);
+        let transformed = sphere.transform(&matrix);
+        assert_near(transformed.center, [1.0, 5.0, 0.0]);
+        assert!((transformed.radius - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn frustum_planes_are_normalized() {
+        for plane in perspective().planes() {
+            let length = dot(
+                [plane[0], plane[1], plane[2]],
+                [plane[0], plane[1], plane[2]],
+            );
+            assert!((length - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn frustum_contains_points_between_near_and_far() {
+        let frustum = perspective();
+        assert!(frustum.contains_point([0.0, 0.0, -10.0]));
+        assert!(frustum.contains_point([9.0, -9.0, -10.0]));
+        // Behind the camera, before the near plane, past the far plane and to the right
+        assert!(!frustum.contains_point([0.0, 0.0, 10.0]));
+        assert!(!frustum.contains_point([0.0, 0.0, -0.5]));
+        assert!(!frustum.contains_point([0.0, 0.0, -200.0]));
+        assert!(!frustum.contains_point([11.0, 0.0, -10.0]));
+    }
+
+    #[test]
+    fn frustum_culls_spheres_by_their_radius() {
+        let frustum = perspective();
+        // 2 / sqrt(2) away from the right plane
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new([12.0, 0.0, -10.0], 1.0)));
+        assert!(frustum.intersects_sphere(&BoundingSphere::new([12.0, 0.0, -10.0], 2.0)));
+        assert!(frustum.intersects_sphere(&BoundingSphere::new([0.0, 0.0, -0.5], 1.0)));
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new([0.0, 0.0, 5.0], 1.0)));
+    }
+
+    #[test]
+    fn frustum_culls_boxes_outside_one_plane() {
+        let frustum = perspective();
+        assert!(frustum.intersects_aabb(&Aabb::new([-1.0, -1.0, -11.0], [1.0, 1.0, -9.0])));
+        // Partly inside
+        assert!(frustum.intersects_aabb(&Aabb::new([9.0, -1.0, -11.0], [11.0, 1.0, -9.0])));
+        // Containing the whole frustum
+        assert!(frustum.intersects_aabb(&Aabb::new([-500.0; 3], [500.0; 3])));
+        assert!(!frustum.intersects_aabb(&Aabb::new([11.0, -1.0, -10.0], [12.0, 1.0, -10.0])));
+        assert!(!frustum.intersects_aabb(&Aabb::new([-1.0, -1.0, 1.0], [1.0, 1.0, 2.0])));
+        assert!(!frustum.intersects_aabb(&Aabb::new([-1.0, -1.0, -300.0], [1.0, 1.0, -200.0])));
+    }
+
+    #[test]
+    fn frustum_follows_the_view() {
+        let mut view_projection = Mat4::new();
+        view_projection.lookat(0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0);
+        let mut projection = Mat4::new();
+        projection.orthographic(-5.0, 5.0, -5.0, 5.0, 1.0, 50.0);
+        view_projection.mult(projection);
+        let frustum = Frustum::from_matrix(&view_projection);
+        // Looking down +x now
+        assert!(frustum.contains_point([10.0, 4.0, 4.0]));
+        assert!(!frustum.contains_point([-10.0, 0.0, 0.0]));
+        assert!(!frustum.contains_point([10.0, 6.0, 0.0]));
+        assert!(!frustum.contains_point([60.0, 0.0, 0.0]));
+    }
+}
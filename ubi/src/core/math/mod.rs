@@ -1 +1,3 @@
+pub mod bounds;
 pub mod transform;
+pub mod vector;
@@ -1,4 +1,5 @@
 // A 3*3 matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
 pub struct Mat3([f32; 9]);
 
@@ -55,6 +56,7 @@ impl Mat3 {
 }
 
 // A 4*4 matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
 pub struct Mat4([f32; 16]);
 
@@ -66,11 +68,21 @@ impl Mat4 {
         ])
     }
 
+    // build a matrix from its 16 values, row by row
+    pub fn from_rows(values: [f32; 16]) -> Self {
+        Mat4(values)
+    }
+
     // returns a pointer to the matrix, readable by OpenGL
     pub fn ptr(&self) -> *const f32 {
         self.0.as_ptr()
     }
 
+    // the 16 values of the matrix, row by row
+    pub fn as_array(&self) -> &[f32; 16] {
+        &self.0
+    }
+
     pub fn mult(&mut self, mat: Mat4) {
         *self = Mat4([
             mat.0[0] * self.0[0]
@@ -218,6 +230,132 @@ impl Mat4 {
         ]));
     }
 
+    /// Orthographic projection mapping the given box to clip space.
+    /// Replaces any earlier transformation to this Mat4.
+    pub fn orthographic(
+        &mut self,
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32,
+    ) {
+        *self = Mat4([
+            2.0 / (right - left),
+            0.0,
+            0.0,
+            -(right + left) / (right - left),
+            0.0,
+            2.0 / (top - bottom),
+            0.0,
+            -(top + bottom) / (top - bottom),
+            0.0,
+            0.0,
+            -2.0 / (far - near),
+            -(far + near) / (far - near),
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        ]);
+    }
+
+    /// Perspective projection with a vertical field of view `fov_y` in radians.
+    /// Replaces any earlier transformation to this Mat4.
+    pub fn perspective(&mut self, fov_y: f32, aspect: f32, near: f32, far: f32) {
+        let f = 1.0 / (fov_y * 0.5).tan();
+        *self = Mat4([
+            f / aspect,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            f,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            (far + near) / (near - far),
+            2.0 * far * near / (near - far),
+            0.0,
+            0.0,
+            -1.0,
+            0.0,
+        ]);
+    }
+
+    // Multiply the point (x, y, z, 1) and divide by w
+    pub fn transform_point(&self, point: [f32; 3]) -> [f32; 3] {
+        let m = &self.0;
+        let [x, y, z] = point;
+        let w = m[12] * x + m[13] * y + m[14] * z + m[15];
+        let w = if w.abs() > f32::EPSILON { w } else { 1.0 };
+        [
+            (m[0] * x + m[1] * y + m[2] * z + m[3]) / w,
+            (m[4] * x + m[5] * y + m[6] * z + m[7]) / w,
+            (m[8] * x + m[9] * y + m[10] * z + m[11]) / w,
+        ]
+    }
+
+    // Multiply the direction (x, y, z, 0), translation is ignored
+    pub fn transform_vector(&self, vector: [f32; 3]) -> [f32; 3] {
+        let m = &self.0;
+        let [x, y, z] = vector;
+        [
+            m[0] * x + m[1] * y + m[2] * z,
+            m[4] * x + m[5] * y + m[6] * z,
+            m[8] * x + m[9] * y + m[10] * z,
+        ]
+    }
+
+    // The translation part of the matrix
+    pub fn translation(&self) -> [f32; 3] {
+        [self.0[3], self.0[7], self.0[11]]
+    }
+
+    // Inverse by cofactors, None when the matrix is singular
+    pub fn inverse(&self) -> Option<Mat4> {
+        let m = &self.0;
+        // 2x2 determinants of the top two rows and of the bottom two rows
+        let s0 = m[0] * m[5] - m[4] * m[1];
+        let s1 = m[0] * m[6] - m[4] * m[2];
+        let s2 = m[0] * m[7] - m[4] * m[3];
+        let s3 = m[1] * m[6] - m[5] * m[2];
+        let s4 = m[1] * m[7] - m[5] * m[3];
+        let s5 = m[2] * m[7] - m[6] * m[3];
+        let c5 = m[10] * m[15] - m[14] * m[11];
+        let c4 = m[9] * m[15] - m[13] * m[11];
+        let c3 = m[9] * m[14] - m[13] * m[10];
+        let c2 = m[8] * m[15] - m[12] * m[11];
+        let c1 = m[8] * m[14] - m[12] * m[10];
+        let c0 = m[8] * m[13] - m[12] * m[9];
+
+        let det = s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+        if det.abs() <= f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        let inv = 1.0 / det;
+        Some(Mat4([
+            (m[5] * c5 - m[6] * c4 + m[7] * c3) * inv,
+            (-m[1] * c5 + m[2] * c4 - m[3] * c3) * inv,
+            (m[13] * s5 - m[14] * s4 + m[15] * s3) * inv,
+            (-m[9] * s5 + m[10] * s4 - m[11] * s3) * inv,
+            (-m[4] * c5 + m[6] * c2 - m[7] * c1) * inv,
+            (m[0] * c5 - m[2] * c2 + m[3] * c1) * inv,
+            (-m[12] * s5 + m[14] * s2 - m[15] * s1) * inv,
+            (m[8] * s5 - m[10] * s2 + m[11] * s1) * inv,
+            (m[4] * c4 - m[5] * c2 + m[7] * c0) * inv,
+            (-m[0] * c4 + m[1] * c2 - m[3] * c0) * inv,
+            (m[12] * s4 - m[13] * s2 + m[15] * s0) * inv,
+            (-m[8] * s4 + m[9] * s2 - m[11] * s0) * inv,
+            (-m[4] * c3 + m[5] * c1 - m[6] * c0) * inv,
+            (m[0] * c3 - m[1] * c1 + m[2] * c0) * inv,
+            (-m[12] * s3 + m[13] * s1 - m[14] * s0) * inv,
+            (m[8] * s3 - m[9] * s1 + m[10] * s0) * inv,
+        ]))
+    }
+
     // Add a translation trnasformation
     pub fn translate(&mut self, x_move: f32, y_move: f32, z_move: f32) {
         self.mult(Mat4([
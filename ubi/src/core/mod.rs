@@ -3,3 +3,5 @@ pub mod custom_error;
 pub mod logger;
 pub mod math;
 pub mod ecs;
+
+pub mod time;
\ No newline at end of file
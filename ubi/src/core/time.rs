@@ -0,0 +1,66 @@
+// Frame clock of the application. The delta is measured between frames unless a fixed delta
+// is set, so a simulation steps the same whatever the frame rate
+use std::time::Instant;
+
+pub struct Time {
+    last: Instant,
+    delta: f32,
+    elapsed: f64,
+    frame: u64,
+    fixed_delta: Option<f32>,
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            delta: 0.0,
+            elapsed: 0.0,
+            frame: 0,
+            fixed_delta: None,
+        }
+    }
+
+    // Start of a frame, with the measured or fixed delta
+    pub fn tick(&mut self) {
+        let measured = self.last.elapsed().as_secs_f32();
+        self.advance(self.fixed_delta.unwrap_or(measured));
+    }
+
+    // Start of a frame with a given delta, e.g. the recorded one of a replay
+    pub fn advance(&mut self, delta: f32) {
+        self.last = Instant::now();
+        self.delta = delta;
+        self.elapsed += delta as f64;
+        self.frame += 1;
+    }
+
+    // Seconds since the last frame
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    // Seconds since the start, sum of the deltas
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    // Frames started so far, the current one included
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn fixed_delta(&self) -> Option<f32> {
+        self.fixed_delta
+    }
+
+    pub fn set_fixed_delta(&mut self, delta: Option<f32>) {
+        self.fixed_delta = delta;
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
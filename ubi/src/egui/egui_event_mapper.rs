@@ -1,3 +1,121 @@
 // This module will convert UbiEvents into egui ones to use in egui raw_input
+use crate::event::input::{KeyCode, Modifiers, MouseButton};
 use crate::prelude::Event as UbiEvent;
 use egui::Event as EguiEvent;
+use egui::{pos2, vec2, Key, MouseWheelUnit, PointerButton};
+
+// `pixels_per_point` turns window pixels into egui points. Mouse events carry no modifiers,
+// they get those of the last key event.
+pub fn to_egui(
+    event: &UbiEvent,
+    pixels_per_point: f32,
+    modifiers: egui::Modifiers,
+) -> Option<EguiEvent> {
+    let point = |x: f32, y: f32| pos2(x / pixels_per_point, y / pixels_per_point);
+    Some(match event {
+        UbiEvent::MouseMoved(data) => {
+            EguiEvent::PointerMoved(point(data.get_x_pos(), data.get_y_pos()))
+        }
+        UbiEvent::MouseButtonPressed(data) => {
+            let (x, y) = data.get_button_pos();
+            EguiEvent::PointerButton {
+                pos: point(x as f32, y as f32),
+                button: to_egui_button(data.get_button())?,
+                pressed: true,
+                modifiers,
+            }
+        }
+        UbiEvent::MouseButtonReleased(data) => {
+            let (x, y) = data.get_button_pos();
+            EguiEvent::PointerButton {
+                pos: point(x as f32, y as f32),
+                button: to_egui_button(data.get_button())?,
+                pressed: false,
+                modifiers,
+            }
+        }
+        // In lines, a positive y scrolls up so the content moves down as egui expects
+        UbiEvent::MouseScroll(data) => EguiEvent::MouseWheel {
+            unit: MouseWheelUnit::Line,
+            delta: vec2(data.get_x_offset(), data.get_y_offset()),
+            modifiers,
+        },
+        UbiEvent::MouseLeft(_) => EguiEvent::PointerGone,
+        UbiEvent::KeyPressed(data) => EguiEvent::Key {
+            key: to_egui_key(data.get_key_code())?,
+            physical_key: None,
+            pressed: true,
+            repeat: data.get_repeat_count() > 0,
+            modifiers: to_egui_modifiers(data.get_modifiers()),
+        },
+        UbiEvent::KeyReleased(data) => EguiEvent::Key {
+            key: to_egui_key(data.get_key_code())?,
+            physical_key: None,
+            pressed: false,
+            repeat: false,
+            modifiers: to_egui_modifiers(data.get_modifiers()),
+        },
+        // Control characters come as key events
+        UbiEvent::TextInput(data) if !data.get_text().chars().any(char::is_control) => {
+            EguiEvent::Text(data.get_text().to_string())
+        }
+        UbiEvent::WindowFocus(_) => EguiEvent::WindowFocused(true),
+        UbiEvent::WindowLostFocus(_) => EguiEvent::WindowFocused(false),
+        _ => return None,
+    })
+}
+
+// Command is Cmd on macOS and Ctrl elsewhere
+pub fn to_egui_modifiers(modifiers: Modifiers) -> egui::Modifiers {
+    let ctrl = modifiers.contains(Modifiers::CTRL);
+    let mac_cmd = cfg!(target_os = "macos") && modifiers.contains(Modifiers::SUPER);
+    egui::Modifiers {
+        alt: modifiers.contains(Modifiers::ALT),
+        ctrl,
+        shift: modifiers.contains(Modifiers::SHIFT),
+        mac_cmd,
+        command: if cfg!(target_os = "macos") {
+            mac_cmd
+        } else {
+            ctrl
+        },
+    }
+}
+
+pub fn to_egui_button(button: MouseButton) -> Option<PointerButton> {
+    Some(match button {
+        MouseButton::Left => PointerButton::Primary,
+        MouseButton::Right => PointerButton::Secondary,
+        MouseButton::Middle => PointerButton::Middle,
+        MouseButton::X1 => PointerButton::Extra1,
+        MouseButton::X2 => PointerButton::Extra2,
+        MouseButton::Unknown => return None,
+    })
+}
+
+// Keys egui has no name for, like the modifiers and the lock keys, are left out
+pub fn to_egui_key(key: KeyCode) -> Option<Key> {
+    Some(match key {
+        KeyCode::Num0 | KeyCode::Kp0 => Key::Num0,
+        KeyCode::Num1 | KeyCode::Kp1 => Key::Num1,
+        KeyCode::Num2 | KeyCode::Kp2 => Key::Num2,
+        KeyCode::Num3 | KeyCode::Kp3 => Key::Num3,
+        KeyCode::Num4 | KeyCode::Kp4 => Key::Num4,
+        KeyCode::Num5 | KeyCode::Kp5 => Key::Num5,
+        KeyCode::Num6 | KeyCode::Kp6 => Key::Num6,
+        KeyCode::Num7 | KeyCode::Kp7 => Key::Num7,
+        KeyCode::Num8 | KeyCode::Kp8 => Key::Num8,
+        KeyCode::Num9 | KeyCode::Kp9 => Key::Num9,
+        KeyCode::KpEnter => Key::Enter,
+        KeyCode::KpMinus => Key::Minus,
+        KeyCode::KpPlus => Key::Plus,
+        KeyCode::KpPeriod => Key::Period,
+        KeyCode::KpEquals => Key::Equals,
+        KeyCode::KpDivide => Key::Slash,
+        KeyCode::LeftBracket => Key::OpenBracket,
+        KeyCode::RightBracket => Key::CloseBracket,
+        KeyCode::Apostrophe => Key::Quote,
+        // Letters, F keys, arrows and the named keys egui spells the same way
+        _ => return Key::from_name(key.name()),
+    })
+}
@@ -1,12 +1,19 @@
-use crate::{event, ubiinfo};
-use crate::event::event::EventDispatcher;
+use super::egui_event_mapper;
+use crate::event::event::{EventCategory, EventDispatcher};
+use crate::event::event_data::{KeyPressedEventData, MouseButtonPressedEventData};
+use crate::event::input::{KeyCode, Modifiers};
+use crate::graphics::gl_state;
+use crate::graphics::shadow::ShadowDebug;
 use crate::layer::Layer;
 use crate::prelude::Event as UbiEvent;
-use egui::{Color32, Context as EguiContext, FullOutput, RawInput, TextureId};
+use crate::window::window_trait::Clipboard;
+use crate::{event, ubiinfo, ubiwarn};
+use egui::{Color32, Context as EguiContext, FullOutput, OutputCommand, RawInput, TextureId};
 use egui_sdl2_gl as egui_backend;
 use egui_sdl2_gl::painter::Painter;
 use egui_sdl2_gl::{DpiScaling, EguiStateHandler, ShaderVersion};
 use sdl2::video::Window;
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::Instant;
 
@@ -17,12 +24,19 @@ pub struct EguiLayer {
     pub painter: Painter,
     pub egui_state: EguiStateHandler,
     pub window: Rc<Window>,
+    shadow_debug: Option<Rc<RefCell<ShadowDebug>>>,
+    // Painter texture showing the shadow atlas, and whether it points at the atlas yet
+    shadow_texture: Option<(TextureId, bool)>,
+    // Copy, cut and paste go through it, from `UBIWindow::clipboard`
+    clipboard: Option<Rc<dyn Clipboard>>,
 }
 
 impl EguiLayer {
     pub fn new(window: Rc<Window>) -> Self {
         let (painter, egui_state) =
             egui_backend::with_sdl2(&window, ShaderVersion::Default, DpiScaling::Default);
+        // Creating the painter binds its own program, buffers and textures behind the cache
+        gl_state::invalidate();
 
         Self {
             ctx: EguiContext::default(),
@@ -31,8 +45,112 @@ impl EguiLayer {
             painter,
             egui_state: egui_state,
             window,
+            shadow_debug: None,
+            shadow_texture: None,
+            clipboard: None,
         }
     }
+
+    pub fn set_clipboard(&mut self, clipboard: Rc<dyn Clipboard>) {
+        self.clipboard = Some(clipboard);
+    }
+
+    // Show the cascades of a `ShadowPass`, from `ShadowPass::debug`
+    pub fn show_shadow_debug(&mut self, debug: Rc<RefCell<ShadowDebug>>) {
+        self.shadow_debug = Some(debug);
+    }
+
+    fn shadow_debug_window(&mut self) {
+        let debug = match &self.shadow_debug {
+            Some(debug) => debug.clone(),
+            None => return,
+        };
+        // The painter uploads its placeholder first, the atlas replaces it once that is done
+        let (texture, _) = *self.shadow_texture.get_or_insert_with(|| {
+            let id = self
+                .painter
+                .new_user_texture_rgba8((1, 1), vec![0, 0, 0, 255], false);
+            (id, false)
+        });
+
+        let mut debug = debug.borrow_mut();
+        egui::Window::new("Shadow cascades").show(&self.ctx, |ui| {
+            ui.checkbox(&mut debug.show_cascades, "Tint cascades");
+            ui.label(format!("Spot light views: {}", debug.spot_views));
+            if debug.cascades.is_empty() {
+                ui.label("No directional light casts shadows");
+                return;
+            }
+            let atlas_size = debug.atlas_size.max(1) as f32;
+            ui.horizontal(|ui| {
+                for (index, cascade) in debug.cascades.iter().enumerate() {
+                    ui.vertical(|ui| {
+                        ui.label(format!(
+                            "#{} {:.1} - {:.1} (r {:.1})",
+                            index, cascade.near, cascade.far, cascade.radius
+                        ));
+                        // GL rows go bottom up, egui ones top down
+                        let [x, y, width, height] = cascade.viewport.map(|v| v as f32);
+                        let uv = egui::Rect::from_min_max(
+                            egui::pos2(x / atlas_size, (y + height) / atlas_size),
+                            egui::pos2((x + width) / atlas_size, y / atlas_size),
+                        );
+                        ui.add(egui::Image::new((texture, egui::vec2(160.0, 160.0))).uv(uv));
+                    });
+                }
+            });
+        });
+    }
+
+    // Translate the event for the next egui frame. Like `Input`, presses a layer above
+    // consumed are not egui's but releases always are, so nothing stays held.
+    fn feed(&mut self, event: &UbiEvent) {
+        let release = matches!(
+            event,
+            UbiEvent::KeyReleased(_) | UbiEvent::MouseButtonReleased(_)
+        );
+        if event.handled() && !release {
+            return;
+        }
+        let modifiers = match event {
+            UbiEvent::KeyPressed(data) => Some(data.get_modifiers()),
+            UbiEvent::KeyReleased(data) => Some(data.get_modifiers()),
+            _ => None,
+        };
+        if let Some(modifiers) = modifiers {
+            self.egui_state.modifiers = egui_event_mapper::to_egui_modifiers(modifiers);
+            self.egui_state.input.modifiers = self.egui_state.modifiers;
+        }
+        let pixels_per_point = self.painter.pixels_per_point;
+        if let Some(egui_event) =
+            egui_event_mapper::to_egui(event, pixels_per_point, self.egui_state.modifiers)
+        {
+            if let egui::Event::PointerMoved(pos) = egui_event {
+                self.egui_state.pointer_pos = pos;
+            }
+            self.egui_state.input.events.push(egui_event);
+        }
+    }
+
+    fn update_shadow_texture(&mut self) {
+        let (Some(debug), Some((texture, ready))) = (&self.shadow_debug, &mut self.shadow_texture)
+        else {
+            return;
+        };
+        let atlas = debug.borrow().atlas;
+        if atlas == 0 {
+            return;
+        }
+        if !*ready {
+            match self.painter.get_raw_gl_texture_id(texture) {
+                Some(placeholder) => unsafe { gl::DeleteTextures(1, &placeholder) },
+                None => return,
+            }
+            *ready = true;
+        }
+        // The atlas is recreated when its size changes
+        self.painter.set_raw_gl_texture_id(texture, atlas);
+    }
 }
 
 impl Layer for EguiLayer {
@@ -57,6 +175,7 @@ impl Layer for EguiLayer {
             }
             ui.checkbox(&mut true, "A checkbox"); // Example interactive element
         });
+        self.shadow_debug_window();
 
         let FullOutput {
             platform_output,
@@ -66,27 +185,82 @@ impl Layer for EguiLayer {
             viewport_output, // You might need to handle this if you use egui viewports
         } = self.ctx.end_frame();
 
-        // Process egui's output (e.g., set mouse cursor, open URLs)
+        // Copies go to our clipboard, the backend handles the rest of the output
+        // (e.g., set mouse cursor, open URLs)
+        let mut platform_output = platform_output;
+        if let Some(clipboard) = &self.clipboard {
+            platform_output.commands.retain(|command| match command {
+                OutputCommand::CopyText(text) => {
+                    if !text.is_empty() {
+                        if let Err(e) = clipboard.set_text(text) {
+                            ubiwarn!("Could not copy to the clipboard: {}", e);
+                        }
+                    }
+                    false
+                }
+                _ => true,
+            });
+        }
         self.egui_state
             .process_output(&self.window, &platform_output);
 
         // Convert egui shapes into paint jobs and paint them
         let paint_jobs = self.ctx.tessellate(shapes, pixels_per_point);
+        // The painter only sets blending and scissor, the rest comes from the last pass
+        gl_state::bind_framebuffer(0);
+        gl_state::set_enabled(gl::DEPTH_TEST, false);
+        gl_state::set_enabled(gl::CULL_FACE, false);
+        gl_state::set_enabled(gl::STENCIL_TEST, false);
+        gl_state::set_enabled(gl::POLYGON_OFFSET_FILL, false);
+        gl_state::polygon_mode(gl::FILL);
         self.painter.paint_jobs(None, textures_delta, paint_jobs);
+        // The painter sets its own program, buffers, blending and scissor
+        gl_state::restore();
+        self.update_shadow_texture();
     }
 
     fn on_event(&mut self, event: &mut crate::prelude::Event) {
+        self.feed(event);
+        // As of the last frame, egui only takes what lands on its windows or text fields
+        let wants_pointer = self.ctx.wants_pointer_input();
+        let wants_keyboard = self.ctx.wants_keyboard_input();
         let mut dispatcher = EventDispatcher::new();
-        match event {
-            crate::prelude::Event::MouseButtonPressed(data) => {
-                dispatcher.dispatch(event, |e| {
-                    println!("{}", e);
-                    ubiinfo!("{}", e);
-                    true
-                });
+        // Clipboard shortcuts, Ctrl on most systems and Cmd on macOS, for a focused text field.
+        // The clipboard is only read then.
+        dispatcher.on::<KeyPressedEventData>(event, |data| {
+            if !wants_keyboard
+                || !data
+                    .get_modifiers()
+                    .intersects(Modifiers::CTRL | Modifiers::SUPER)
+            {
+                return false;
+            }
+            let input = &mut self.egui_state.input.events;
+            match data.get_key_code() {
+                KeyCode::C => input.push(egui::Event::Copy),
+                KeyCode::X => input.push(egui::Event::Cut),
+                KeyCode::V => {
+                    if let Some(Ok(text)) = self.clipboard.as_ref().map(|c| c.get_text()) {
+                        input.push(egui::Event::Paste(text));
+                    }
+                }
+                _ => {}
             }
+            // The keys are not consumed here, the keyboard rule below decides
+            false
+        });
 
-            _ => {}
-        }
+        dispatcher.on::<MouseButtonPressedEventData>(event, |data| {
+            ubiinfo!(
+                "Mouse button {} pressed at {:?}",
+                data.get_button(),
+                data.get_button_pos()
+            );
+            wants_pointer
+        });
+
+        // The rest goes on to the layers below and `Input`
+        dispatcher.on_category(event, EventCategory::MOUSE, |_| wants_pointer);
+        dispatcher.on_category(event, EventCategory::KEYBOARD, |_| wants_keyboard);
     }
 }
@@ -0,0 +1,1046 @@
+// Named actions and axes bound to keys, mouse buttons and gamepads, so game logic asks for
+// "jump" instead of a key and players can rebind the controls
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+
+use crate::core::custom_error::UbiError;
+
+use super::input::{GamepadAxis, GamepadButton, Input, KeyCode, MouseButton, ScanCode};
+
+// A button-like input, written "key:Space", "scan:W", "mouse:Left" or "gamepad:South" in files
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    ScanCode(ScanCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButton),
+}
+
+impl Binding {
+    pub fn pressed(&self, input: &Input) -> bool {
+        match *self {
+            Binding::Key(key) => input.key_pressed(key),
+            Binding::ScanCode(scan_code) => input.scan_code_pressed(scan_code),
+            Binding::Mouse(button) => input.mouse_pressed(button),
+            Binding::GamepadButton(button) => input.gamepad_button_pressed(button),
+        }
+    }
+
+    pub fn just_pressed(&self, input: &Input) -> bool {
+        match *self {
+            Binding::Key(key) => input.key_just_pressed(key),
+            Binding::ScanCode(scan_code) => input.scan_code_just_pressed(scan_code),
+            Binding::Mouse(button) => input.mouse_just_pressed(button),
+            Binding::GamepadButton(button) => input.gamepad_button_just_pressed(button),
+        }
+    }
+
+    pub fn just_released(&self, input: &Input) -> bool {
+        match *self {
+            Binding::Key(key) => input.key_just_released(key),
+            Binding::ScanCode(scan_code) => input.scan_code_just_released(scan_code),
+            Binding::Mouse(button) => input.mouse_just_released(button),
+            Binding::GamepadButton(button) => input.gamepad_button_just_released(button),
+        }
+    }
+
+    // Some input pressed during the last frame, keys first
+    pub fn capture(input: &Input) -> Option<Binding> {
+        input
+            .just_pressed_keys()
+            .find(|key| *key != KeyCode::Unknown)
+            .map(Binding::Key)
+            .or_else(|| {
+                input
+                    .just_pressed_mouse_buttons()
+                    .next()
+                    .map(Binding::Mouse)
+            })
+            .or_else(|| {
+                input
+                    .just_pressed_gamepad_buttons()
+                    .next()
+                    .map(Binding::GamepadButton)
+            })
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key:{}", key),
+            Binding::ScanCode(scan_code) => write!(f, "scan:{}", scan_code),
+            Binding::Mouse(button) => write!(f, "mouse:{}", button),
+            Binding::GamepadButton(button) => write!(f, "gamepad:{}", button),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = UbiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (device, name) = s
+            .split_once(':')
+            .ok_or_else(|| error(&format!("binding {:?} has no device", s)))?;
+        let binding = match device {
+            "key" => KeyCode::from_name(name).map(Binding::Key),
+            "scan" => ScanCode::from_name(name).map(Binding::ScanCode),
+            "mouse" => MouseButton::from_name(name).map(Binding::Mouse),
+            "gamepad" => GamepadButton::from_name(name).map(Binding::GamepadButton),
+            _ => return Err(error(&format!("unknown device {:?}", device))),
+        };
+        binding.ok_or_else(|| error(&format!("unknown {} {:?}", device, name)))
+    }
+}
+
+// Input giving a value from -1.0 to 1.0
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AxisBinding {
+    // -1.0 while `negative` is held, 1.0 while `positive` is, 0.0 with both
+    Buttons {
+        negative: Binding,
+        positive: Binding,
+    },
+    // Values within the dead zone read 0.0, the rest is rescaled to start from there
+    Gamepad {
+        axis: GamepadAxis,
+        dead_zone: f32,
+        invert: bool,
+    },
+}
+
+impl AxisBinding {
+    pub fn value(&self, input: &Input) -> f32 {
+        match *self {
+            AxisBinding::Buttons { negative, positive } => {
+                positive.pressed(input) as i32 as f32 - negative.pressed(input) as i32 as f32
+            }
+            AxisBinding::Gamepad {
+                axis,
+                dead_zone,
+                invert,
+            } => {
+                let value = apply_dead_zone(input.gamepad_axis(axis), dead_zone);
+                if invert {
+                    -value
+                } else {
+                    value
+                }
+            }
+        }
+    }
+}
+
+// Input giving a direction of length up to 1.0, x to the right and y up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Axis2dBinding {
+    // WASD and the like, diagonals are normalized
+    Buttons {
+        up: Binding,
+        down: Binding,
+        left: Binding,
+        right: Binding,
+    },
+    // The dead zone applies to the length of the stick position, y is flipped to point up
+    Gamepad {
+        x: GamepadAxis,
+        y: GamepadAxis,
+        dead_zone: f32,
+    },
+}
+
+impl Axis2dBinding {
+    pub fn value(&self, input: &Input) -> (f32, f32) {
+        match *self {
+            Axis2dBinding::Buttons {
+                up,
+                down,
+                left,
+                right,
+            } => {
+                let x = right.pressed(input) as i32 as f32 - left.pressed(input) as i32 as f32;
+                let y = up.pressed(input) as i32 as f32 - down.pressed(input) as i32 as f32;
+                let length = (x * x + y * y).sqrt();
+                if length > 1.0 {
+                    (x / length, y / length)
+                } else {
+                    (x, y)
+                }
+            }
+            Axis2dBinding::Gamepad { x, y, dead_zone } => {
+                let (x, y) = (input.gamepad_axis(x), -input.gamepad_axis(y));
+                let length = (x * x + y * y).sqrt();
+                if length <= dead_zone || length <= f32::EPSILON {
+                    return (0.0, 0.0);
+                }
+                let scale = apply_dead_zone(length.min(1.0), dead_zone) / length;
+                (x * scale, y * scale)
+            }
+        }
+    }
+}
+
+// Gamepad axes move this far from rest to be taken by a rebind
+const REBIND_AXIS_THRESHOLD: f32 = 0.5;
+
+// Dead zone of gamepad axes bound by a rebind, unless the slot had one
+const DEFAULT_DEAD_ZONE: f32 = 0.2;
+
+// Button of an `AxisBinding::Buttons`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AxisSide {
+    Negative,
+    Positive,
+}
+
+// Button of an `Axis2dBinding::Buttons`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// Binding slot waiting for an input
+#[derive(Debug, Clone, PartialEq)]
+pub enum RebindTarget {
+    Action {
+        action: String,
+        slot: usize,
+    },
+    Axis {
+        axis: String,
+        slot: usize,
+        side: AxisSide,
+    },
+    Axis2d {
+        axis: String,
+        slot: usize,
+        direction: Direction,
+    },
+}
+
+// Input bound by a rebind
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Captured {
+    Button(Binding),
+    GamepadAxis(GamepadAxis),
+}
+
+#[derive(Debug, Clone)]
+struct Rebinding {
+    target: RebindTarget,
+    // Set by the first update, input of the frame the rebind started in is ignored so the
+    // click or key starting it is not bound
+    armed: bool,
+    // Axes already moved when armed, e.g. a stick held to navigate the menu
+    held_axes: Vec<GamepadAxis>,
+}
+
+// Actions, axes and 2D axes by name. Each can have several bindings: an action is pressed
+// while any of its bindings is, an axis takes the binding with the largest value.
+#[derive(Debug, Clone)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+    axes_2d: BTreeMap<String, Vec<Axis2dBinding>>,
+    rebinding: Option<Rebinding>,
+    // Input cancelling a rebind, None to make every input bindable
+    rebind_cancel: Option<Binding>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self {
+            actions: BTreeMap::new(),
+            axes: BTreeMap::new(),
+            axes_2d: BTreeMap::new(),
+            rebinding: None,
+            rebind_cancel: Some(Binding::Key(KeyCode::Escape)),
+        }
+    }
+
+    pub fn bind_action(&mut self, action: &str, binding: Binding) {
+        self.actions
+            .entry(action.to_string())
+            .or_default()
+            .push(binding);
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.entry(axis.to_string()).or_default().push(binding);
+    }
+
+    pub fn bind_axis_2d(&mut self, axis: &str, binding: Axis2dBinding) {
+        self.axes_2d
+            .entry(axis.to_string())
+            .or_default()
+            .push(binding);
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], |b| b.as_slice())
+    }
+
+    pub fn action_bindings_mut(&mut self, action: &str) -> &mut Vec<Binding> {
+        self.actions.entry(action.to_string()).or_default()
+    }
+
+    pub fn axis_bindings_mut(&mut self, axis: &str) -> &mut Vec<AxisBinding> {
+        self.axes.entry(axis.to_string()).or_default()
+    }
+
+    pub fn axis_2d_bindings_mut(&mut self, axis: &str) -> &mut Vec<Axis2dBinding> {
+        self.axes_2d.entry(axis.to_string()).or_default()
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    // Actions using `binding`, to warn about conflicts after a rebind
+    pub fn actions_bound_to(&self, binding: Binding) -> impl Iterator<Item = &str> {
+        self.actions
+            .iter()
+            .filter(move |(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| action.as_str())
+    }
+
+    pub fn pressed(&self, input: &Input, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| binding.pressed(input))
+    }
+
+    pub fn just_pressed(&self, input: &Input, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| binding.just_pressed(input))
+    }
+
+    pub fn just_released(&self, input: &Input, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| binding.just_released(input))
+    }
+
+    // 0.0 for an unknown axis
+    pub fn axis(&self, input: &Input, axis: &str) -> f32 {
+        self.axes
+            .get(axis)
+            .into_iter()
+            .flatten()
+            .map(|binding| binding.value(input))
+            .fold(0.0, |best, value| {
+                if value.abs() > best.abs() {
+                    value
+                } else {
+                    best
+                }
+            })
+    }
+
+    pub fn axis_2d(&self, input: &Input, axis: &str) -> (f32, f32) {
+        self.axes_2d
+            .get(axis)
+            .into_iter()
+            .flatten()
+            .map(|binding| binding.value(input))
+            .fold((0.0, 0.0), |best, value| {
+                if value.0 * value.0 + value.1 * value.1 > best.0 * best.0 + best.1 * best.1 {
+                    value
+                } else {
+                    best
+                }
+            })
+    }
+
+    // Wait for an input to replace binding `slot` of `action`, a slot past the end adds one.
+    // Feed the frames to `update_rebind` until it returns the new binding.
+    pub fn start_rebind(&mut self, action: &str, slot: usize) {
+        self.start(RebindTarget::Action {
+            action: action.to_string(),
+            slot,
+        });
+    }
+
+    // A button replaces `side` of a button pair, a gamepad axis replaces the whole slot and
+    // reads as `side` when moved the way it was during the rebind. New and gamepad slots
+    // only take an axis, bind a pair with `bind_axis` first to rebind its buttons.
+    pub fn start_axis_rebind(&mut self, axis: &str, slot: usize, side: AxisSide) {
+        self.start(RebindTarget::Axis {
+            axis: axis.to_string(),
+            slot,
+            side,
+        });
+    }
+
+    // As `start_axis_rebind`, a stick axis binds both axes of its stick
+    pub fn start_axis_2d_rebind(&mut self, axis: &str, slot: usize, direction: Direction) {
+        self.start(RebindTarget::Axis2d {
+            axis: axis.to_string(),
+            slot,
+            direction,
+        });
+    }
+
+    fn start(&mut self, target: RebindTarget) {
+        self.rebinding = Some(Rebinding {
+            target,
+            armed: false,
+            held_axes: Vec::new(),
+        });
+    }
+
+    pub fn cancel_rebind(&mut self) {
+        self.rebinding = None;
+    }
+
+    pub fn rebinding(&self) -> Option<&RebindTarget> {
+        self.rebinding.as_ref().map(|rebinding| &rebinding.target)
+    }
+
+    // Escape by default
+    pub fn set_rebind_cancel(&mut self, cancel: Option<Binding>) {
+        self.rebind_cancel = cancel;
+    }
+
+    pub fn rebind_cancel(&self) -> Option<Binding> {
+        self.rebind_cancel
+    }
+
+    // Bind the first input pressed or moved during the frame, once per frame
+    pub fn update_rebind(&mut self, input: &Input) -> Option<Captured> {
+        let rebinding = self.rebinding.as_mut()?;
+        let moved = |axis: &GamepadAxis| input.gamepad_axis(*axis).abs() >= REBIND_AXIS_THRESHOLD;
+        if !rebinding.armed {
+            rebinding.armed = true;
+            rebinding.held_axes = GamepadAxis::ALL.iter().copied().filter(moved).collect();
+            return None;
+        }
+        // Held axes count again once back to rest
+        rebinding.held_axes.retain(moved);
+        if self
+            .rebind_cancel
+            .is_some_and(|cancel| cancel.just_pressed(input))
+        {
+            self.rebinding = None;
+            return None;
+        }
+        let axis = GamepadAxis::ALL
+            .iter()
+            .copied()
+            .filter(moved)
+            .find(|axis| !rebinding.held_axes.contains(axis))
+            .map(|axis| (axis, input.gamepad_axis(axis)));
+        let target = rebinding.target.clone();
+        let captured = self.rebind(&target, Binding::capture(input), axis)?;
+        self.rebinding = None;
+        Some(captured)
+    }
+
+    fn rebind(
+        &mut self,
+        target: &RebindTarget,
+        button: Option<Binding>,
+        axis: Option<(GamepadAxis, f32)>,
+    ) -> Option<Captured> {
+        match target {
+            RebindTarget::Action { action, slot } => {
+                let binding = button?;
+                replace_slot(self.action_bindings_mut(action), *slot, binding);
+                Some(Captured::Button(binding))
+            }
+            RebindTarget::Axis {
+                axis: name,
+                slot,
+                side,
+            } => {
+                let bindings = self.axis_bindings_mut(name);
+                let dead_zone = match bindings.get(*slot) {
+                    Some(AxisBinding::Gamepad { dead_zone, .. }) => *dead_zone,
+                    _ => DEFAULT_DEAD_ZONE,
+                };
+                match (bindings.get_mut(*slot), button, axis) {
+                    (Some(AxisBinding::Buttons { negative, positive }), Some(binding), _) => {
+                        match side {
+                            AxisSide::Negative => *negative = binding,
+                            AxisSide::Positive => *positive = binding,
+                        }
+                        Some(Captured::Button(binding))
+                    }
+                    (_, _, Some((moved, value))) => {
+                        let invert = (value < 0.0) != (*side == AxisSide::Negative);
+                        let binding = AxisBinding::Gamepad {
+                            axis: moved,
+                            dead_zone,
+                            invert,
+                        };
+                        replace_slot(bindings, *slot, binding);
+                        Some(Captured::GamepadAxis(moved))
+                    }
+                    _ => None,
+                }
+            }
+            RebindTarget::Axis2d {
+                axis: name,
+                slot,
+                direction,
+            } => {
+                let bindings = self.axis_2d_bindings_mut(name);
+                let dead_zone = match bindings.get(*slot) {
+                    Some(Axis2dBinding::Gamepad { dead_zone, .. }) => *dead_zone,
+                    _ => DEFAULT_DEAD_ZONE,
+                };
+                match (bindings.get_mut(*slot), button, axis) {
+                    (
+                        Some(Axis2dBinding::Buttons {
+                            up,
+                            down,
+                            left,
+                            right,
+                        }),
+                        Some(binding),
+                        _,
+                    ) => {
+                        match direction {
+                            Direction::Up => *up = binding,
+                            Direction::Down => *down = binding,
+                            Direction::Left => *left = binding,
+                            Direction::Right => *right = binding,
+                        }
+                        Some(Captured::Button(binding))
+                    }
+                    (_, _, Some((moved, _))) => {
+                        let (x, y) = match moved {
+                            GamepadAxis::LeftX | GamepadAxis::LeftY => {
+                                (GamepadAxis::LeftX, GamepadAxis::LeftY)
+                            }
+                            GamepadAxis::RightX | GamepadAxis::RightY => {
+                                (GamepadAxis::RightX, GamepadAxis::RightY)
+                            }
+                            _ => return None,
+                        };
+                        replace_slot(bindings, *slot, Axis2dBinding::Gamepad { x, y, dead_zone });
+                        Some(Captured::GamepadAxis(moved))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, UbiError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::from_json(&source).map_err(|e| match e {
+            UbiError::ResourceError(message) => {
+                UbiError::ResourceError(format!("{}: {}", path.display(), message))
+            }
+            e => e,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), UbiError> {
+        std::fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    // {"actions": {"jump": ["key:Space"]},
+    //  "axes": {"move_x": [{"negative": "key:A", "positive": "key:D"},
+    //                      {"axis": "LeftX", "dead_zone": 0.2, "invert": false}]},
+    //  "axes_2d": {"move": [{"up": "key:W", "down": "key:S", "left": "key:A", "right": "key:D"},
+    //                       {"x": "LeftX", "y": "LeftY", "dead_zone": 0.2}]}}
+    pub fn from_json(source: &str) -> Result<Self, UbiError> {
+        let root: Value = serde_json::from_str(source).map_err(|e| error(&e.to_string()))?;
+        let mut map = ActionMap::new();
+        for (action, bindings) in json_entries(&root, "actions")? {
+            for binding in json_list(bindings, action)? {
+                map.bind_action(action, json_binding(binding)?);
+            }
+        }
+        for (axis, bindings) in json_entries(&root, "axes")? {
+            for binding in json_list(bindings, axis)? {
+                let binding = if binding.get("axis").is_some() {
+                    AxisBinding::Gamepad {
+                        axis: json_gamepad_axis(&binding["axis"])?,
+                        dead_zone: binding["dead_zone"].as_f64().unwrap_or(0.0) as f32,
+                        invert: binding["invert"].as_bool().unwrap_or(false),
+                    }
+                } else {
+                    AxisBinding::Buttons {
+                        negative: json_binding(&binding["negative"])?,
+                        positive: json_binding(&binding["positive"])?,
+                    }
+                };
+                map.bind_axis(axis, binding);
+            }
+        }
+        for (axis, bindings) in json_entries(&root, "axes_2d")? {
+            for binding in json_list(bindings, axis)? {
+                let binding = if binding.get("x").is_some() {
+                    Axis2dBinding::Gamepad {
+                        x: json_gamepad_axis(&binding["x"])?,
+                        y: json_gamepad_axis(&binding["y"])?,
+                        dead_zone: binding["dead_zone"].as_f64().unwrap_or(0.0) as f32,
+                    }
+                } else {
+                    Axis2dBinding::Buttons {
+                        up: json_binding(&binding["up"])?,
+                        down: json_binding(&binding["down"])?,
+                        left: json_binding(&binding["left"])?,
+                        right: json_binding(&binding["right"])?,
+                    }
+                };
+                map.bind_axis_2d(axis, binding);
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn to_json(&self) -> String {
+        let actions: Map<String, Value> = self
+            .actions
+            .iter()
+            .map(|(action, bindings)| {
+                let bindings = bindings.iter().map(|b| json!(b.to_string())).collect();
+                (action.clone(), Value::Array(bindings))
+            })
+            .collect();
+        let axes: Map<String, Value> = self
+            .axes
+            .iter()
+            .map(|(axis, bindings)| {
+                let bindings = bindings
+                    .iter()
+                    .map(|binding| match binding {
+                        AxisBinding::Buttons { negative, positive } => json!({
+                            "negative": negative.to_string(),
+                            "positive": positive.to_string(),
+                        }),
+                        AxisBinding::Gamepad {
+                            axis,
+                            dead_zone,
+                            invert,
+                        } => json!({
+                            "axis": axis.name(),
+                            "dead_zone": dead_zone,
+                            "invert": invert,
+                        }),
+                    })
+                    .collect();
+                (axis.clone(), Value::Array(bindings))
+            })
+            .collect();
+        let axes_2d: Map<String, Value> = self
+            .axes_2d
+            .iter()
+            .map(|(axis, bindings)| {
+                let bindings = bindings
+                    .iter()
+                    .map(|binding| match binding {
+                        Axis2dBinding::Buttons {
+                            up,
+                            down,
+                            left,
+                            right,
+                        } => json!({
+                            "up": up.to_string(),
+                            "down": down.to_string(),
+                            "left": left.to_string(),
+                            "right": right.to_string(),
+                        }),
+                        Axis2dBinding::Gamepad { x, y, dead_zone } => json!({
+                            "x": x.name(),
+                            "y": y.name(),
+                            "dead_zone": dead_zone,
+                        }),
+                    })
+                    .collect();
+                (axis.clone(), Value::Array(bindings))
+            })
+            .collect();
+        let root = json!({
+            "actions": actions,
+            "axes": axes,
+            "axes_2d": axes_2d,
+        });
+        serde_json::to_string_pretty(&root).unwrap_or_default()
+    }
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Replace binding `slot`, a slot past the end adds one
+fn replace_slot<T>(bindings: &mut Vec<T>, slot: usize, binding: T) {
+    if slot < bindings.len() {
+        bindings[slot] = binding;
+    } else {
+        bindings.push(binding);
+    }
+}
+
+// Rescale what is past the dead zone to start from 0.0, keeping the sign
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let dead_zone = dead_zone.clamp(0.0, 0.99);
+    if value.abs() <= dead_zone {
+        return 0.0;
+    }
+    value.signum() * ((value.abs() - dead_zone) / (1.0 - dead_zone)).min(1.0)
+}
+
+// Entries of an optional object
+fn json_entries<'a>(root: &'a Value, key: &str) -> Result<Vec<(&'a str, &'a Value)>, UbiError> {
+    match &root[key] {
+        Value::Null => Ok(Vec::new()),
+        Value::Object(entries) => Ok(entries.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        _ => Err(error(&format!("{} is not an object", key))),
+    }
+}
+
+fn json_list<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, UbiError> {
+    value
+        .as_array()
+        .ok_or_else(|| error(&format!("bindings of {} are not a list", name)))
+}
+
+fn json_binding(value: &Value) -> Result<Binding, UbiError> {
+    value
+        .as_str()
+        .ok_or_else(|| error(&format!("binding {} is not a string", value)))?
+        .parse()
+}
+
+fn json_gamepad_axis(value: &Value) -> Result<GamepadAxis, UbiError> {
+    value
+        .as_str()
+        .and_then(GamepadAxis::from_name)
+        .ok_or_else(|| error(&format!("unknown gamepad axis {}", value)))
+}
+
+fn error(message: &str) -> UbiError {
+    UbiError::ResourceError(format!("input bindings: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::event::Event;
+    use crate::event::event_data::{
+        GamepadAxisMovedEventData, GamepadConnectedEventData, KeyPressedEventData,
+        KeyReleasedEventData, MouseButtonPressedEventData,
+    };
+    use crate::event::input::Modifiers;
+
+    fn press(input: &mut Input, key: KeyCode) {
+        input.handle_event(&Event::KeyPressed(KeyPressedEventData::new(
+            key,
+            ScanCode::Unknown,
+            Modifiers::NONE,
+            0,
+        )));
+    }
+
+    fn release(input: &mut Input, key: KeyCode) {
+        input.handle_event(&Event::KeyReleased(KeyReleasedEventData::new(
+            key,
+            ScanCode::Unknown,
+            Modifiers::NONE,
+        )));
+    }
+
+    fn gamepad() -> Input {
+        let mut input = Input::new();
+        input.handle_event(&Event::GamepadConnected(GamepadConnectedEventData::new(
+            0,
+            "pad".to_string(),
+        )));
+        input
+    }
+
+    fn move_axis(input: &mut Input, axis: GamepadAxis, value: f32) {
+        input.handle_event(&Event::GamepadAxisMoved(GamepadAxisMovedEventData::new(
+            0, axis, value,
+        )));
+    }
+
+    fn wasd() -> Axis2dBinding {
+        Axis2dBinding::Buttons {
+            up: Binding::Key(KeyCode::W),
+            down: Binding::Key(KeyCode::S),
+            left: Binding::Key(KeyCode::A),
+            right: Binding::Key(KeyCode::D),
+        }
+    }
+
+    #[test]
+    fn bindings_parse_what_they_display() {
+        let bindings = [
+            Binding::Key(KeyCode::Space),
+            Binding::ScanCode(ScanCode::W),
+            Binding::Mouse(MouseButton::X1),
+            Binding::GamepadButton(GamepadButton::South),
+        ];
+        for binding in bindings {
+            assert_eq!(binding.to_string().parse::<Binding>().unwrap(), binding);
+        }
+        assert!("Space".parse::<Binding>().is_err());
+        assert!("pen:Tip".parse::<Binding>().is_err());
+        assert!("key:Nope".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut map = ActionMap::new();
+        map.bind_action("jump", Binding::Key(KeyCode::Space));
+        map.bind_action("jump", Binding::GamepadButton(GamepadButton::South));
+        map.bind_axis(
+            "move_x",
+            AxisBinding::Buttons {
+                negative: Binding::ScanCode(ScanCode::A),
+                positive: Binding::ScanCode(ScanCode::D),
+            },
+        );
+        map.bind_axis(
+            "zoom",
+            AxisBinding::Gamepad {
+                axis: GamepadAxis::RightTrigger,
+                dead_zone: 0.25,
+                invert: true,
+            },
+        );
+        map.bind_axis_2d("move", wasd());
+        map.bind_axis_2d(
+            "move",
+            Axis2dBinding::Gamepad {
+                x: GamepadAxis::LeftX,
+                y: GamepadAxis::LeftY,
+                dead_zone: 0.5,
+            },
+        );
+
+        let mut loaded = ActionMap::from_json(&map.to_json()).unwrap();
+        assert_eq!(loaded.to_json(), map.to_json());
+        assert_eq!(loaded.action_bindings("jump"), map.action_bindings("jump"));
+        assert_eq!(
+            loaded.axis_bindings_mut("zoom"),
+            map.axis_bindings_mut("zoom")
+        );
+        assert_eq!(
+            loaded.axis_2d_bindings_mut("move"),
+            map.axis_2d_bindings_mut("move")
+        );
+    }
+
+    #[test]
+    fn json_errors_are_reported() {
+        assert!(ActionMap::from_json("{\"actions\": []}").is_err());
+        assert!(ActionMap::from_json("{\"actions\": {\"jump\": \"key:Space\"}}").is_err());
+        assert!(ActionMap::from_json("{\"actions\": {\"jump\": [\"key:Jump\"]}}").is_err());
+        assert!(ActionMap::from_json("{\"axes\": {\"x\": [{\"axis\": \"Wheel\"}]}}").is_err());
+        assert!(ActionMap::from_json("{}")
+            .unwrap()
+            .actions()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn axes_take_the_largest_binding() {
+        let mut map = ActionMap::new();
+        map.bind_axis(
+            "move_x",
+            AxisBinding::Buttons {
+                negative: Binding::Key(KeyCode::A),
+                positive: Binding::Key(KeyCode::D),
+            },
+        );
+        map.bind_axis(
+            "move_x",
+            AxisBinding::Gamepad {
+                axis: GamepadAxis::LeftX,
+                dead_zone: 0.2,
+                invert: false,
+            },
+        );
+        map.bind_axis_2d("move", wasd());
+
+        let mut input = gamepad();
+        move_axis(&mut input, GamepadAxis::LeftX, 0.1);
+        assert_eq!(map.axis(&input, "move_x"), 0.0);
+        move_axis(&mut input, GamepadAxis::LeftX, 0.6);
+        assert!((map.axis(&input, "move_x") - 0.5).abs() < 1e-5);
+        press(&mut input, KeyCode::A);
+        assert_eq!(map.axis(&input, "move_x"), -1.0);
+
+        press(&mut input, KeyCode::W);
+        let (x, y) = map.axis_2d(&input, "move");
+        assert!((x + std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-5);
+        assert!((y - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-5);
+        assert_eq!(map.axis(&input, "unknown"), 0.0);
+    }
+
+    #[test]
+    fn rebind_ignores_the_input_starting_it() {
+        let mut map = ActionMap::new();
+        map.bind_action("jump", Binding::Key(KeyCode::Space));
+        let mut input = Input::new();
+
+        // The click on the rebind button
+        input.handle_event(&Event::MouseButtonPressed(
+            MouseButtonPressedEventData::new(MouseButton::Left, 0, 0, 1),
+        ));
+        map.start_rebind("jump", 0);
+        assert_eq!(map.update_rebind(&input), None);
+
+        input.begin_frame();
+        assert_eq!(map.update_rebind(&input), None);
+        input.begin_frame();
+        press(&mut input, KeyCode::J);
+        assert_eq!(
+            map.update_rebind(&input),
+            Some(Captured::Button(Binding::Key(KeyCode::J)))
+        );
+        assert_eq!(map.action_bindings("jump"), &[Binding::Key(KeyCode::J)]);
+        assert_eq!(map.rebinding(), None);
+
+        // A slot past the end adds a binding
+        map.start_rebind("jump", 5);
+        map.update_rebind(&input);
+        input.begin_frame();
+        press(&mut input, KeyCode::K);
+        map.update_rebind(&input);
+        assert_eq!(
+            map.action_bindings("jump"),
+            &[Binding::Key(KeyCode::J), Binding::Key(KeyCode::K)]
+        );
+    }
+
+    #[test]
+    fn rebind_cancel_is_configurable() {
+        let mut map = ActionMap::new();
+        map.bind_action("pause", Binding::Key(KeyCode::P));
+        let mut input = Input::new();
+
+        map.start_rebind("pause", 0);
+        map.update_rebind(&input);
+        press(&mut input, KeyCode::Escape);
+        assert_eq!(map.update_rebind(&input), None);
+        assert_eq!(map.rebinding(), None);
+        assert_eq!(map.action_bindings("pause"), &[Binding::Key(KeyCode::P)]);
+
+        map.set_rebind_cancel(None);
+        release(&mut input, KeyCode::Escape);
+        input.begin_frame();
+        map.start_rebind("pause", 0);
+        map.update_rebind(&input);
+        press(&mut input, KeyCode::Escape);
+        assert_eq!(
+            map.update_rebind(&input),
+            Some(Captured::Button(Binding::Key(KeyCode::Escape)))
+        );
+        assert_eq!(
+            map.action_bindings("pause"),
+            &[Binding::Key(KeyCode::Escape)]
+        );
+    }
+
+    #[test]
+    fn rebind_axis_buttons() {
+        let mut map = ActionMap::new();
+        map.bind_axis(
+            "move_x",
+            AxisBinding::Buttons {
+                negative: Binding::Key(KeyCode::A),
+                positive: Binding::Key(KeyCode::D),
+            },
+        );
+        map.bind_axis_2d("move", wasd());
+        let mut input = Input::new();
+
+        map.start_axis_rebind("move_x", 0, AxisSide::Positive);
+        map.update_rebind(&input);
+        press(&mut input, KeyCode::Right);
+        map.update_rebind(&input);
+        assert_eq!(
+            map.axis_bindings_mut("move_x"),
+            &vec![AxisBinding::Buttons {
+                negative: Binding::Key(KeyCode::A),
+                positive: Binding::Key(KeyCode::Right),
+            }]
+        );
+
+        input.begin_frame();
+        map.start_axis_2d_rebind("move", 0, Direction::Up);
+        map.update_rebind(&input);
+        press(&mut input, KeyCode::Up);
+        map.update_rebind(&input);
+        assert_eq!(
+            map.axis_2d_bindings_mut("move")[0],
+            Axis2dBinding::Buttons {
+                up: Binding::Key(KeyCode::Up),
+                down: Binding::Key(KeyCode::S),
+                left: Binding::Key(KeyCode::A),
+                right: Binding::Key(KeyCode::D),
+            }
+        );
+    }
+
+    #[test]
+    fn rebind_gamepad_axes() {
+        let mut map = ActionMap::new();
+        let mut input = gamepad();
+
+        // LeftX is held when the rebind starts, it is only taken after going back to rest
+        move_axis(&mut input, GamepadAxis::LeftX, 0.9);
+        map.start_axis_rebind("throttle", 0, AxisSide::Positive);
+        map.update_rebind(&input);
+        // A button does not make a new slot
+        press(&mut input, KeyCode::W);
+        assert_eq!(map.update_rebind(&input), None);
+        move_axis(&mut input, GamepadAxis::LeftY, -0.8);
+        assert_eq!(
+            map.update_rebind(&input),
+            Some(Captured::GamepadAxis(GamepadAxis::LeftY))
+        );
+        // Pushed up, which reads negative, for the positive side
+        assert_eq!(
+            map.axis_bindings_mut("throttle"),
+            &vec![AxisBinding::Gamepad {
+                axis: GamepadAxis::LeftY,
+                dead_zone: DEFAULT_DEAD_ZONE,
+                invert: true,
+            }]
+        );
+        assert!(map.axis(&input, "throttle") > 0.0);
+
+        move_axis(&mut input, GamepadAxis::LeftY, 0.0);
+        map.start_axis_2d_rebind("look", 0, Direction::Right);
+        map.update_rebind(&input);
+        assert_eq!(map.update_rebind(&input), None);
+        move_axis(&mut input, GamepadAxis::LeftX, 0.0);
+        map.update_rebind(&input);
+        move_axis(&mut input, GamepadAxis::RightY, 0.7);
+        assert_eq!(
+            map.update_rebind(&input),
+            Some(Captured::GamepadAxis(GamepadAxis::RightY))
+        );
+        assert_eq!(
+            map.axis_2d_bindings_mut("look"),
+            &vec![Axis2dBinding::Gamepad {
+                x: GamepadAxis::RightX,
+                y: GamepadAxis::RightY,
+                dead_zone: DEFAULT_DEAD_ZONE,
+            }]
+        );
+    }
+}
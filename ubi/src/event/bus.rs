@@ -0,0 +1,306 @@
+// Typed events between layers, e.g. a `PlayerDied` struct, without going through `Event`.
+// Storage is double buffered: an event lives for the frame it was sent and the next one,
+// so every layer reads it once whatever its position in the stack.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+pub struct Events<T> {
+    // Events of the last frame, then of this frame
+    previous: Vec<T>,
+    current: Vec<T>,
+    // Number of events dropped since the start, ids of the readers count from there
+    start: usize,
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            start: 0,
+        }
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    // Next frame, the events of the last frame are dropped
+    pub fn update(&mut self) {
+        self.start += self.previous.len();
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    // Events of this frame and the last one
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    pub fn clear(&mut self) {
+        self.start += self.len();
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    // Id the next event gets
+    fn end(&self) -> usize {
+        self.start + self.len()
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct EventWriter<'a, T> {
+    events: &'a mut Events<T>,
+}
+
+impl<'a, T> EventWriter<'a, T> {
+    pub fn new(events: &'a mut Events<T>) -> Self {
+        Self { events }
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.events.send(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        for event in events {
+            self.events.send(event);
+        }
+    }
+}
+
+// Cursor of one reader, kept by the layer between frames
+pub struct EventReader<T> {
+    last: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> EventReader<T> {
+    pub fn new() -> Self {
+        Self {
+            last: 0,
+            marker: PhantomData,
+        }
+    }
+
+    // Events sent since the last read, oldest first
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        // Events dropped before being read are skipped
+        let skip = self.last.saturating_sub(events.start);
+        self.last = events.end();
+        events.iter().skip(skip)
+    }
+
+    pub fn len(&self, events: &Events<T>) -> usize {
+        events.end() - self.last.max(events.start)
+    }
+
+    pub fn is_empty(&self, events: &Events<T>) -> bool {
+        self.len(events) == 0
+    }
+
+    // Mark everything as read
+    pub fn clear(&mut self, events: &Events<T>) {
+        self.last = events.end();
+    }
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Type erased `Events<T>` so the bus can update them all
+trait EventStorage {
+    fn update(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> EventStorage for Events<T> {
+    fn update(&mut self) {
+        Events::update(self);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// One `Events<T>` per event type, owned by the application and updated every frame
+#[derive(Default)]
+pub struct EventBus {
+    events: HashMap<TypeId, Box<dyn EventStorage>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            events: HashMap::new(),
+        }
+    }
+
+    pub fn events<T: 'static>(&self) -> Option<&Events<T>> {
+        self.events
+            .get(&TypeId::of::<T>())
+            .and_then(|events| events.as_any().downcast_ref::<Events<T>>())
+    }
+
+    // Storage of `T`, created on first use
+    pub fn events_mut<T: 'static>(&mut self) -> &mut Events<T> {
+        self.events
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Events::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<Events<T>>()
+            .unwrap()
+    }
+
+    pub fn writer<T: 'static>(&mut self) -> EventWriter<'_, T> {
+        EventWriter::new(self.events_mut::<T>())
+    }
+
+    pub fn send<T: 'static>(&mut self, event: T) {
+        self.events_mut::<T>().send(event);
+    }
+
+    // Events of `T` the reader has not seen yet, nothing if none was ever sent
+    pub fn read<'a, T: 'static>(
+        &'a self,
+        reader: &'a mut EventReader<T>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.events::<T>()
+            .into_iter()
+            .flat_map(move |events| reader.read(events))
+    }
+
+    // Called by the application at the start of every frame
+    pub fn update(&mut self) {
+        for events in self.events.values_mut() {
+            events.update();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Hit(u32);
+
+    fn read(reader: &mut EventReader<Hit>, events: &Events<Hit>) -> Vec<u32> {
+        reader.read(events).map(|hit| hit.0).collect()
+    }
+
+    #[test]
+    fn events_live_for_two_frames() {
+        let mut events = Events::new();
+        events.send(Hit(1));
+        events.update();
+        events.send(Hit(2));
+        assert_eq!(events.len(), 2);
+        events.update();
+        assert_eq!(events.iter().collect::<Vec<_>>(), [&Hit(2)]);
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn reader_cursor_survives_updates() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+        events.send(Hit(1));
+        events.send(Hit(2));
+        assert_eq!(reader.len(&events), 2);
+        assert_eq!(read(&mut reader, &events), [1, 2]);
+        assert!(reader.is_empty(&events));
+
+        // Still stored for a frame, but already read
+        events.update();
+        events.send(Hit(3));
+        assert_eq!(reader.len(&events), 1);
+        assert_eq!(read(&mut reader, &events), [3]);
+
+        events.update();
+        events.update();
+        events.send(Hit(4));
+        assert_eq!(read(&mut reader, &events), [4]);
+        assert_eq!(read(&mut reader, &events), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn events_dropped_before_being_read_are_skipped() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+        events.send(Hit(1));
+        events.update();
+        events.send(Hit(2));
+        events.update();
+        events.send(Hit(3));
+        assert_eq!(reader.len(&events), 2);
+        assert_eq!(read(&mut reader, &events), [2, 3]);
+
+        events.send(Hit(4));
+        events.clear();
+        events.send(Hit(5));
+        assert_eq!(read(&mut reader, &events), [5]);
+    }
+
+    #[test]
+    fn readers_are_independent() {
+        let mut events = Events::new();
+        let mut first = EventReader::new();
+        let mut second = EventReader::new();
+        events.send(Hit(1));
+        assert_eq!(read(&mut first, &events), [1]);
+        events.update();
+        events.send(Hit(2));
+        assert_eq!(read(&mut first, &events), [2]);
+        assert_eq!(read(&mut second, &events), [1, 2]);
+
+        second.clear(&events);
+        events.send(Hit(3));
+        assert_eq!(read(&mut second, &events), [3]);
+    }
+
+    #[test]
+    fn bus_stores_each_type_apart() {
+        let mut bus = EventBus::new();
+        let mut hits = EventReader::<Hit>::new();
+        let mut names = EventReader::<&str>::new();
+        assert_eq!(bus.read(&mut hits).count(), 0);
+        assert!(bus.events::<Hit>().is_none());
+
+        bus.send(Hit(1));
+        bus.writer().send_batch(["a", "b"]);
+        assert_eq!(bus.read(&mut hits).collect::<Vec<_>>(), [&Hit(1)]);
+        bus.update();
+        bus.send(Hit(2));
+        assert_eq!(bus.read(&mut names).collect::<Vec<_>>(), [&"a", &"b"]);
+        assert_eq!(bus.read(&mut hits).collect::<Vec<_>>(), [&Hit(2)]);
+        bus.update();
+        bus.update();
+        assert_eq!(bus.events::<&str>().map(Events::len), Some(0));
+    }
+}
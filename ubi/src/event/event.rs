@@ -17,6 +17,23 @@ pub enum EventType {
     WindowFocus,
     WindowLostFocus,
     WindowMoved,
+    WindowMinimized,
+    WindowMaximized,
+    WindowRestored,
+    WindowScaleChanged,
+    MouseEntered,
+    MouseLeft,
+    GamepadConnected,
+    GamepadDisconnected,
+    GamepadButtonPressed,
+    GamepadButtonReleased,
+    GamepadAxisMoved,
+    TextInput,
+    TextEditing,
+    DropBegin,
+    FileDropped,
+    DropComplete,
+    Custom,
 }
 
 impl fmt::Display for EventType {
@@ -34,6 +51,8 @@ bitflags! {
         const KEYBOARD    = 1 << 2;
         const MOUSE       = 1 << 3;
         const MOUSE_BUTTON = 1 << 4;
+        const GAMEPAD     = 1 << 5;
+        const CUSTOM      = 1 << 6;
     }
 }
 
@@ -41,12 +60,32 @@ bitflags! {
 pub enum Event {
     WindowClose(WindowCloseEventData),
     WindowResize(WindowResizeEventData),
+    WindowFocus(WindowFocusEventData),
+    WindowLostFocus(WindowLostFocusEventData),
+    WindowMoved(WindowMovedEventData),
+    WindowMinimized(WindowMinimizedEventData),
+    WindowMaximized(WindowMaximizedEventData),
+    WindowRestored(WindowRestoredEventData),
+    WindowScaleChanged(WindowScaleChangedEventData),
+    MouseEntered(MouseEnteredEventData),
+    MouseLeft(MouseLeftEventData),
     KeyPressed(KeyPressedEventData),
     KeyReleased(KeyReleasedEventData),
     MouseMoved(MouseMovedEventData),
     MouseButtonPressed(MouseButtonPressedEventData),
     MouseButtonReleased(MouseButtonReleasedEventData),
     MouseScroll(MouseScrollEventData),
+    GamepadConnected(GamepadConnectedEventData),
+    GamepadDisconnected(GamepadDisconnectedEventData),
+    GamepadButtonPressed(GamepadButtonPressedEventData),
+    GamepadButtonReleased(GamepadButtonReleasedEventData),
+    GamepadAxisMoved(GamepadAxisMovedEventData),
+    TextInput(TextInputEventData),
+    TextEditing(TextEditingEventData),
+    DropBegin(DropBeginEventData),
+    FileDropped(FileDroppedEventData),
+    DropComplete(DropCompleteEventData),
+    Custom(CustomEventData),
 }
 
 impl Event {
@@ -54,12 +93,32 @@ impl Event {
         match self {
             Event::WindowClose(_) => EventType::WindowClose,
             Event::WindowResize(_) => EventType::WindowResize,
+            Event::WindowFocus(_) => EventType::WindowFocus,
+            Event::WindowLostFocus(_) => EventType::WindowLostFocus,
+            Event::WindowMoved(_) => EventType::WindowMoved,
+            Event::WindowMinimized(_) => EventType::WindowMinimized,
+            Event::WindowMaximized(_) => EventType::WindowMaximized,
+            Event::WindowRestored(_) => EventType::WindowRestored,
+            Event::WindowScaleChanged(_) => EventType::WindowScaleChanged,
+            Event::MouseEntered(_) => EventType::MouseEntered,
+            Event::MouseLeft(_) => EventType::MouseLeft,
             Event::KeyPressed(_) => EventType::KeyPressed,
             Event::KeyReleased(_) => EventType::KeyReleased,
             Event::MouseMoved(_) => EventType::MouseMoved,
             Event::MouseButtonPressed(_) => EventType::MouseButtonPressed,
             Event::MouseButtonReleased(_) => EventType::MouseButtonReleased,
             Event::MouseScroll(_) => EventType::MouseScroll,
+            Event::GamepadConnected(_) => EventType::GamepadConnected,
+            Event::GamepadDisconnected(_) => EventType::GamepadDisconnected,
+            Event::GamepadButtonPressed(_) => EventType::GamepadButtonPressed,
+            Event::GamepadButtonReleased(_) => EventType::GamepadButtonReleased,
+            Event::GamepadAxisMoved(_) => EventType::GamepadAxisMoved,
+            Event::TextInput(_) => EventType::TextInput,
+            Event::TextEditing(_) => EventType::TextEditing,
+            Event::DropBegin(_) => EventType::DropBegin,
+            Event::FileDropped(_) => EventType::FileDropped,
+            Event::DropComplete(_) => EventType::DropComplete,
+            Event::Custom(_) => EventType::Custom,
         }
     }
 
@@ -67,12 +126,32 @@ impl Event {
         match self {
             Event::WindowClose(_) => "WindowClose",
             Event::WindowResize(_) => "WindowResize",
+            Event::WindowFocus(_) => "WindowFocus",
+            Event::WindowLostFocus(_) => "WindowLostFocus",
+            Event::WindowMoved(_) => "WindowMoved",
+            Event::WindowMinimized(_) => "WindowMinimized",
+            Event::WindowMaximized(_) => "WindowMaximized",
+            Event::WindowRestored(_) => "WindowRestored",
+            Event::WindowScaleChanged(_) => "WindowScaleChanged",
+            Event::MouseEntered(_) => "MouseEntered",
+            Event::MouseLeft(_) => "MouseLeft",
             Event::KeyPressed(_) => "KeyPressed",
             Event::KeyReleased(_) => "KeyReleased",
             Event::MouseMoved(_) => "MouseMoved",
             Event::MouseButtonPressed(_) => "MouseButtonPressed",
             Event::MouseButtonReleased(_) => "MouseButtonReleased",
             Event::MouseScroll(_) => "MouseScroll",
+            Event::GamepadConnected(_) => "GamepadConnected",
+            Event::GamepadDisconnected(_) => "GamepadDisconnected",
+            Event::GamepadButtonPressed(_) => "GamepadButtonPressed",
+            Event::GamepadButtonReleased(_) => "GamepadButtonReleased",
+            Event::GamepadAxisMoved(_) => "GamepadAxisMoved",
+            Event::TextInput(_) => "TextInput",
+            Event::TextEditing(_) => "TextEditing",
+            Event::DropBegin(_) => "DropBegin",
+            Event::FileDropped(_) => "FileDropped",
+            Event::DropComplete(_) => "DropComplete",
+            Event::Custom(_) => "Custom",
         }
     }
 
@@ -80,12 +159,36 @@ impl Event {
         match self {
             Event::WindowClose(_) => EventCategory::APPLICATION,
             Event::WindowResize(_) => EventCategory::APPLICATION,
+            Event::WindowFocus(_) => EventCategory::APPLICATION,
+            Event::WindowLostFocus(_) => EventCategory::APPLICATION,
+            Event::WindowMoved(_) => EventCategory::APPLICATION,
+            Event::WindowMinimized(_) => EventCategory::APPLICATION,
+            Event::WindowMaximized(_) => EventCategory::APPLICATION,
+            Event::WindowRestored(_) => EventCategory::APPLICATION,
+            Event::WindowScaleChanged(_) => EventCategory::APPLICATION,
+            Event::MouseEntered(_) => EventCategory::APPLICATION | EventCategory::MOUSE,
+            Event::MouseLeft(_) => EventCategory::APPLICATION | EventCategory::MOUSE,
             Event::KeyPressed(_) => EventCategory::INPUT | EventCategory::KEYBOARD,
             Event::KeyReleased(_) => EventCategory::INPUT | EventCategory::KEYBOARD,
-            Event::MouseMoved(_) => EventCategory::MOUSE,
-            Event::MouseButtonPressed(_) => EventCategory::INPUT | EventCategory::MOUSE | EventCategory::MOUSE_BUTTON,
-            Event::MouseButtonReleased(_) => EventCategory::INPUT | EventCategory::MOUSE | EventCategory::MOUSE_BUTTON,
-            Event::MouseScroll(_) => EventCategory::MOUSE,
+            Event::MouseMoved(_) => EventCategory::INPUT | EventCategory::MOUSE,
+            Event::MouseButtonPressed(_) => {
+                EventCategory::INPUT | EventCategory::MOUSE | EventCategory::MOUSE_BUTTON
+            }
+            Event::MouseButtonReleased(_) => {
+                EventCategory::INPUT | EventCategory::MOUSE | EventCategory::MOUSE_BUTTON
+            }
+            Event::MouseScroll(_) => EventCategory::INPUT | EventCategory::MOUSE,
+            Event::GamepadConnected(_) => EventCategory::APPLICATION | EventCategory::GAMEPAD,
+            Event::GamepadDisconnected(_) => EventCategory::APPLICATION | EventCategory::GAMEPAD,
+            Event::GamepadButtonPressed(_) => EventCategory::INPUT | EventCategory::GAMEPAD,
+            Event::GamepadButtonReleased(_) => EventCategory::INPUT | EventCategory::GAMEPAD,
+            Event::GamepadAxisMoved(_) => EventCategory::INPUT | EventCategory::GAMEPAD,
+            Event::TextInput(_) => EventCategory::INPUT | EventCategory::KEYBOARD,
+            Event::TextEditing(_) => EventCategory::INPUT | EventCategory::KEYBOARD,
+            Event::DropBegin(_) => EventCategory::APPLICATION,
+            Event::FileDropped(_) => EventCategory::APPLICATION,
+            Event::DropComplete(_) => EventCategory::APPLICATION,
+            Event::Custom(_) => EventCategory::CUSTOM,
         }
     }
 
@@ -97,12 +200,32 @@ impl Event {
         match self {
             Event::WindowClose(data) => data.handled(),
             Event::WindowResize(data) => data.handled(),
+            Event::WindowFocus(data) => data.handled(),
+            Event::WindowLostFocus(data) => data.handled(),
+            Event::WindowMoved(data) => data.handled(),
+            Event::WindowMinimized(data) => data.handled(),
+            Event::WindowMaximized(data) => data.handled(),
+            Event::WindowRestored(data) => data.handled(),
+            Event::WindowScaleChanged(data) => data.handled(),
+            Event::MouseEntered(data) => data.handled(),
+            Event::MouseLeft(data) => data.handled(),
             Event::KeyPressed(data) => data.handled(),
             Event::KeyReleased(data) => data.handled(),
             Event::MouseMoved(data) => data.handled(),
             Event::MouseButtonPressed(data) => data.handled(),
             Event::MouseButtonReleased(data) => data.handled(),
             Event::MouseScroll(data) => data.handled(),
+            Event::GamepadConnected(data) => data.handled(),
+            Event::GamepadDisconnected(data) => data.handled(),
+            Event::GamepadButtonPressed(data) => data.handled(),
+            Event::GamepadButtonReleased(data) => data.handled(),
+            Event::GamepadAxisMoved(data) => data.handled(),
+            Event::TextInput(data) => data.handled(),
+            Event::TextEditing(data) => data.handled(),
+            Event::DropBegin(data) => data.handled(),
+            Event::FileDropped(data) => data.handled(),
+            Event::DropComplete(data) => data.handled(),
+            Event::Custom(data) => data.handled(),
         }
     }
 
@@ -110,12 +233,54 @@ impl Event {
         match self {
             Event::WindowClose(data) => data.set_handled(handled),
             Event::WindowResize(data) => data.set_handled(handled),
+            Event::WindowFocus(data) => data.set_handled(handled),
+            Event::WindowLostFocus(data) => data.set_handled(handled),
+            Event::WindowMoved(data) => data.set_handled(handled),
+            Event::WindowMinimized(data) => data.set_handled(handled),
+            Event::WindowMaximized(data) => data.set_handled(handled),
+            Event::WindowRestored(data) => data.set_handled(handled),
+            Event::WindowScaleChanged(data) => data.set_handled(handled),
+            Event::MouseEntered(data) => data.set_handled(handled),
+            Event::MouseLeft(data) => data.set_handled(handled),
             Event::KeyPressed(data) => data.set_handled(handled),
             Event::KeyReleased(data) => data.set_handled(handled),
             Event::MouseMoved(data) => data.set_handled(handled),
             Event::MouseButtonPressed(data) => data.set_handled(handled),
             Event::MouseButtonReleased(data) => data.set_handled(handled),
             Event::MouseScroll(data) => data.set_handled(handled),
+            Event::GamepadConnected(data) => data.set_handled(handled),
+            Event::GamepadDisconnected(data) => data.set_handled(handled),
+            Event::GamepadButtonPressed(data) => data.set_handled(handled),
+            Event::GamepadButtonReleased(data) => data.set_handled(handled),
+            Event::GamepadAxisMoved(data) => data.set_handled(handled),
+            Event::TextInput(data) => data.set_handled(handled),
+            Event::TextEditing(data) => data.set_handled(handled),
+            Event::DropBegin(data) => data.set_handled(handled),
+            Event::FileDropped(data) => data.set_handled(handled),
+            Event::DropComplete(data) => data.set_handled(handled),
+            Event::Custom(data) => data.set_handled(handled),
+        }
+    }
+
+    // Wrap a game value to post it with the window events
+    pub fn custom<T: Any>(payload: T) -> Self {
+        Event::Custom(CustomEventData::new(payload))
+    }
+
+    // Payload of the event if it is a `T`
+    pub fn data<T: EventData>(&self) -> Option<&T> {
+        T::from_event(self)
+    }
+
+    pub fn data_mut<T: EventData>(&mut self) -> Option<&mut T> {
+        T::from_event_mut(self)
+    }
+
+    // Payload of a custom event of type `T`
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match self {
+            Event::Custom(data) => data.get::<T>(),
+            _ => None,
         }
     }
 
@@ -135,42 +300,219 @@ impl fmt::Display for Event {
                 data.get_height(),
                 data.handled()
             ),
+            Event::WindowFocus(data) => write!(f, "WindowFocusEvent: handled={}", data.handled()),
+            Event::WindowLostFocus(data) => {
+                write!(f, "WindowLostFocusEvent: handled={}", data.handled())
+            }
+            Event::WindowMoved(data) => write!(
+                f,
+                "WindowMovedEvent: x={}, y={}, handled={}",
+                data.get_x(),
+                data.get_y(),
+                data.handled()
+            ),
+            Event::WindowMinimized(data) => {
+                write!(f, "WindowMinimizedEvent: handled={}", data.handled())
+            }
+            Event::WindowMaximized(data) => {
+                write!(f, "WindowMaximizedEvent: handled={}", data.handled())
+            }
+            Event::WindowRestored(data) => {
+                write!(f, "WindowRestoredEvent: handled={}", data.handled())
+            }
+            Event::WindowScaleChanged(data) => write!(
+                f,
+                "WindowScaleChangedEvent: scale={}, handled={}",
+                data.get_scale(),
+                data.handled()
+            ),
+            Event::MouseEntered(data) => write!(f, "MouseEnteredEvent: handled={}", data.handled()),
+            Event::MouseLeft(data) => write!(f, "MouseLeftEvent: handled={}", data.handled()),
             Event::KeyPressed(data) => write!(
                 f,
-                "KeyPressedEvent: keycode={}, repeat={}, handled={}",
+                "KeyPressedEvent: keycode={}, scancode={}, modifiers={}, repeat={}, handled={}",
                 data.get_key_code(),
+                data.get_scan_code(),
+                data.get_modifiers(),
                 data.get_repeat_count(),
                 data.handled()
             ),
             Event::KeyReleased(data) => write!(
                 f,
-                "KeyReleasedEvent: keycode={}, handled={}",
-                data.get_key_code(), data.handled()
+                "KeyReleasedEvent: keycode={}, scancode={}, modifiers={}, handled={}",
+                data.get_key_code(),
+                data.get_scan_code(),
+                data.get_modifiers(),
+                data.handled()
             ),
             Event::MouseMoved(data) => write!(
                 f,
-                "MouseMovedEvent: x={}, y={}, handled={}",
-                data.get_x_pos(), data.get_y_pos(), data.handled()
+                "MouseMovedEvent: x={}, y={}, dx={}, dy={}, handled={}",
+                data.get_x_pos(),
+                data.get_y_pos(),
+                data.get_x_delta(),
+                data.get_y_delta(),
+                data.handled()
             ),
             Event::MouseButtonPressed(data) => write!(
                 f,
-                "MouseButtonPressedEvent: button_code={}, x={}, y={}, handled={}",
-                data.get_button_code(), data.get_button_pos().0, data.get_button_pos().1,  data.handled()
+                "MouseButtonPressedEvent: button={}, x={}, y={}, clicks={}, handled={}",
+                data.get_button(),
+                data.get_button_pos().0,
+                data.get_button_pos().1,
+                data.get_clicks(),
+                data.handled()
             ),
             Event::MouseButtonReleased(data) => write!(
                 f,
-                "MouseButtonReleasedEvent: button_code={}, handled={}",
-                data.get_button_code(), data.handled()
+                "MouseButtonReleasedEvent: button={}, x={}, y={}, handled={}",
+                data.get_button(),
+                data.get_button_pos().0,
+                data.get_button_pos().1,
+                data.handled()
             ),
             Event::MouseScroll(data) => write!(
                 f,
                 "MouseScrollEvent: x={}, y={}, handled={}",
-                data.get_x_offset(), data.get_y_offset(), data.handled()
+                data.get_x_offset(),
+                data.get_y_offset(),
+                data.handled()
+            ),
+            Event::GamepadConnected(data) => write!(
+                f,
+                "GamepadConnectedEvent: id={}, name={}, handled={}",
+                data.get_id(),
+                data.get_name(),
+                data.handled()
+            ),
+            Event::GamepadDisconnected(data) => write!(
+                f,
+                "GamepadDisconnectedEvent: id={}, handled={}",
+                data.get_id(),
+                data.handled()
+            ),
+            Event::GamepadButtonPressed(data) => write!(
+                f,
+                "GamepadButtonPressedEvent: id={}, button={}, handled={}",
+                data.get_id(),
+                data.get_button(),
+                data.handled()
+            ),
+            Event::GamepadButtonReleased(data) => write!(
+                f,
+                "GamepadButtonReleasedEvent: id={}, button={}, handled={}",
+                data.get_id(),
+                data.get_button(),
+                data.handled()
+            ),
+            Event::GamepadAxisMoved(data) => write!(
+                f,
+                "GamepadAxisMovedEvent: id={}, axis={}, value={}, handled={}",
+                data.get_id(),
+                data.get_axis(),
+                data.get_value(),
+                data.handled()
+            ),
+            Event::TextInput(data) => write!(
+                f,
+                "TextInputEvent: text={:?}, handled={}",
+                data.get_text(),
+                data.handled()
+            ),
+            Event::TextEditing(data) => write!(
+                f,
+                "TextEditingEvent: text={:?}, start={}, length={}, handled={}",
+                data.get_text(),
+                data.get_start(),
+                data.get_length(),
+                data.handled()
+            ),
+            Event::DropBegin(data) => write!(f, "DropBeginEvent: handled={}", data.handled()),
+            Event::FileDropped(data) => write!(
+                f,
+                "FileDroppedEvent: path={}, handled={}",
+                data.get_path().display(),
+                data.handled()
+            ),
+            Event::DropComplete(data) => {
+                write!(f, "DropCompleteEvent: handled={}", data.handled())
+            }
+            Event::Custom(data) => write!(
+                f,
+                "CustomEvent: type={}, handled={}",
+                data.get_name(),
+                data.handled()
             ),
         }
     }
 }
 
+// Implemented by every `*EventData`, to get the typed payload out of an `Event`
+pub trait EventData: Sized {
+    fn event_type() -> EventType;
+    fn from_event(event: &Event) -> Option<&Self>;
+    fn from_event_mut(event: &mut Event) -> Option<&mut Self>;
+}
+
+macro_rules! event_data {
+    ($($variant:ident => $data:ty),* $(,)?) => {
+        $(
+            impl EventData for $data {
+                fn event_type() -> EventType {
+                    EventType::$variant
+                }
+
+                fn from_event(event: &Event) -> Option<&Self> {
+                    match event {
+                        Event::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+
+                fn from_event_mut(event: &mut Event) -> Option<&mut Self> {
+                    match event {
+                        Event::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+event_data! {
+    WindowClose => WindowCloseEventData,
+    WindowResize => WindowResizeEventData,
+    WindowFocus => WindowFocusEventData,
+    WindowLostFocus => WindowLostFocusEventData,
+    WindowMoved => WindowMovedEventData,
+    WindowMinimized => WindowMinimizedEventData,
+    WindowMaximized => WindowMaximizedEventData,
+    WindowRestored => WindowRestoredEventData,
+    WindowScaleChanged => WindowScaleChangedEventData,
+    MouseEntered => MouseEnteredEventData,
+    MouseLeft => MouseLeftEventData,
+    KeyPressed => KeyPressedEventData,
+    KeyReleased => KeyReleasedEventData,
+    MouseMoved => MouseMovedEventData,
+    MouseButtonPressed => MouseButtonPressedEventData,
+    MouseButtonReleased => MouseButtonReleasedEventData,
+    MouseScroll => MouseScrollEventData,
+    GamepadConnected => GamepadConnectedEventData,
+    GamepadDisconnected => GamepadDisconnectedEventData,
+    GamepadButtonPressed => GamepadButtonPressedEventData,
+    GamepadButtonReleased => GamepadButtonReleasedEventData,
+    GamepadAxisMoved => GamepadAxisMovedEventData,
+    TextInput => TextInputEventData,
+    TextEditing => TextEditingEventData,
+    DropBegin => DropBeginEventData,
+    FileDropped => FileDroppedEventData,
+    DropComplete => DropCompleteEventData,
+    Custom => CustomEventData,
+}
+
+// Runs the handlers matching the event, in order. Handlers return whether they handled
+// the event, the ones after an handled event are skipped
 pub struct EventDispatcher {}
 
 impl EventDispatcher {
@@ -178,12 +520,173 @@ impl EventDispatcher {
         Self {}
     }
 
-    pub fn dispatch<F>(&mut self, event: &mut Event, mut func: F) -> bool
+    // Any event
+    pub fn dispatch<F>(&mut self, event: &mut Event, func: F) -> bool
     where
-        F: FnMut(&mut Event) -> bool,
+        F: FnOnce(&mut Event) -> bool,
     {
+        if event.handled() {
+            return false;
+        }
         let handled = func(event);
         event.set_handled(handled);
         handled
     }
+
+    // Events of type `T`, e.g. `dispatcher.on::<KeyPressedEventData>(event, |data| ...)`
+    pub fn on<T: EventData>(
+        &mut self,
+        event: &mut Event,
+        func: impl FnOnce(&mut T) -> bool,
+    ) -> bool {
+        if event.handled() {
+            return false;
+        }
+        let handled = match T::from_event_mut(event) {
+            Some(data) => func(data),
+            None => return false,
+        };
+        event.set_handled(handled);
+        handled
+    }
+
+    // Events in every flag of `category`, e.g. `EventCategory::INPUT | EventCategory::MOUSE`
+    pub fn on_category<F>(&mut self, event: &mut Event, category: EventCategory, func: F) -> bool
+    where
+        F: FnOnce(&mut Event) -> bool,
+    {
+        if !event.is_in_category(category) {
+            return false;
+        }
+        self.dispatch(event, func)
+    }
+
+    // Custom events carrying a `T`
+    pub fn on_custom<T: Any>(
+        &mut self,
+        event: &mut Event,
+        func: impl FnOnce(&mut T) -> bool,
+    ) -> bool {
+        if event.handled() {
+            return false;
+        }
+        let handled =
+            match CustomEventData::from_event_mut(event).and_then(|data| data.get_mut::<T>()) {
+                Some(payload) => func(payload),
+                None => return false,
+            };
+        event.set_handled(handled);
+        handled
+    }
+}
+
+impl Default for EventDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::input::{KeyCode, Modifiers, MouseButton, ScanCode};
+
+    fn key_pressed() -> Event {
+        Event::KeyPressed(KeyPressedEventData::new(
+            KeyCode::Space,
+            ScanCode::Space,
+            Modifiers::NONE,
+            0,
+        ))
+    }
+
+    #[test]
+    fn on_runs_for_its_payload_type_only() {
+        let mut event = key_pressed();
+        let mut dispatcher = EventDispatcher::new();
+        assert!(!dispatcher.on::<MouseButtonPressedEventData>(&mut event, |_| true));
+        assert!(!event.handled());
+
+        let mut key = None;
+        assert!(dispatcher.on::<KeyPressedEventData>(&mut event, |data| {
+            key = Some(data.get_key_code());
+            true
+        }));
+        assert_eq!(key, Some(KeyCode::Space));
+        assert!(event.handled());
+    }
+
+    #[test]
+    fn handlers_returning_false_let_the_next_ones_run() {
+        let mut event = key_pressed();
+        let mut dispatcher = EventDispatcher::new();
+        let mut calls = 0;
+        dispatcher.on::<KeyPressedEventData>(&mut event, |_| {
+            calls += 1;
+            false
+        });
+        dispatcher.dispatch(&mut event, |_| {
+            calls += 1;
+            false
+        });
+        assert_eq!(calls, 2);
+        assert!(!event.handled());
+    }
+
+    #[test]
+    fn handled_events_skip_every_handler() {
+        let mut event = key_pressed();
+        event.set_handled(true);
+        let mut dispatcher = EventDispatcher::new();
+        assert!(!dispatcher.dispatch(&mut event, |_| panic!("dispatch ran")));
+        assert!(!dispatcher.on::<KeyPressedEventData>(&mut event, |_| panic!("on ran")));
+        let keyboard = EventCategory::KEYBOARD;
+        assert!(!dispatcher.on_category(&mut event, keyboard, |_| panic!("on_category ran")));
+        // Still handled, the skipped handlers do not reset it
+        assert!(event.handled());
+
+        let mut event = Event::custom(1u32);
+        event.set_handled(true);
+        assert!(!EventDispatcher::new().on_custom::<u32>(&mut event, |_| panic!("on_custom ran")));
+    }
+
+    #[test]
+    fn on_category_needs_every_flag() {
+        let mut event =
+            Event::MouseButtonPressed(MouseButtonPressedEventData::new(MouseButton::Left, 0, 0, 1));
+        let mut dispatcher = EventDispatcher::new();
+        assert!(!dispatcher.on_category(&mut event, EventCategory::KEYBOARD, |_| true));
+        assert!(!dispatcher.on_category(
+            &mut event,
+            EventCategory::MOUSE | EventCategory::GAMEPAD,
+            |_| true
+        ));
+        assert!(!event.handled());
+        assert!(dispatcher.on_category(
+            &mut event,
+            EventCategory::INPUT | EventCategory::MOUSE,
+            |_| true
+        ));
+        assert!(event.handled());
+    }
+
+    #[test]
+    fn on_custom_matches_the_payload_type() {
+        #[derive(Debug, PartialEq)]
+        struct Score(u32);
+
+        let mut event = Event::custom(Score(1));
+        let mut dispatcher = EventDispatcher::new();
+        assert!(!dispatcher.on_custom::<u32>(&mut event, |_| true));
+        assert!(!dispatcher.on::<KeyPressedEventData>(&mut event, |_| true));
+        assert!(dispatcher.on_custom::<Score>(&mut event, |score| {
+            score.0 += 1;
+            true
+        }));
+        assert!(event.handled());
+        assert_eq!(event.downcast_ref::<Score>(), Some(&Score(2)));
+        assert!(event.is_in_category(EventCategory::CUSTOM));
+        assert!(event.data::<CustomEventData>().is_some());
+        assert!(event.data::<KeyPressedEventData>().is_none());
+    }
 }
@@ -1,5 +1,10 @@
 // This module contains event types related to application events, such as closing and resizing.
 
+use std::any::{type_name, Any};
+use std::path::{Path, PathBuf};
+
+use super::input::{GamepadAxis, GamepadButton, KeyCode, Modifiers, MouseButton, ScanCode};
+
 // - WindowCloseEvent: Represents a request to close the application window.
 #[derive(Debug, Default, Copy, Clone)]
 pub struct WindowCloseEventData {
@@ -50,15 +55,208 @@ impl WindowResizeEventData {
     }
 }
 
+// - WindowFocusEvent: The window gained the keyboard focus.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WindowFocusEventData {
+    handled: bool,
+}
+
+impl WindowFocusEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - WindowLostFocusEvent: The window lost the keyboard focus.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WindowLostFocusEventData {
+    handled: bool,
+}
+
+impl WindowLostFocusEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - WindowMovedEvent: The window moved, with the new position of its top left corner on the desktop.
+#[derive(Debug, Copy, Clone)]
+pub struct WindowMovedEventData {
+    x: i32,
+    y: i32,
+    handled: bool,
+}
+
+impl WindowMovedEventData {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> i32 {
+        self.y
+    }
+}
+
+// - WindowMinimizedEvent: The window was minimized.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WindowMinimizedEventData {
+    handled: bool,
+}
+
+impl WindowMinimizedEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - WindowMaximizedEvent: The window was maximized.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WindowMaximizedEventData {
+    handled: bool,
+}
+
+impl WindowMaximizedEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - WindowRestoredEvent: The window left the minimized or maximized state.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WindowRestoredEventData {
+    handled: bool,
+}
+
+impl WindowRestoredEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - WindowScaleChangedEvent: The display scale of the window changed, usually by moving to another monitor.
+#[derive(Debug, Copy, Clone)]
+pub struct WindowScaleChangedEventData {
+    scale: f32,
+    handled: bool,
+}
+
+impl WindowScaleChangedEventData {
+    pub fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    // 1.0 at 96 DPI, 2.0 on a HiDPI display
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+// - MouseEnteredEvent: The mouse entered the window.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MouseEnteredEventData {
+    handled: bool,
+}
+
+impl MouseEnteredEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - MouseLeftEvent: The mouse left the window.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MouseLeftEventData {
+    handled: bool,
+}
+
+impl MouseLeftEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
 // - KeyPressedEvent: Contains information about the key pressed, including its code and repeat count.
 #[derive(Debug)]
 pub struct KeyPressedEventData {
-    key_code: u32,
+    key_code: KeyCode,
+    scan_code: ScanCode,
+    modifiers: Modifiers,
     repeat_count: u32,
     handled: bool,
 }
 
 impl KeyPressedEventData {
+    // `repeat_count` is 0 for the first press, then counts the key repeats while held
+    pub fn new(
+        key_code: KeyCode,
+        scan_code: ScanCode,
+        modifiers: Modifiers,
+        repeat_count: u32,
+    ) -> Self {
+        Self {
+            key_code,
+            scan_code,
+            modifiers,
+            repeat_count,
+            handled: false,
+        }
+    }
+
     pub fn handled(&self) -> bool {
         self.handled
     }
@@ -67,23 +265,46 @@ impl KeyPressedEventData {
         self.handled = handled;
     }
 
-    pub fn get_key_code(&self) -> u32 {
+    pub fn get_key_code(&self) -> KeyCode {
         self.key_code
     }
 
+    pub fn get_scan_code(&self) -> ScanCode {
+        self.scan_code
+    }
+
+    pub fn get_modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
     pub fn get_repeat_count(&self) -> u32 {
         self.repeat_count
     }
+
+    pub fn is_repeat(&self) -> bool {
+        self.repeat_count > 0
+    }
 }
 
 // - KeyReleasedEvent: Contains information about the key released, including its code.
 #[derive(Debug)]
 pub struct KeyReleasedEventData {
-    key_code: u32,
+    key_code: KeyCode,
+    scan_code: ScanCode,
+    modifiers: Modifiers,
     handled: bool,
 }
 
 impl KeyReleasedEventData {
+    pub fn new(key_code: KeyCode, scan_code: ScanCode, modifiers: Modifiers) -> Self {
+        Self {
+            key_code,
+            scan_code,
+            modifiers,
+            handled: false,
+        }
+    }
+
     pub fn handled(&self) -> bool {
         self.handled
     }
@@ -92,9 +313,17 @@ impl KeyReleasedEventData {
         self.handled = handled;
     }
 
-    pub fn get_key_code(&self) -> u32 {
+    pub fn get_key_code(&self) -> KeyCode {
         self.key_code
     }
+
+    pub fn get_scan_code(&self) -> ScanCode {
+        self.scan_code
+    }
+
+    pub fn get_modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
 }
 
 // - MouseMovedEvent: Contains information about the mouse movement, including its position.
@@ -102,10 +331,23 @@ impl KeyReleasedEventData {
 pub struct MouseMovedEventData {
     x_pos: f32,
     y_pos: f32,
+    x_delta: f32,
+    y_delta: f32,
     handled: bool,
 }
 
 impl MouseMovedEventData {
+    // Position in window pixels from the top left corner, delta since the last motion
+    pub fn new(x_pos: f32, y_pos: f32, x_delta: f32, y_delta: f32) -> Self {
+        Self {
+            x_pos,
+            y_pos,
+            x_delta,
+            y_delta,
+            handled: false,
+        }
+    }
+
     pub fn handled(&self) -> bool {
         self.handled
     }
@@ -121,12 +363,20 @@ impl MouseMovedEventData {
     pub fn get_y_pos(&self) -> f32 {
         self.y_pos
     }
+
+    pub fn get_x_delta(&self) -> f32 {
+        self.x_delta
+    }
+
+    pub fn get_y_delta(&self) -> f32 {
+        self.y_delta
+    }
 }
 
 // - MouseButtonPressedEvent: Contains information about the mouse button pressed, including its code.
 #[derive(Debug)]
 pub struct MouseButtonPressedEventData {
-    button_code: u32,
+    button: MouseButton,
     x: i32,
     y: i32,
     clicks: u32,
@@ -134,12 +384,12 @@ pub struct MouseButtonPressedEventData {
 }
 
 impl MouseButtonPressedEventData {
-    pub fn new(button_code: u32, x: i32, y: i32, clicks: u32) -> Self {
+    pub fn new(button: MouseButton, x: i32, y: i32, clicks: u32) -> Self {
         Self {
-            button_code: button_code,
-            x: x,
-            y: y,
-            clicks: clicks,
+            button,
+            x,
+            y,
+            clicks,
             handled: false,
         }
     }
@@ -152,23 +402,39 @@ impl MouseButtonPressedEventData {
         self.handled = handled;
     }
 
-    pub fn get_button_code(&self) -> u32 {
-        self.button_code
+    pub fn get_button(&self) -> MouseButton {
+        self.button
     }
 
     pub fn get_button_pos(&self) -> (i32, i32) {
         (self.x, self.y)
     }
+
+    // 1 for a single click, 2 for a double click...
+    pub fn get_clicks(&self) -> u32 {
+        self.clicks
+    }
 }
 
 // - MouseButtonReleasedEvent: Contains information about the mouse button released, including its code.
 #[derive(Debug)]
 pub struct MouseButtonReleasedEventData {
-    button_code: u32,
+    button: MouseButton,
+    x: i32,
+    y: i32,
     handled: bool,
 }
 
 impl MouseButtonReleasedEventData {
+    pub fn new(button: MouseButton, x: i32, y: i32) -> Self {
+        Self {
+            button,
+            x,
+            y,
+            handled: false,
+        }
+    }
+
     pub fn handled(&self) -> bool {
         self.handled
     }
@@ -177,8 +443,12 @@ impl MouseButtonReleasedEventData {
         self.handled = handled;
     }
 
-    pub fn get_button_code(&self) -> u32 {
-        self.button_code
+    pub fn get_button(&self) -> MouseButton {
+        self.button
+    }
+
+    pub fn get_button_pos(&self) -> (i32, i32) {
+        (self.x, self.y)
     }
 }
 
@@ -191,6 +461,15 @@ pub struct MouseScrollEventData {
 }
 
 impl MouseScrollEventData {
+    // Positive offsets scroll right and away from the user
+    pub fn new(x_offset: f32, y_offset: f32) -> Self {
+        Self {
+            x_offset,
+            y_offset,
+            handled: false,
+        }
+    }
+
     pub fn handled(&self) -> bool {
         self.handled
     }
@@ -207,3 +486,347 @@ impl MouseScrollEventData {
         self.y_offset
     }
 }
+
+// - GamepadConnectedEvent: A gamepad was plugged in, or was already when the application started.
+#[derive(Debug, Clone)]
+pub struct GamepadConnectedEventData {
+    id: u32,
+    name: String,
+    handled: bool,
+}
+
+impl GamepadConnectedEventData {
+    pub fn new(id: u32, name: String) -> Self {
+        Self {
+            id,
+            name,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    // Stays the same until the gamepad is disconnected
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+// - GamepadDisconnectedEvent: A gamepad was unplugged.
+#[derive(Debug, Copy, Clone)]
+pub struct GamepadDisconnectedEventData {
+    id: u32,
+    handled: bool,
+}
+
+impl GamepadDisconnectedEventData {
+    pub fn new(id: u32) -> Self {
+        Self { id, handled: false }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+// - GamepadButtonPressedEvent: Contains the gamepad and the button pressed.
+#[derive(Debug, Copy, Clone)]
+pub struct GamepadButtonPressedEventData {
+    id: u32,
+    button: GamepadButton,
+    handled: bool,
+}
+
+impl GamepadButtonPressedEventData {
+    pub fn new(id: u32, button: GamepadButton) -> Self {
+        Self {
+            id,
+            button,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_button(&self) -> GamepadButton {
+        self.button
+    }
+}
+
+// - GamepadButtonReleasedEvent: Contains the gamepad and the button released.
+#[derive(Debug, Copy, Clone)]
+pub struct GamepadButtonReleasedEventData {
+    id: u32,
+    button: GamepadButton,
+    handled: bool,
+}
+
+impl GamepadButtonReleasedEventData {
+    pub fn new(id: u32, button: GamepadButton) -> Self {
+        Self {
+            id,
+            button,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_button(&self) -> GamepadButton {
+        self.button
+    }
+}
+
+// - GamepadAxisMovedEvent: Contains the gamepad, the axis and its new value.
+#[derive(Debug, Copy, Clone)]
+pub struct GamepadAxisMovedEventData {
+    id: u32,
+    axis: GamepadAxis,
+    value: f32,
+    handled: bool,
+}
+
+impl GamepadAxisMovedEventData {
+    pub fn new(id: u32, axis: GamepadAxis, value: f32) -> Self {
+        Self {
+            id,
+            axis,
+            value,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_axis(&self) -> GamepadAxis {
+        self.axis
+    }
+
+    // -1.0 to 1.0 for the sticks, 0.0 to 1.0 for the triggers
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+}
+
+// - TextInputEvent: Text typed or committed by the IME, as UTF-8. Sent while text input is started.
+#[derive(Debug, Clone)]
+pub struct TextInputEventData {
+    text: String,
+    handled: bool,
+}
+
+impl TextInputEventData {
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+}
+
+// - TextEditingEvent: Text being composed in the IME, not committed yet. Replaces the previous
+//   composition, an empty text ends it.
+#[derive(Debug, Clone)]
+pub struct TextEditingEventData {
+    text: String,
+    start: i32,
+    length: i32,
+    handled: bool,
+}
+
+impl TextEditingEventData {
+    pub fn new(text: String, start: i32, length: i32) -> Self {
+        Self {
+            text,
+            start,
+            length,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    // Cursor position in the composition, in characters
+    pub fn get_start(&self) -> i32 {
+        self.start
+    }
+
+    // Characters selected from the cursor
+    pub fn get_length(&self) -> i32 {
+        self.length
+    }
+}
+
+// - DropBeginEvent: Files are about to be dropped on the window, one FileDropped event each follows.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DropBeginEventData {
+    handled: bool,
+}
+
+impl DropBeginEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - FileDroppedEvent: A file was dragged onto the window, with its path.
+#[derive(Debug, Clone)]
+pub struct FileDroppedEventData {
+    path: PathBuf,
+    handled: bool,
+}
+
+impl FileDroppedEventData {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// - DropCompleteEvent: Every file of the drop was sent.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DropCompleteEventData {
+    handled: bool,
+}
+
+impl DropCompleteEventData {
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+// - CustomEvent: Any value posted by the game, e.g. a `PlayerDied`, propagated like the
+// window events.
+#[derive(Debug)]
+pub struct CustomEventData {
+    name: &'static str,
+    payload: Box<dyn Any>,
+    handled: bool,
+}
+
+impl CustomEventData {
+    pub fn new<T: Any>(payload: T) -> Self {
+        Self {
+            name: type_name::<T>(),
+            payload: Box::new(payload),
+            handled: false,
+        }
+    }
+
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+
+    pub fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+
+    // Type name of the payload
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.is::<T>()
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref::<T>()
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.payload.downcast_mut::<T>()
+    }
+}
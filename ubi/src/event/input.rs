@@ -0,0 +1,565 @@
+use bitflags::bitflags;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+
+use super::event::Event;
+
+// Enum of plain variants, displayed and parsed by the variant names as in binding files
+macro_rules! named_enum {
+    ($name:ident { $($variant:ident),* $(,)? }) => {
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum $name {
+            $($variant,)*
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($variant),)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($variant) => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.name())
+            }
+        }
+    };
+}
+
+// KeyCode and ScanCode name the same keys. A key code is the key as labeled by the keyboard
+// layout, for shortcuts and text related keys. A scan code is the physical key, named after
+// its position on a US keyboard, for movement and the like.
+macro_rules! keys {
+    ($($key:ident),* $(,)?) => {
+        named_enum!(KeyCode { $($key,)* Unknown });
+        named_enum!(ScanCode { $($key,)* Unknown });
+    };
+}
+
+keys! {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9,
+    Enter, Escape, Backspace, Tab, Space,
+    Minus, Equals, LeftBracket, RightBracket, Backslash, Semicolon, Apostrophe, Grave, Comma,
+    Period, Slash,
+    CapsLock, ScrollLock, NumLock, PrintScreen, Pause, Menu,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+    Insert, Delete, Home, End, PageUp, PageDown,
+    Left, Right, Up, Down,
+    Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9,
+    KpDivide, KpMultiply, KpMinus, KpPlus, KpEnter, KpPeriod, KpEquals,
+    LShift, RShift, LCtrl, RCtrl, LAlt, RAlt, LSuper, RSuper,
+}
+
+// X1 and X2 are the side buttons, usually back and forward
+named_enum!(MouseButton {
+    Left,
+    Middle,
+    Right,
+    X1,
+    X2,
+    Unknown,
+});
+
+// Buttons by position, South is A on an Xbox controller and Cross on a PlayStation one
+named_enum!(GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Misc,
+    Paddle1,
+    Paddle2,
+    Paddle3,
+    Paddle4,
+    Touchpad,
+});
+
+// Sticks go from -1.0 to 1.0 with y down, triggers from 0.0 to 1.0
+named_enum!(GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+});
+
+bitflags! {
+    // Modifier keys held, and lock keys on, when the event happened. Either side sets the flag.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+    pub struct Modifiers: u32 {
+        const NONE      = 0;
+        const SHIFT     = 1 << 0;
+        const CTRL      = 1 << 1;
+        const ALT       = 1 << 2;
+        const SUPER     = 1 << 3;
+        const CAPS_LOCK = 1 << 4;
+        const NUM_LOCK  = 1 << 5;
+    }
+}
+
+impl fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "NONE");
+        }
+        bitflags::parser::to_writer(self, f)
+    }
+}
+
+// Held, just pressed and just released buttons of one kind
+#[derive(Debug, Clone)]
+struct ButtonState<T> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> ButtonState<T> {
+    fn new() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+
+    fn press(&mut self, button: T) {
+        if self.pressed.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.just_released.extend(self.pressed.drain());
+    }
+
+    fn begin_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+// Buttons and axes of one gamepad
+#[derive(Debug, Clone)]
+pub struct GamepadState {
+    name: String,
+    buttons: ButtonState<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+impl GamepadState {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            buttons: ButtonState::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn button_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.pressed.contains(&button)
+    }
+
+    pub fn button_just_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.just_pressed.contains(&button)
+    }
+
+    pub fn button_just_released(&self, button: GamepadButton) -> bool {
+        self.buttons.just_released.contains(&button)
+    }
+
+    // Raw value, without dead zone
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+// Keyboard, mouse and gamepad state for systems polling it each frame, fed with the same
+// events the layers receive. Presses, motion and wheel marked handled by a layer are ignored,
+// releases always count so a key never stays stuck. Losing the focus releases everything.
+#[derive(Debug, Clone)]
+pub struct Input {
+    keys: ButtonState<KeyCode>,
+    scan_codes: ButtonState<ScanCode>,
+    mouse_buttons: ButtonState<MouseButton>,
+    // By gamepad id, SDL gives increasing ids so in connection order
+    gamepads: BTreeMap<u32, GamepadState>,
+    modifiers: Modifiers,
+    mouse_position: (f32, f32),
+    mouse_delta: (f32, f32),
+    scroll_delta: (f32, f32),
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self {
+            keys: ButtonState::new(),
+            scan_codes: ButtonState::new(),
+            mouse_buttons: ButtonState::new(),
+            gamepads: BTreeMap::new(),
+            modifiers: Modifiers::NONE,
+            mouse_position: (0.0, 0.0),
+            mouse_delta: (0.0, 0.0),
+            scroll_delta: (0.0, 0.0),
+        }
+    }
+
+    // Forget the transitions and deltas of the previous frame, before its events
+    pub fn begin_frame(&mut self) {
+        self.keys.begin_frame();
+        self.scan_codes.begin_frame();
+        self.mouse_buttons.begin_frame();
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.buttons.begin_frame();
+        }
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll_delta = (0.0, 0.0);
+    }
+
+    // Feed an event after the layers saw it
+    pub fn handle_event(&mut self, event: &Event) {
+        let handled = event.handled();
+        match event {
+            Event::KeyPressed(data) => {
+                if !handled {
+                    self.keys.press(data.get_key_code());
+                    self.scan_codes.press(data.get_scan_code());
+                }
+                self.modifiers = data.get_modifiers();
+            }
+            Event::KeyReleased(data) => {
+                self.keys.release(data.get_key_code());
+                self.scan_codes.release(data.get_scan_code());
+                self.modifiers = data.get_modifiers();
+            }
+            Event::MouseButtonPressed(data) if !handled => {
+                self.mouse_buttons.press(data.get_button());
+            }
+            Event::MouseButtonReleased(data) => self.mouse_buttons.release(data.get_button()),
+            Event::MouseMoved(data) => {
+                // The position stays current, only the motion is consumed
+                self.mouse_position = (data.get_x_pos(), data.get_y_pos());
+                if !handled {
+                    self.mouse_delta.0 += data.get_x_delta();
+                    self.mouse_delta.1 += data.get_y_delta();
+                }
+            }
+            Event::MouseScroll(data) if !handled => {
+                self.scroll_delta.0 += data.get_x_offset();
+                self.scroll_delta.1 += data.get_y_offset();
+            }
+            Event::GamepadConnected(data) => {
+                self.gamepads.insert(
+                    data.get_id(),
+                    GamepadState::new(data.get_name().to_string()),
+                );
+            }
+            Event::GamepadDisconnected(data) => {
+                self.gamepads.remove(&data.get_id());
+            }
+            Event::GamepadButtonPressed(data) if !handled => {
+                if let Some(gamepad) = self.gamepads.get_mut(&data.get_id()) {
+                    gamepad.buttons.press(data.get_button());
+                }
+            }
+            Event::GamepadButtonReleased(data) => {
+                if let Some(gamepad) = self.gamepads.get_mut(&data.get_id()) {
+                    gamepad.buttons.release(data.get_button());
+                }
+            }
+            Event::GamepadAxisMoved(data) => {
+                if let Some(gamepad) = self.gamepads.get_mut(&data.get_id()) {
+                    gamepad.axes.insert(data.get_axis(), data.get_value());
+                }
+            }
+            Event::WindowLostFocus(_) => {
+                self.keys.release_all();
+                self.scan_codes.release_all();
+                self.mouse_buttons.release_all();
+                for gamepad in self.gamepads.values_mut() {
+                    gamepad.buttons.release_all();
+                }
+                self.modifiers = Modifiers::NONE;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys.pressed.contains(&key)
+    }
+
+    // Pressed during the last frame, key repeats excluded
+    pub fn key_just_pressed(&self, key: KeyCode) -> bool {
+        self.keys.just_pressed.contains(&key)
+    }
+
+    pub fn key_just_released(&self, key: KeyCode) -> bool {
+        self.keys.just_released.contains(&key)
+    }
+
+    pub fn scan_code_pressed(&self, scan_code: ScanCode) -> bool {
+        self.scan_codes.pressed.contains(&scan_code)
+    }
+
+    pub fn scan_code_just_pressed(&self, scan_code: ScanCode) -> bool {
+        self.scan_codes.just_pressed.contains(&scan_code)
+    }
+
+    pub fn scan_code_just_released(&self, scan_code: ScanCode) -> bool {
+        self.scan_codes.just_released.contains(&scan_code)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed.contains(&button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_pressed.contains(&button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_released.contains(&button)
+    }
+
+    pub fn gamepad(&self, id: u32) -> Option<&GamepadState> {
+        self.gamepads.get(&id)
+    }
+
+    // Connected gamepads by id, in connection order
+    pub fn gamepads(&self) -> impl Iterator<Item = (u32, &GamepadState)> {
+        self.gamepads.iter().map(|(id, gamepad)| (*id, gamepad))
+    }
+
+    // On any gamepad
+    pub fn gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepads.values().any(|g| g.button_pressed(button))
+    }
+
+    pub fn gamepad_button_just_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepads
+            .values()
+            .any(|g| g.button_just_pressed(button))
+    }
+
+    pub fn gamepad_button_just_released(&self, button: GamepadButton) -> bool {
+        self.gamepads
+            .values()
+            .any(|g| g.button_just_released(button))
+    }
+
+    // Value furthest from rest among the gamepads
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .values()
+            .map(|gamepad| gamepad.axis(axis))
+            .fold(0.0, |best, value| {
+                if value.abs() > best.abs() {
+                    value
+                } else {
+                    best
+                }
+            })
+    }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.keys.pressed.iter().copied()
+    }
+
+    pub fn just_pressed_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.keys.just_pressed.iter().copied()
+    }
+
+    pub fn just_pressed_scan_codes(&self) -> impl Iterator<Item = ScanCode> + '_ {
+        self.scan_codes.just_pressed.iter().copied()
+    }
+
+    pub fn just_pressed_mouse_buttons(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.mouse_buttons.just_pressed.iter().copied()
+    }
+
+    pub fn just_pressed_gamepad_buttons(&self) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.gamepads
+            .values()
+            .flat_map(|gamepad| gamepad.buttons.just_pressed.iter().copied())
+    }
+
+    // Modifiers of the last key event
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    // In window pixels from the top left corner
+    pub fn mouse_position(&self) -> (f32, f32) {
+        self.mouse_position
+    }
+
+    // Motion during the last frame
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta
+    }
+
+    // Wheel offsets during the last frame
+    pub fn scroll_delta(&self) -> (f32, f32) {
+        self.scroll_delta
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::event_data::*;
+
+    fn key_pressed(key: KeyCode) -> Event {
+        Event::KeyPressed(KeyPressedEventData::new(
+            key,
+            ScanCode::Unknown,
+            Modifiers::NONE,
+            0,
+        ))
+    }
+
+    fn key_released(key: KeyCode) -> Event {
+        Event::KeyReleased(KeyReleasedEventData::new(
+            key,
+            ScanCode::Unknown,
+            Modifiers::NONE,
+        ))
+    }
+
+    fn handled(mut event: Event) -> Event {
+        event.set_handled(true);
+        event
+    }
+
+    #[test]
+    fn handled_presses_are_ignored() {
+        let mut input = Input::new();
+        input.handle_event(&handled(key_pressed(KeyCode::A)));
+        input.handle_event(&handled(Event::MouseButtonPressed(
+            MouseButtonPressedEventData::new(MouseButton::Left, 0, 0, 1),
+        )));
+        input.handle_event(&handled(Event::MouseMoved(MouseMovedEventData::new(
+            10.0, 20.0, 3.0, 4.0,
+        ))));
+        input.handle_event(&handled(Event::MouseScroll(MouseScrollEventData::new(
+            0.0, 1.0,
+        ))));
+        assert!(!input.key_pressed(KeyCode::A));
+        assert!(!input.key_just_pressed(KeyCode::A));
+        assert!(!input.mouse_pressed(MouseButton::Left));
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+        assert_eq!(input.scroll_delta(), (0.0, 0.0));
+        // The position stays current
+        assert_eq!(input.mouse_position(), (10.0, 20.0));
+    }
+
+    #[test]
+    fn releases_always_count() {
+        let mut input = Input::new();
+        input.handle_event(&key_pressed(KeyCode::A));
+        input.handle_event(&Event::MouseButtonPressed(
+            MouseButtonPressedEventData::new(MouseButton::Left, 0, 0, 1),
+        ));
+        input.handle_event(&handled(key_released(KeyCode::A)));
+        input.handle_event(&handled(Event::MouseButtonReleased(
+            MouseButtonReleasedEventData::new(MouseButton::Left, 0, 0),
+        )));
+        assert!(!input.key_pressed(KeyCode::A));
+        assert!(input.key_just_released(KeyCode::A));
+        assert!(!input.mouse_pressed(MouseButton::Left));
+        assert!(input.mouse_just_released(MouseButton::Left));
+    }
+
+    #[test]
+    fn losing_the_focus_releases_everything() {
+        let mut input = Input::new();
+        input.handle_event(&Event::GamepadConnected(GamepadConnectedEventData::new(
+            0,
+            "pad".to_string(),
+        )));
+        input.handle_event(&key_pressed(KeyCode::A));
+        input.handle_event(&Event::MouseButtonPressed(
+            MouseButtonPressedEventData::new(MouseButton::Right, 0, 0, 1),
+        ));
+        input.handle_event(&Event::GamepadButtonPressed(
+            GamepadButtonPressedEventData::new(0, GamepadButton::South),
+        ));
+        input.begin_frame();
+
+        input.handle_event(&Event::WindowLostFocus(WindowLostFocusEventData::default()));
+        assert!(!input.key_pressed(KeyCode::A));
+        assert!(input.key_just_released(KeyCode::A));
+        assert!(!input.mouse_pressed(MouseButton::Right));
+        assert!(input.mouse_just_released(MouseButton::Right));
+        assert!(!input.gamepad_button_pressed(GamepadButton::South));
+        assert!(input.gamepad_button_just_released(GamepadButton::South));
+        assert_eq!(input.modifiers(), Modifiers::NONE);
+    }
+
+    #[test]
+    fn begin_frame_resets_transitions_and_deltas() {
+        let mut input = Input::new();
+        input.handle_event(&key_pressed(KeyCode::A));
+        input.handle_event(&key_pressed(KeyCode::B));
+        input.handle_event(&key_released(KeyCode::B));
+        input.handle_event(&Event::MouseMoved(MouseMovedEventData::new(
+            10.0, 20.0, 3.0, 4.0,
+        )));
+        input.handle_event(&Event::MouseScroll(MouseScrollEventData::new(0.0, 1.0)));
+        assert!(input.key_just_pressed(KeyCode::A));
+        assert!(input.key_just_released(KeyCode::B));
+        assert_eq!(input.mouse_delta(), (3.0, 4.0));
+        assert_eq!(input.scroll_delta(), (0.0, 1.0));
+
+        input.begin_frame();
+        assert!(input.key_pressed(KeyCode::A));
+        assert!(!input.key_just_pressed(KeyCode::A));
+        assert!(!input.key_just_released(KeyCode::B));
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+        assert_eq!(input.scroll_delta(), (0.0, 0.0));
+        assert_eq!(input.mouse_position(), (10.0, 20.0));
+    }
+}
@@ -1,2 +1,6 @@
 pub mod event_data;
-pub mod event;
\ No newline at end of file
+pub mod event;
+pub mod input;
+pub mod action;
+pub mod bus;
+pub mod record;
\ No newline at end of file
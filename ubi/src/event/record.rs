@@ -0,0 +1,670 @@
+// Window events recorded frame by frame with the frame deltas, replayed by
+// `Application::replay` in place of the window to reproduce a session exactly.
+// Saved as compact JSON, one entry per frame:
+// {"version": 1, "frames": [{"delta": 0.016, "events": [{"type": "KeyPressed", "key": "W", ...}]}]}
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::core::custom_error::UbiError;
+use crate::core::time::Time;
+use crate::layer::Layer;
+use crate::ubiwarn;
+
+use super::event::Event;
+use super::event_data::*;
+use super::input::{GamepadAxis, GamepadButton, Input, KeyCode, Modifiers, MouseButton, ScanCode};
+
+const VERSION: u64 = 1;
+
+pub struct RecordedFrame {
+    delta: f32,
+    // Checked when recorded or loaded, they always convert back to events
+    events: Vec<Value>,
+}
+
+impl RecordedFrame {
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // New events each call, ready to be dispatched
+    pub fn events(&self) -> Vec<Event> {
+        self.events
+            .iter()
+            .filter_map(|event| event_from_json(event).ok())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Recording {
+    frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self { frames: Vec::new() }
+    }
+
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Total of the frame deltas, in seconds
+    pub fn duration(&self) -> f64 {
+        self.frames.iter().map(|frame| frame.delta as f64).sum()
+    }
+
+    pub fn begin_frame(&mut self, delta: f32) {
+        self.frames.push(RecordedFrame {
+            delta,
+            events: Vec::new(),
+        });
+    }
+
+    // Add to the last frame, custom events have no file format and are skipped
+    pub fn record(&mut self, event: &Event) -> bool {
+        let Some(frame) = self.frames.last_mut() else {
+            return false;
+        };
+        match event_to_json(event) {
+            Some(value) => {
+                frame.events.push(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, UbiError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::from_json(&source).map_err(|e| match e {
+            UbiError::ResourceError(message) => {
+                UbiError::ResourceError(format!("{}: {}", path.display(), message))
+            }
+            e => e,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), UbiError> {
+        std::fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, UbiError> {
+        let root: Value = serde_json::from_str(source).map_err(|e| error(&e.to_string()))?;
+        match root["version"].as_u64() {
+            Some(VERSION) => {}
+            Some(version) => return Err(error(&format!("unsupported version {}", version))),
+            None => return Err(error("missing version")),
+        }
+        let frames = root["frames"]
+            .as_array()
+            .ok_or_else(|| error("frames are not a list"))?;
+        let mut recording = Recording::new();
+        for (index, frame) in frames.iter().enumerate() {
+            let delta = frame["delta"]
+                .as_f64()
+                .ok_or_else(|| error(&format!("frame {} has no delta", index)))?;
+            let events = match &frame["events"] {
+                Value::Null => Vec::new(),
+                Value::Array(events) => events.clone(),
+                _ => return Err(error(&format!("events of frame {} are not a list", index))),
+            };
+            for event in &events {
+                event_from_json(event).map_err(|e| error(&format!("frame {}: {}", index, e)))?;
+            }
+            recording.frames.push(RecordedFrame {
+                delta: delta as f32,
+                events,
+            });
+        }
+        Ok(recording)
+    }
+
+    // Compact, frames without events only keep their delta
+    pub fn to_json(&self) -> String {
+        let frames: Vec<Value> = self
+            .frames
+            .iter()
+            .map(|frame| {
+                if frame.events.is_empty() {
+                    json!({ "delta": frame.delta })
+                } else {
+                    json!({ "delta": frame.delta, "events": frame.events })
+                }
+            })
+            .collect();
+        let root = json!({
+            "version": VERSION,
+            "frames": frames,
+        });
+        serde_json::to_string(&root).unwrap_or_default()
+    }
+}
+
+// Frames of a recording played one per application frame
+pub struct Replay {
+    frames: VecDeque<RecordedFrame>,
+    played: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            frames: recording.frames.into(),
+            played: 0,
+        }
+    }
+
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let frame = self.frames.pop_front()?;
+        self.played += 1;
+        Some(frame)
+    }
+
+    pub fn played(&self) -> usize {
+        self.played
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+// Layer recording the window events, push it as the last overlay so it sees every event
+// first. Events pushed by the layers in `on_update` are left out, replaying the layers posts
+// them again. Gamepads connected before the recording starts are connected in its first
+// frame. The recording is saved when the layer is dropped, even on a panic.
+pub struct InputRecorder {
+    recording: Recording,
+    path: Option<PathBuf>,
+    recording_active: bool,
+    delta: f32,
+    // Events of the frame pushed by the layers and dispatched before the window events
+    posted: usize,
+    seen: usize,
+    // Connected when the recording starts, by id and name
+    gamepads: Vec<(u32, String)>,
+}
+
+impl InputRecorder {
+    // Records from the first frame, `path` is where the recording is saved on drop
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            recording: Recording::new(),
+            path,
+            recording_active: true,
+            delta: 0.0,
+            posted: 0,
+            seen: 0,
+            gamepads: Vec::new(),
+        }
+    }
+
+    // Start over with an empty recording
+    pub fn start(&mut self) {
+        self.recording = Recording::new();
+        self.recording_active = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.recording_active = false;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording_active
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn take_recording(&mut self) -> Recording {
+        std::mem::take(&mut self.recording)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), UbiError> {
+        self.recording.save(path)
+    }
+}
+
+impl Layer for InputRecorder {
+    fn on_attach(&mut self) {}
+
+    fn on_detach(&mut self) {}
+
+    fn on_time(&mut self, time: &Time) {
+        self.delta = time.delta();
+    }
+
+    fn on_input(&mut self, input: &Input) {
+        if self.recording_active && self.recording.is_empty() {
+            self.gamepads = input
+                .gamepads()
+                .map(|(id, gamepad)| (id, gamepad.name().to_string()))
+                .collect();
+        }
+    }
+
+    fn on_update(&mut self, events: &mut Vec<Event>) {
+        // Last in the update order, only the events pushed by the other layers are there
+        self.posted = events.len();
+        self.seen = 0;
+        if self.recording_active {
+            self.recording.begin_frame(self.delta);
+            for (id, name) in self.gamepads.drain(..) {
+                let connected = GamepadConnectedEventData::new(id, name);
+                self.recording.record(&Event::GamepadConnected(connected));
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: &mut Event) {
+        self.seen += 1;
+        if self.recording_active && self.seen > self.posted {
+            self.recording.record(event);
+        }
+    }
+}
+
+impl Drop for InputRecorder {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            if let Err(e) = self.recording.save(path) {
+                ubiwarn!("Could not save the input recording: {}", e);
+            }
+        }
+    }
+}
+
+fn event_to_json(event: &Event) -> Option<Value> {
+    let kind = event.name();
+    let value = match event {
+        Event::WindowResize(data) => json!({
+            "type": kind,
+            "width": data.get_width(),
+            "height": data.get_height(),
+        }),
+        Event::WindowMoved(data) => json!({ "type": kind, "x": data.get_x(), "y": data.get_y() }),
+        Event::WindowScaleChanged(data) => json!({ "type": kind, "scale": data.get_scale() }),
+        Event::KeyPressed(data) => json!({
+            "type": kind,
+            "key": data.get_key_code().name(),
+            "scan": data.get_scan_code().name(),
+            "modifiers": data.get_modifiers().bits(),
+            "repeat": data.get_repeat_count(),
+        }),
+        Event::KeyReleased(data) => json!({
+            "type": kind,
+            "key": data.get_key_code().name(),
+            "scan": data.get_scan_code().name(),
+            "modifiers": data.get_modifiers().bits(),
+        }),
+        Event::MouseMoved(data) => json!({
+            "type": kind,
+            "x": data.get_x_pos(),
+            "y": data.get_y_pos(),
+            "dx": data.get_x_delta(),
+            "dy": data.get_y_delta(),
+        }),
+        Event::MouseButtonPressed(data) => json!({
+            "type": kind,
+            "button": data.get_button().name(),
+            "x": data.get_button_pos().0,
+            "y": data.get_button_pos().1,
+            "clicks": data.get_clicks(),
+        }),
+        Event::MouseButtonReleased(data) => json!({
+            "type": kind,
+            "button": data.get_button().name(),
+            "x": data.get_button_pos().0,
+            "y": data.get_button_pos().1,
+        }),
+        Event::MouseScroll(data) => json!({
+            "type": kind,
+            "x": data.get_x_offset(),
+            "y": data.get_y_offset(),
+        }),
+        Event::GamepadConnected(data) => json!({
+            "type": kind,
+            "id": data.get_id(),
+            "name": data.get_name(),
+        }),
+        Event::GamepadDisconnected(data) => json!({ "type": kind, "id": data.get_id() }),
+        Event::GamepadButtonPressed(data) => json!({
+            "type": kind,
+            "id": data.get_id(),
+            "button": data.get_button().name(),
+        }),
+        Event::GamepadButtonReleased(data) => json!({
+            "type": kind,
+            "id": data.get_id(),
+            "button": data.get_button().name(),
+        }),
+        Event::GamepadAxisMoved(data) => json!({
+            "type": kind,
+            "id": data.get_id(),
+            "axis": data.get_axis().name(),
+            "value": data.get_value(),
+        }),
+        Event::TextInput(data) => json!({ "type": kind, "text": data.get_text() }),
+        Event::TextEditing(data) => json!({
+            "type": kind,
+            "text": data.get_text(),
+            "start": data.get_start(),
+            "length": data.get_length(),
+        }),
+        Event::FileDropped(data) => json!({
+            "type": kind,
+            "path": data.get_path().to_string_lossy(),
+        }),
+        Event::Custom(_) => return None,
+        // Nothing but the type
+        _ => json!({ "type": kind }),
+    };
+    Some(value)
+}
+
+fn event_from_json(value: &Value) -> Result<Event, UbiError> {
+    let event = match json_str(value, "type")? {
+        "WindowClose" => Event::WindowClose(WindowCloseEventData::default()),
+        "WindowResize" => Event::WindowResize(WindowResizeEventData::new(
+            json_i32(value, "width")?,
+            json_i32(value, "height")?,
+        )),
+        "WindowFocus" => Event::WindowFocus(WindowFocusEventData::default()),
+        "WindowLostFocus" => Event::WindowLostFocus(WindowLostFocusEventData::default()),
+        "WindowMoved" => Event::WindowMoved(WindowMovedEventData::new(
+            json_i32(value, "x")?,
+            json_i32(value, "y")?,
+        )),
+        "WindowMinimized" => Event::WindowMinimized(WindowMinimizedEventData::default()),
+        "WindowMaximized" => Event::WindowMaximized(WindowMaximizedEventData::default()),
+        "WindowRestored" => Event::WindowRestored(WindowRestoredEventData::default()),
+        "WindowScaleChanged" => {
+            Event::WindowScaleChanged(WindowScaleChangedEventData::new(json_f32(value, "scale")?))
+        }
+        "MouseEntered" => Event::MouseEntered(MouseEnteredEventData::default()),
+        "MouseLeft" => Event::MouseLeft(MouseLeftEventData::default()),
+        "KeyPressed" => Event::KeyPressed(KeyPressedEventData::new(
+            json_named(value, "key", KeyCode::from_name)?,
+            json_named(value, "scan", ScanCode::from_name)?,
+            json_modifiers(value)?,
+            json_u32(value, "repeat")?,
+        )),
+        "KeyReleased" => Event::KeyReleased(KeyReleasedEventData::new(
+            json_named(value, "key", KeyCode::from_name)?,
+            json_named(value, "scan", ScanCode::from_name)?,
+            json_modifiers(value)?,
+        )),
+        "MouseMoved" => Event::MouseMoved(MouseMovedEventData::new(
+            json_f32(value, "x")?,
+            json_f32(value, "y")?,
+            json_f32(value, "dx")?,
+            json_f32(value, "dy")?,
+        )),
+        "MouseButtonPressed" => Event::MouseButtonPressed(MouseButtonPressedEventData::new(
+            json_named(value, "button", MouseButton::from_name)?,
+            json_i32(value, "x")?,
+            json_i32(value, "y")?,
+            json_u32(value, "clicks")?,
+        )),
+        "MouseButtonReleased" => Event::MouseButtonReleased(MouseButtonReleasedEventData::new(
+            json_named(value, "button", MouseButton::from_name)?,
+            json_i32(value, "x")?,
+            json_i32(value, "y")?,
+        )),
+        "MouseScroll" => Event::MouseScroll(MouseScrollEventData::new(
+            json_f32(value, "x")?,
+            json_f32(value, "y")?,
+        )),
+        "GamepadConnected" => Event::GamepadConnected(GamepadConnectedEventData::new(
+            json_u32(value, "id")?,
+            json_str(value, "name")?.to_string(),
+        )),
+        "GamepadDisconnected" => {
+            Event::GamepadDisconnected(GamepadDisconnectedEventData::new(json_u32(value, "id")?))
+        }
+        "GamepadButtonPressed" => Event::GamepadButtonPressed(GamepadButtonPressedEventData::new(
+            json_u32(value, "id")?,
+            json_named(value, "button", GamepadButton::from_name)?,
+        )),
+        "GamepadButtonReleased" => {
+            Event::GamepadButtonReleased(GamepadButtonReleasedEventData::new(
+                json_u32(value, "id")?,
+                json_named(value, "button", GamepadButton::from_name)?,
+            ))
+        }
+        "GamepadAxisMoved" => Event::GamepadAxisMoved(GamepadAxisMovedEventData::new(
+            json_u32(value, "id")?,
+            json_named(value, "axis", GamepadAxis::from_name)?,
+            json_f32(value, "value")?,
+        )),
+        "TextInput" => Event::TextInput(TextInputEventData::new(
+            json_str(value, "text")?.to_string(),
+        )),
+        "TextEditing" => Event::TextEditing(TextEditingEventData::new(
+            json_str(value, "text")?.to_string(),
+            json_i32(value, "start")?,
+            json_i32(value, "length")?,
+        )),
+        "DropBegin" => Event::DropBegin(DropBeginEventData::default()),
+        "FileDropped" => Event::FileDropped(FileDroppedEventData::new(PathBuf::from(json_str(
+            value, "path",
+        )?))),
+        "DropComplete" => Event::DropComplete(DropCompleteEventData::default()),
+        kind => return Err(error(&format!("unknown event type {}", kind))),
+    };
+    Ok(event)
+}
+
+fn json_str<'a>(value: &'a Value, key: &str) -> Result<&'a str, UbiError> {
+    value[key]
+        .as_str()
+        .ok_or_else(|| error(&format!("{} is not a string in {}", key, value)))
+}
+
+fn json_f32(value: &Value, key: &str) -> Result<f32, UbiError> {
+    value[key]
+        .as_f64()
+        .map(|number| number as f32)
+        .ok_or_else(|| error(&format!("{} is not a number in {}", key, value)))
+}
+
+fn json_i32(value: &Value, key: &str) -> Result<i32, UbiError> {
+    value[key]
+        .as_i64()
+        .and_then(|number| i32::try_from(number).ok())
+        .ok_or_else(|| error(&format!("{} is not an integer in {}", key, value)))
+}
+
+fn json_u32(value: &Value, key: &str) -> Result<u32, UbiError> {
+    value[key]
+        .as_u64()
+        .and_then(|number| u32::try_from(number).ok())
+        .ok_or_else(|| error(&format!("{} is not a positive integer in {}", key, value)))
+}
+
+fn json_named<T>(
+    value: &Value,
+    key: &str,
+    from_name: fn(&str) -> Option<T>,
+) -> Result<T, UbiError> {
+    from_name(json_str(value, key)?)
+        .ok_or_else(|| error(&format!("unknown {} {}", key, value[key])))
+}
+
+fn json_modifiers(value: &Value) -> Result<Modifiers, UbiError> {
+    Ok(Modifiers::from_bits_truncate(json_u32(value, "modifiers")?))
+}
+
+fn error(message: &str) -> UbiError {
+    UbiError::ResourceError(format!("input recording: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One event of each type but custom, with fields away from their defaults
+    fn every_event() -> Vec<Event> {
+        vec![
+            Event::WindowClose(WindowCloseEventData::default()),
+            Event::WindowResize(WindowResizeEventData::new(1280, 720)),
+            Event::WindowFocus(WindowFocusEventData::default()),
+            Event::WindowLostFocus(WindowLostFocusEventData::default()),
+            Event::WindowMoved(WindowMovedEventData::new(-20, 40)),
+            Event::WindowMinimized(WindowMinimizedEventData::default()),
+            Event::WindowMaximized(WindowMaximizedEventData::default()),
+            Event::WindowRestored(WindowRestoredEventData::default()),
+            Event::WindowScaleChanged(WindowScaleChangedEventData::new(1.5)),
+            Event::MouseEntered(MouseEnteredEventData::default()),
+            Event::MouseLeft(MouseLeftEventData::default()),
+            Event::KeyPressed(KeyPressedEventData::new(
+                KeyCode::W,
+                ScanCode::Z,
+                Modifiers::SHIFT | Modifiers::CTRL,
+                3,
+            )),
+            Event::KeyReleased(KeyReleasedEventData::new(
+                KeyCode::Escape,
+                ScanCode::Escape,
+                Modifiers::ALT,
+            )),
+            Event::MouseMoved(MouseMovedEventData::new(10.5, 20.25, -1.0, 2.0)),
+            Event::MouseButtonPressed(MouseButtonPressedEventData::new(
+                MouseButton::Right,
+                5,
+                6,
+                2,
+            )),
+            Event::MouseButtonReleased(MouseButtonReleasedEventData::new(MouseButton::X2, 7, 8)),
+            Event::MouseScroll(MouseScrollEventData::new(0.5, -3.0)),
+            Event::GamepadConnected(GamepadConnectedEventData::new(2, "Pad \"2\"".to_string())),
+            Event::GamepadDisconnected(GamepadDisconnectedEventData::new(2)),
+            Event::GamepadButtonPressed(GamepadButtonPressedEventData::new(
+                1,
+                GamepadButton::DPadLeft,
+            )),
+            Event::GamepadButtonReleased(GamepadButtonReleasedEventData::new(
+                1,
+                GamepadButton::Touchpad,
+            )),
+            Event::GamepadAxisMoved(GamepadAxisMovedEventData::new(
+                1,
+                GamepadAxis::RightTrigger,
+                0.75,
+            )),
+            Event::TextInput(TextInputEventData::new("é日".to_string())),
+            Event::TextEditing(TextEditingEventData::new("かな".to_string(), 1, 2)),
+            Event::DropBegin(DropBeginEventData::default()),
+            Event::FileDropped(FileDroppedEventData::new(PathBuf::from("maps/level 1.tmx"))),
+            Event::DropComplete(DropCompleteEventData::default()),
+        ]
+    }
+
+    #[test]
+    fn every_event_type_round_trips() {
+        let events = every_event();
+        let mut recording = Recording::new();
+        recording.begin_frame(0.016);
+        for event in &events {
+            assert!(recording.record(event), "{} was not recorded", event.name());
+        }
+        recording.begin_frame(0.02);
+
+        let loaded = Recording::from_json(&recording.to_json()).unwrap();
+        assert_eq!(loaded.to_json(), recording.to_json());
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.frames()[0].delta(), 0.016);
+        assert!(loaded.frames()[1].is_empty());
+        let replayed = loaded.frames()[0].events();
+        assert_eq!(replayed.len(), events.len());
+        for (replayed, event) in replayed.iter().zip(&events) {
+            assert_eq!(format!("{:?}", replayed), format!("{:?}", event));
+        }
+    }
+
+    #[test]
+    fn custom_events_are_not_recorded() {
+        let mut recording = Recording::new();
+        assert!(!recording.record(&Event::WindowClose(WindowCloseEventData::default())));
+        recording.begin_frame(0.016);
+        assert!(!recording.record(&Event::custom(3u32)));
+        assert!(recording.frames()[0].is_empty());
+    }
+
+    #[test]
+    fn invalid_recordings_are_rejected() {
+        assert!(Recording::from_json("{\"frames\": []}").is_err());
+        assert!(Recording::from_json("{\"version\": 2, \"frames\": []}").is_err());
+        assert!(Recording::from_json("{\"version\": 1, \"frames\": [{}]}").is_err());
+        let unknown =
+            "{\"version\": 1, \"frames\": [{\"delta\": 0.1, \"events\": [{\"type\": \"Jump\"}]}]}";
+        assert!(Recording::from_json(unknown).is_err());
+        let missing = "{\"version\": 1, \"frames\": [{\"delta\": 0.1, \"events\": [{\"type\": \"KeyPressed\"}]}]}";
+        assert!(Recording::from_json(missing).is_err());
+    }
+
+    #[test]
+    fn replay_plays_frames_in_order() {
+        let mut recording = Recording::new();
+        recording.begin_frame(0.1);
+        recording.begin_frame(0.2);
+        assert!((recording.duration() - 0.3).abs() < 1e-6);
+        let mut replay = Replay::new(recording);
+        assert_eq!(replay.next_frame().map(|frame| frame.delta()), Some(0.1));
+        assert_eq!((replay.played(), replay.remaining()), (1, 1));
+        assert_eq!(replay.next_frame().map(|frame| frame.delta()), Some(0.2));
+        assert!(replay.is_finished());
+        assert!(replay.next_frame().is_none());
+    }
+
+    #[test]
+    fn recorder_connects_the_gamepads_in_the_first_frame() {
+        let mut input = Input::new();
+        input.handle_event(&Event::GamepadConnected(GamepadConnectedEventData::new(
+            3,
+            "Pad".to_string(),
+        )));
+        let mut recorder = InputRecorder::new(None);
+        let mut events = Vec::new();
+        for _ in 0..2 {
+            recorder.on_input(&input);
+            recorder.on_update(&mut events);
+        }
+        let frames = recorder.recording().frames();
+        let connected = frames[0].events();
+        assert_eq!(connected.len(), 1);
+        match &connected[0] {
+            Event::GamepadConnected(data) => {
+                assert_eq!((data.get_id(), data.get_name()), (3, "Pad"));
+            }
+            event => panic!("{} recorded", event.name()),
+        }
+        assert!(frames[1].is_empty());
+    }
+}
@@ -1,6 +1,9 @@
 use std::{ffi::CString, mem, ptr::null};
 
 use gl::types::{GLint, GLuint};
+
+use super::gl_state;
+
 // OpenGL Vertex Buffer Obejct
 pub struct Vbo {
     id: GLuint,
@@ -15,38 +18,64 @@ impl Vbo {
         Vbo { id }
     }
 
-    pub fn set(&self, data: &Vec<f32>) {
+    pub fn set(&self, data: &[f32]) {
         self.bind();
         self.data(data);
     }
 
-    fn data(&self, vertices: &Vec<f32>) {
+    // Reserve `size` bytes of storage that is rewritten every frame
+    pub fn allocate(&self, size: usize) {
+        self.bind();
+        unsafe {
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size as gl::types::GLsizeiptr,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+        }
+    }
+
+    // Upload `data` at `offset` bytes into storage reserved with `allocate`
+    pub fn sub_data(&self, offset: usize, data: &[f32]) {
+        self.bind();
+        unsafe {
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                offset as gl::types::GLintptr,
+                std::mem::size_of_val(data) as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+            );
+        }
+    }
+
+    fn data(&self, vertices: &[f32]) {
         unsafe {
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                mem::size_of_val(vertices) as gl::types::GLsizeiptr,
                 vertices.as_ptr() as *const gl::types::GLvoid,
                 gl::STATIC_DRAW,
             );
         }
     }
 
-    fn bind(&self) {
-        unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, self.id) }
+    pub fn bind(&self) {
+        gl_state::bind_buffer(gl::ARRAY_BUFFER, self.id);
     }
 
-    fn unbind(&self) {
-        unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, 0) }
+    pub fn unbind(&self) {
+        gl_state::bind_buffer(gl::ARRAY_BUFFER, 0);
     }
 
     fn delete(&self) {
+        gl_state::forget_buffer(self.id);
         unsafe { gl::DeleteBuffers(1, &self.id) }
     }
 }
 
 impl Drop for Vbo {
     fn drop(&mut self) {
-        self.unbind();
         self.delete();
     }
 }
@@ -65,42 +94,105 @@ impl Ibo {
         Ibo { id }
     }
 
-    pub fn set(&self, data: &Vec<u32>) {
+    pub fn set(&self, data: &[u32]) {
         self.bind();
         self.data(data);
     }
 
-    fn data(&self, indices: &Vec<u32>) {
+    fn data(&self, indices: &[u32]) {
         unsafe {
             gl::BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
-                (indices.len() * std::mem::size_of::<u32>()) as gl::types::GLsizeiptr,
+                mem::size_of_val(indices) as gl::types::GLsizeiptr,
                 indices.as_ptr() as *const gl::types::GLvoid,
                 gl::DYNAMIC_DRAW,
             );
         }
     }
 
-    fn bind(&self) {
-        unsafe { gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id) }
+    pub fn bind(&self) {
+        gl_state::bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
     }
 
-    fn unbind(&self) {
-        unsafe { gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0) }
+    pub fn unbind(&self) {
+        gl_state::bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 0);
     }
 
     fn delete(&self) {
+        gl_state::forget_buffer(self.id);
         unsafe { gl::DeleteBuffers(1, &self.id) }
     }
 }
 
 impl Drop for Ibo {
     fn drop(&mut self) {
-        self.unbind();
         self.delete();
     }
 }
 
+// A single float vertex attribute inside an interleaved vertex
+#[derive(Debug, Clone, Copy)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub components: i32,
+    pub offset: usize,
+}
+
+// Describes how the floats of an interleaved vertex map to shader locations
+#[derive(Debug, Clone, Default)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    stride: usize,
+    divisor: u32,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Append an attribute of `components` floats bound to `location`
+    pub fn with(mut self, location: u32, components: i32) -> Self {
+        self.attributes.push(VertexAttribute {
+            location,
+            components,
+            offset: self.stride,
+        });
+        self.stride += components as usize * mem::size_of::<f32>();
+        self
+    }
+
+    // Append a 4*4 matrix, it takes the four locations starting at `location`, one per row
+    pub fn with_mat4(self, location: u32) -> Self {
+        (location..location + 4).fold(self, |layout, row| layout.with(row, 4))
+    }
+
+    // Advance the attributes once per instance instead of once per vertex
+    pub fn per_instance(mut self) -> Self {
+        self.divisor = 1;
+        self
+    }
+
+    // 0 for per vertex data, otherwise the number of instances sharing one element
+    pub fn divisor(&self) -> u32 {
+        self.divisor
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    // Size in bytes of one vertex
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    // Number of floats in one vertex
+    pub fn floats_per_vertex(&self) -> usize {
+        self.stride / mem::size_of::<f32>()
+    }
+}
+
 pub struct Vao {
     id: GLuint,
 }
@@ -119,6 +211,36 @@ impl Vao {
         self.setup();
     }
 
+    // Bind the vao and point its attributes at the currently bound vbo
+    pub fn set_layout(&self, layout: &VertexLayout) {
+        self.bind();
+        for attribute in layout.attributes() {
+            unsafe {
+                gl::EnableVertexAttribArray(attribute.location);
+                gl::VertexAttribPointer(
+                    attribute.location,
+                    attribute.components,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    layout.stride() as GLint,
+                    attribute.offset as *const gl::types::GLvoid,
+                );
+                gl::VertexAttribDivisor(attribute.location, layout.divisor());
+            }
+        }
+    }
+
+    // Stop reading the attributes of `layout` from buffers, e.g. after an instanced draw
+    pub fn disable_layout(&self, layout: &VertexLayout) {
+        self.bind();
+        for attribute in layout.attributes() {
+            unsafe {
+                gl::DisableVertexAttribArray(attribute.location);
+                gl::VertexAttribDivisor(attribute.location, 0);
+            }
+        }
+    }
+
     fn setup(&self) {
         unsafe {
             gl::EnableVertexAttribArray(0);
@@ -142,22 +264,22 @@ impl Vao {
         }
     }
 
-    fn bind(&self) {
-        unsafe { gl::BindVertexArray(self.id) }
+    pub fn bind(&self) {
+        gl_state::bind_vertex_array(self.id);
     }
 
-    fn unbind(&self) {
-        unsafe { gl::BindVertexArray(0) }
+    pub fn unbind(&self) {
+        gl_state::bind_vertex_array(0);
     }
 
     fn delete(&self) {
+        gl_state::forget_vertex_array(self.id);
         unsafe { gl::DeleteVertexArrays(1, &self.id) }
     }
 }
 
 impl Drop for Vao {
     fn drop(&mut self) {
-        self.unbind();
         self.delete();
     }
 }
@@ -0,0 +1,88 @@
+use crate::core::math::transform::Mat4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // Vertical field of view in radians
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    // Height of the view volume in world units, the width follows the aspect ratio
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+// A viewpoint looking from `position` at `target`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    pub projection: Projection,
+    // Width / height of the viewport
+    pub aspect: f32,
+}
+
+impl Camera {
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self {
+            position: [0.0, 0.0, 5.0],
+            target: [0.0; 3],
+            up: [0.0, 1.0, 0.0],
+            projection: Projection::Perspective { fov_y, near, far },
+            aspect,
+        }
+    }
+
+    pub fn orthographic(height: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic { height, near, far },
+            ..Self::perspective(1.0, aspect, near, far)
+        }
+    }
+
+    pub fn near(&self) -> f32 {
+        match self.projection {
+            Projection::Perspective { near, .. } | Projection::Orthographic { near, .. } => near,
+        }
+    }
+
+    pub fn far(&self) -> f32 {
+        match self.projection {
+            Projection::Perspective { far, .. } | Projection::Orthographic { far, .. } => far,
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        let mut view = Mat4::new();
+        view.lookat(
+            self.position[0],
+            self.position[1],
+            self.position[2],
+            self.target[0],
+            self.target[1],
+            self.target[2],
+            self.up[0],
+            self.up[1],
+            self.up[2],
+        );
+        view
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        let mut projection = Mat4::new();
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                projection.perspective(fov_y, self.aspect, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let (half_w, half_h) = (height * self.aspect * 0.5, height * 0.5);
+                projection.orthographic(-half_w, half_w, -half_h, half_h, near, far)
+            }
+        }
+        projection
+    }
+
+    // projection * view
+    pub fn view_projection(&self) -> Mat4 {
+        let mut view_projection = self.view();
+        view_projection.mult(self.projection_matrix());
+        view_projection
+    }
+}
@@ -0,0 +1,155 @@
+use std::ffi::c_void;
+
+use gl::types::{GLint, GLsizei, GLuint};
+
+use super::gl_state;
+use super::texture::TextureFormat;
+
+// Face order of GL, +x -x +y -y +z -z
+pub const CUBE_FACES: u32 = 6;
+
+// A cube map texture with float faces
+pub struct CubeMap {
+    pub id: GLuint,
+    size: u32,
+    mip_levels: u32,
+    format: TextureFormat,
+}
+
+impl CubeMap {
+    // Uninitialised faces of `size`*`size`, with `mip_levels` levels (1 for none)
+    pub fn new(size: u32, mip_levels: u32, format: TextureFormat) -> Self {
+        let mut id: GLuint = 0;
+        let mip_levels = mip_levels.max(1);
+        let (internal, pixel_format, kind) = format.gl_formats();
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, id);
+            for level in 0..mip_levels {
+                let level_size = (size >> level).max(1) as GLsizei;
+                for face in 0..CUBE_FACES {
+                    gl::TexImage2D(
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                        level as GLint,
+                        internal,
+                        level_size,
+                        level_size,
+                        0,
+                        pixel_format,
+                        kind,
+                        std::ptr::null(),
+                    );
+                }
+            }
+            let min_filter = if mip_levels > 1 {
+                gl::LINEAR_MIPMAP_LINEAR
+            } else {
+                gl::LINEAR
+            };
+            for (param, value) in [
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_MIN_FILTER, min_filter),
+                (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+            ] {
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, param, value as GLint);
+            }
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_MAX_LEVEL,
+                mip_levels as GLint - 1,
+            );
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        Self {
+            id,
+            size,
+            mip_levels,
+            format,
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    // Side length of mip `level`
+    pub fn level_size(&self, level: u32) -> u32 {
+        (self.size >> level).max(1)
+    }
+
+    // Floats of one face at one mip level, `format().channels()` per pixel
+    pub fn read_face(&self, face: u32, level: u32) -> Vec<f32> {
+        let size = self.level_size(level) as usize;
+        let mut data = vec![0.0f32; size * size * self.format.channels()];
+        unsafe {
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTexImage(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                level as GLint,
+                self.format.gl_float_format(),
+                gl::FLOAT,
+                data.as_mut_ptr() as *mut c_void,
+            );
+            // Back to the GL default the other uploads and readbacks expect
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        data
+    }
+
+    // Replace one face at one mip level, the length must match `read_face`
+    pub fn write_face(&self, face: u32, level: u32, data: &[f32]) {
+        let size = self.level_size(level) as usize;
+        debug_assert_eq!(data.len(), size * size * self.format.channels());
+        unsafe {
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                level as GLint,
+                0,
+                0,
+                size as GLsizei,
+                size as GLsizei,
+                self.format.gl_float_format(),
+                gl::FLOAT,
+                data.as_ptr() as *const c_void,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+    }
+
+    pub fn generate_mipmaps(&self) {
+        unsafe {
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+    }
+
+    // Bind to texture unit `unit`, leaves that unit active
+    pub fn bind_unit(&self, unit: u32) {
+        gl_state::active_texture(unit);
+        gl_state::bind_texture(gl::TEXTURE_CUBE_MAP, self.id);
+    }
+}
+
+impl Drop for CubeMap {
+    fn drop(&mut self) {
+        gl_state::forget_texture(self.id);
+        unsafe { gl::DeleteTextures(1, &self.id) }
+    }
+}
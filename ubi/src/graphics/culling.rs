@@ -0,0 +1,39 @@
+use crate::core::math::bounds::Frustum;
+use crate::core::math::transform::Mat4;
+
+use super::mesh::Mesh;
+
+// Objects a pass tested against its frustums
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    pub visible: u32,
+    pub culled: u32,
+}
+
+impl CullingStats {
+    pub fn tested(&self) -> u32 {
+        self.visible + self.culled
+    }
+
+    // Count the result and pass it through
+    pub(crate) fn record(&mut self, visible: bool) -> bool {
+        if visible {
+            self.visible += 1;
+        } else {
+            self.culled += 1;
+        }
+        visible
+    }
+}
+
+// Whether `mesh` placed by `transform` may be seen through `frustum`. The sphere test is
+// cheaper and rejects most objects, the box is tighter for long or flat meshes. Meshes
+// without bounds are always visible.
+pub fn is_visible(frustum: &Frustum, mesh: &Mesh, transform: &Mat4) -> bool {
+    let (aabb, sphere) = match (mesh.bounds(), mesh.bounding_sphere()) {
+        (Some(aabb), Some(sphere)) => (aabb, sphere),
+        _ => return true,
+    };
+    frustum.intersects_sphere(&sphere.transform(transform))
+        && frustum.intersects_aabb(&aabb.transform(transform))
+}
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use gl::types::GLint;
+
+use crate::core::custom_error::UbiError;
+use crate::core::ecs::transform::GlobalTransform;
+use crate::core::ecs::world::World;
+use crate::core::math::bounds::Frustum;
+use crate::core::math::transform::Mat4;
+
+use super::buffer::Vao;
+use super::camera::Camera;
+use super::culling::{is_visible, CullingStats};
+use super::environment::bind_environment;
+use super::forward::ForwardPass;
+use super::framebuffer::{Framebuffer, SavedTarget};
+use super::gl_state;
+use super::instancing::{draw_instanced, InstanceBuffer, MeshRenderer};
+use super::light::{gather_lights, upload_lights, LightKind, MAX_LIGHTS_PER_OBJECT};
+use super::material::Material;
+use super::mesh::Mesh;
+use super::pipeline::{BlendState, CompareFunc, CullMode, DepthState, PipelineState};
+use super::shader::Program;
+use super::texture::{Texture, TextureFormat};
+
+// Texture units the lighting shaders read the G-buffer from
+const ALBEDO_UNIT: u32 = 0;
+const NORMAL_UNIT: u32 = 1;
+const MATERIAL_UNIT: u32 = 2;
+const DEPTH_UNIT: u32 = 3;
+const LIGHT_UNIT: u32 = 4;
+
+// Fullscreen lights added up in the light buffer
+const LIGHTING: PipelineState = PipelineState::OPAQUE
+    .with_blend(Some(BlendState::ADDITIVE))
+    .with_depth(DepthState::DISABLED);
+// Back faces behind the scene surface: pixels inside the volume, even from inside it
+const LIGHT_VOLUMES: PipelineState = LIGHTING
+    .with_depth(DepthState {
+        test: true,
+        write: false,
+        func: CompareFunc::GreaterEqual,
+        bias: None,
+    })
+    .with_cull(CullMode::Front);
+// Writes the G-buffer depth over the target's
+const RESOLVE: PipelineState = PipelineState::OPAQUE.with_depth(DepthState {
+    test: true,
+    write: true,
+    func: CompareFunc::Always,
+    bias: None,
+});
+
+// Surface attributes of the visible fragments, and the light they receive
+pub struct GBuffer {
+    width: u32,
+    height: u32,
+    // Linear base color
+    albedo: Texture,
+    // World space normal
+    normal: Texture,
+    // Metallic, roughness, occlusion
+    material: Texture,
+    depth: Texture,
+    // Copy of `depth` the light volumes are tested against, `depth` is sampled at the same
+    // time and a texture can not be both read and attached
+    _volume_depth: Texture,
+    // Radiance accumulated by the lighting passes, before tone mapping
+    light: Texture,
+    // albedo, normal, material and light
+    geometry: Framebuffer,
+    // light only, with the depth copy
+    lighting: Framebuffer,
+}
+
+impl GBuffer {
+    pub fn new(width: u32, height: u32) -> Result<Self, UbiError> {
+        let albedo = Texture::with_format(width, height, TextureFormat::Rgba8)?;
+        let normal = Texture::with_format(width, height, TextureFormat::Rgba16F)?;
+        let material = Texture::with_format(width, height, TextureFormat::Rgba8)?;
+        let depth = Texture::with_format(width, height, TextureFormat::Depth32F)?;
+        let volume_depth = Texture::with_format(width, height, TextureFormat::Depth32F)?;
+        let light = Texture::with_format(width, height, TextureFormat::Rgba16F)?;
+
+        let saved = SavedTarget::save();
+        let geometry = Framebuffer::gen();
+        for (index, texture) in [&albedo, &normal, &material, &light].iter().enumerate() {
+            geometry.attach_color(index as u32, gl::TEXTURE_2D, texture.id, 0);
+        }
+        geometry.attach_depth(gl::TEXTURE_2D, depth.id, 0);
+        geometry.set_draw_buffers(4);
+        let lighting = Framebuffer::gen();
+        lighting.attach_color(0, gl::TEXTURE_2D, light.id, 0);
+        lighting.attach_depth(gl::TEXTURE_2D, volume_depth.id, 0);
+        lighting.set_draw_buffers(1);
+        let status = geometry.check().and_then(|_| lighting.check());
+        saved.restore();
+        status?;
+
+        Ok(Self {
+            width,
+            height,
+            albedo,
+            normal,
+            material,
+            depth,
+            _volume_depth: volume_depth,
+            light,
+            geometry,
+            lighting,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn albedo(&self) -> &Texture {
+        &self.albedo
+    }
+
+    pub fn normal(&self) -> &Texture {
+        &self.normal
+    }
+
+    pub fn material(&self) -> &Texture {
+        &self.material
+    }
+
+    pub fn depth(&self) -> &Texture {
+        &self.depth
+    }
+
+    pub fn light(&self) -> &Texture {
+        &self.light
+    }
+
+    // Bind `lighting` and the textures its passes read. The light buffer is the target, it is
+    // unbound from its unit until the resolve
+    fn begin_lighting(&self) {
+        self.lighting.bind();
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.geometry.id());
+            gl::BlitFramebuffer(
+                0,
+                0,
+                self.width as GLint,
+                self.height as GLint,
+                0,
+                0,
+                self.width as GLint,
+                self.height as GLint,
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+            // The cache expects `lighting` as both read and draw framebuffer
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.lighting.id());
+        }
+        self.albedo.bind_unit(ALBEDO_UNIT);
+        self.normal.bind_unit(NORMAL_UNIT);
+        self.material.bind_unit(MATERIAL_UNIT);
+        self.depth.bind_unit(DEPTH_UNIT);
+        gl_state::bind_texture_unit(LIGHT_UNIT, gl::TEXTURE_2D, 0);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeferredStats {
+    // Objects written to the G-buffer
+    pub objects: u32,
+    pub lights: u32,
+    pub draw_calls: u32,
+    pub culling: CullingStats,
+}
+
+// Deferred shading for scenes with many lights. Objects whose material uses the program of
+// `gbuffer_material` write their surface to the G-buffer, then the lights are accumulated:
+// ambient, image based and directional lighting in fullscreen passes, point and spot lights
+// as spheres instanced in a single draw. Every other object, transparent ones included, is
+// drawn afterwards by the `ForwardPass`, which also holds the ambient, exposure and environment.
+// Shadows only reach the forward objects for now.
+pub struct DeferredPass {
+    gbuffer: Option<GBuffer>,
+    gbuffer_material: Rc<Material>,
+    ambient_program: Program,
+    light_program: Program,
+    resolve_program: Program,
+    sphere: Mesh,
+    fullscreen: Vao,
+    instances: InstanceBuffer,
+    stats: DeferredStats,
+}
+
+impl DeferredPass {
+    pub fn new() -> Result<Self, UbiError> {
+        let gbuffer_program = Program::from_sources(
+            include_str!("../../../assets/shaders/lit.vert"),
+            include_str!("../../../assets/shaders/gbuffer.frag"),
+        )?;
+        let white = Rc::new(Texture::from_rgba(1, 1, &[255, 255, 255, 255])?);
+        let flat_normal = Rc::new(Texture::from_rgba(1, 1, &[128, 128, 255, 255])?);
+        // Same defaults as `ForwardPass::pbr_material`
+        let mut material = Material::new(Rc::new(gbuffer_program));
+        material.set("u_base_color_factor", [1.0, 1.0, 1.0, 1.0])?;
+        material.set("u_metallic_factor", 0.0)?;
+        material.set("u_roughness_factor", 0.5)?;
+        material.set("u_normal_scale", 1.0)?;
+        material.set("u_occlusion_strength", 1.0)?;
+        material.set("u_emissive_factor", [0.0, 0.0, 0.0])?;
+        material.set_texture("u_base_color_map", white.clone())?;
+        material.set_texture("u_metallic_roughness_map", white.clone())?;
+        material.set_texture("u_normal_map", flat_normal)?;
+        material.set_texture("u_occlusion_map", white.clone())?;
+        material.set_texture("u_emissive_map", white)?;
+
+        Ok(Self {
+            gbuffer: None,
+            gbuffer_material: Rc::new(material),
+            ambient_program: Program::from_sources(
+                include_str!("../../../assets/shaders/fullscreen.vert"),
+                include_str!("../../../assets/shaders/deferred_ambient.frag"),
+            )?,
+            light_program: Program::from_sources(
+                include_str!("../../../assets/shaders/light_volume.vert"),
+                include_str!("../../../assets/shaders/deferred_light.frag"),
+            )?,
+            resolve_program: Program::from_sources(
+                include_str!("../../../assets/shaders/fullscreen.vert"),
+                include_str!("../../../assets/shaders/deferred_resolve.frag"),
+            )?,
+            sphere: Mesh::sphere(16, 8),
+            fullscreen: Vao::gen(),
+            // position and range, radiance and type, direction and inner cone, outer cone
+            instances: InstanceBuffer::transforms(),
+            stats: DeferredStats::default(),
+        })
+    }
+
+    // Metallic-roughness material writing to the G-buffer, with the parameters of
+    // `ForwardPass::pbr_material`. Make instances of it for the deferred objects.
+    pub fn gbuffer_material(&self) -> &Rc<Material> {
+        &self.gbuffer_material
+    }
+
+    // Whether `renderer` goes through the G-buffer rather than the forward pass
+    pub fn accepts(&self, renderer: &MeshRenderer) -> bool {
+        Rc::ptr_eq(renderer.material.program(), self.gbuffer_material.program())
+            && !renderer.material.is_transparent()
+    }
+
+    // G-buffer of the last render, sized like the viewport
+    pub fn gbuffer(&self) -> Option<&GBuffer> {
+        self.gbuffer.as_ref()
+    }
+
+    // Stats of the last `render`, the forward objects are in `ForwardPass::stats`
+    pub fn stats(&self) -> DeferredStats {
+        self.stats
+    }
+
+    // Render into the current framebuffer and viewport, the G-buffer follows the viewport size
+    pub fn render(
+        &mut self,
+        world: &World,
+        camera: &Camera,
+        forward: &mut ForwardPass,
+    ) -> Result<(), UbiError> {
+        self.stats = DeferredStats::default();
+        let saved = SavedTarget::save();
+        let [_, _, width, height] = saved.viewport().map(|v| v.max(1) as u32);
+        if self
+            .gbuffer
+            .as_ref()
+            .map(|gbuffer| (gbuffer.width, gbuffer.height))
+            != Some((width, height))
+        {
+            self.gbuffer = Some(GBuffer::new(width, height)?);
+        }
+        let gbuffer = match &self.gbuffer {
+            Some(gbuffer) => gbuffer,
+            None => return Ok(()),
+        };
+
+        // Geometry, instanced by material and mesh
+        let frustum = Frustum::from_matrix(&camera.view_projection());
+        let mut batches: Vec<(Rc<Material>, Rc<Mesh>, Vec<f32>)> = Vec::new();
+        let mut lookup: HashMap<(*const Material, *const Mesh), usize> = HashMap::new();
+        for (_, renderer, transform) in world.query2::<MeshRenderer, GlobalTransform>() {
+            if !self.accepts(renderer) {
+                continue;
+            }
+            let visible = is_visible(&frustum, &renderer.mesh, &transform.0);
+            if !self.stats.culling.record(visible) {
+                continue;
+            }
+            let key = (Rc::as_ptr(&renderer.material), Rc::as_ptr(&renderer.mesh));
+            let index = *lookup.entry(key).or_insert_with(|| {
+                batches.push((renderer.material.clone(), renderer.mesh.clone(), Vec::new()));
+                batches.len() - 1
+            });
+            batches[index].2.extend_from_slice(transform.0.as_array());
+            self.stats.objects += 1;
+        }
+        batches.sort_by_key(|(material, mesh, _)| (material.sort_key(), Rc::as_ptr(mesh)));
+
+        let view_projection = camera.view_projection();
+        gbuffer.geometry.bind();
+        // Clears follow the depth mask and scissor
+        PipelineState::OPAQUE.apply();
+        unsafe {
+            gl::Viewport(0, 0, width as GLint, height as GLint);
+            let zero = [0.0f32; 4];
+            for index in 0..4 {
+                gl::ClearBufferfv(gl::COLOR, index, zero.as_ptr());
+            }
+            gl::ClearBufferfv(gl::DEPTH, 0, &1.0);
+        }
+        let program = self.gbuffer_material.program();
+        program.set();
+        program.set_mat4("u_view_projection", &view_projection);
+        let mut current_material: Option<*const Material> = None;
+        for (material, mesh, transforms) in &batches {
+            if current_material != Some(Rc::as_ptr(material)) {
+                material.apply();
+                current_material = Some(Rc::as_ptr(material));
+            }
+            self.instances.set(transforms);
+            draw_instanced(mesh, &self.instances, self.instances.len());
+            self.stats.draw_calls += 1;
+        }
+
+        // Lighting, added up in the light buffer
+        let lights = gather_lights(world);
+        self.stats.lights = lights.len() as u32;
+        let inverse_view_projection = view_projection.inverse().unwrap_or_else(Mat4::new);
+        gbuffer.begin_lighting();
+        LIGHTING.apply();
+        for program in [&self.ambient_program, &self.light_program] {
+            program.set();
+            program.set_int("u_albedo", ALBEDO_UNIT as i32);
+            program.set_int("u_normal", NORMAL_UNIT as i32);
+            program.set_int("u_material", MATERIAL_UNIT as i32);
+            program.set_int("u_depth", DEPTH_UNIT as i32);
+            program.set_mat4("u_inverse_view_projection", &inverse_view_projection);
+            program.set_vec3("u_camera_position", camera.position);
+        }
+
+        // Ambient comes with the first directional lights, more of them take more passes
+        let directional: Vec<usize> = (0..lights.len())
+            .filter(|&index| lights[index].kind == LightKind::Directional)
+            .collect();
+        let mut chunks: Vec<&[usize]> = directional.chunks(MAX_LIGHTS_PER_OBJECT).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+        self.ambient_program.set();
+        self.ambient_program.set_vec3("u_ambient", forward.ambient);
+        bind_environment(
+            &self.ambient_program,
+            forward
+                .environment()
+                .map(|environment| environment.as_ref()),
+        );
+        self.fullscreen.bind();
+        for (index, chunk) in chunks.iter().enumerate() {
+            if index == 1 {
+                self.ambient_program.set_vec3("u_ambient", [0.0; 3]);
+                self.ambient_program.set_float("u_ibl_intensity", 0.0);
+            }
+            upload_lights(&self.ambient_program, &lights, chunk);
+            unsafe {
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            }
+            self.stats.draw_calls += 1;
+        }
+        self.fullscreen.unbind();
+
+        let volumes: Vec<f32> = lights
+            .iter()
+            .filter(|light| light.kind != LightKind::Directional)
+            .flat_map(|light| {
+                let [x, y, z] = light.position;
+                let [r, g, b] = light.radiance;
+                let [dx, dy, dz] = light.direction;
+                let [inner, outer] = light.cone;
+                #[rustfmt::skip]
+                let values = [
+                    x, y, z, light.range,
+                    r, g, b, light.kind as i32 as f32,
+                    dx, dy, dz, inner,
+                    outer, 0.0, 0.0, 0.0,
+                ];
+                values
+            })
+            .collect();
+        if !volumes.is_empty() {
+            LIGHT_VOLUMES.apply();
+            self.light_program.set();
+            self.light_program
+                .set_mat4("u_view_projection", &view_projection);
+            self.light_program
+                .set_vec2("u_screen_size", [width as f32, height as f32]);
+            self.instances.set(&volumes);
+            draw_instanced(&self.sphere, &self.instances, self.instances.len());
+            self.stats.draw_calls += 1;
+        }
+
+        // Tone map into the target, with the depth so forward objects are hidden behind
+        saved.restore();
+        gbuffer.light.bind_unit(LIGHT_UNIT);
+        self.resolve_program.set();
+        self.resolve_program.set_int("u_light", LIGHT_UNIT as i32);
+        self.resolve_program.set_int("u_depth", DEPTH_UNIT as i32);
+        self.resolve_program
+            .set_float("u_exposure", forward.exposure);
+        RESOLVE.apply();
+        self.fullscreen.bind();
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        self.fullscreen.unbind();
+        self.stats.draw_calls += 1;
+
+        forward.render_filtered(world, camera, |renderer| !self.accepts(renderer));
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::core::custom_error::UbiError;
+use crate::core::math::transform::Mat4;
+use crate::{ubiinfo, ubiwarn};
+
+use super::buffer::Vao;
+use super::cubemap::{CubeMap, CUBE_FACES};
+use super::framebuffer::{Framebuffer, SavedTarget};
+use super::mesh::Mesh;
+use super::pipeline::{DepthState, PipelineState};
+use super::shader::Program;
+use super::texture::{Texture, TextureFormat};
+
+pub const ENVIRONMENT_SIZE: u32 = 512;
+pub const IRRADIANCE_SIZE: u32 = 32;
+pub const PREFILTERED_SIZE: u32 = 128;
+pub const PREFILTERED_MIPS: u32 = 5;
+pub const BRDF_LUT_SIZE: u32 = 256;
+
+// Texture units of the IBL maps, above the ones materials get
+pub const BRDF_LUT_UNIT: u32 = 13;
+pub const IRRADIANCE_UNIT: u32 = 14;
+pub const PREFILTERED_UNIT: u32 = 15;
+
+// Bump when the precomputation or the file layout changes
+const CACHE_MAGIC: &[u8; 8] = b"UBIIBL01";
+
+// Image based lighting computed from an HDR environment: diffuse irradiance,
+// specular radiance prefiltered per roughness in the mip chain, and the split sum BRDF table
+pub struct Environment {
+    irradiance: CubeMap,
+    prefiltered: CubeMap,
+    brdf_lut: Rc<Texture>,
+    pub intensity: f32,
+}
+
+impl Environment {
+    // Load an equirectangular .hdr and compute its maps, or read them back from `cache_dir`
+    // where they are stored after the first computation
+    pub fn from_hdr(path: impl AsRef<Path>, cache_dir: Option<&Path>) -> Result<Self, UbiError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|e| UbiError::ResourceError(format!("{}: {}", path.display(), e)))?;
+        let cache_path = cache_dir.map(|dir| cache_file(dir, path, &bytes));
+
+        if let Some(cache_path) = &cache_path {
+            if cache_path.exists() {
+                match Self::read_cache(cache_path) {
+                    Ok(environment) => return Ok(environment),
+                    Err(e) => {
+                        ubiwarn!("Ignoring IBL cache {}: {}", cache_path.display(), e);
+                    }
+                }
+            }
+        }
+
+        let image = image::load_from_memory(&bytes)
+            .map_err(|e| UbiError::TextureError(format!("{}: {}", path.display(), e)))?
+            .to_rgb32f();
+        let image = image::imageops::flip_vertical(&image);
+        let (width, height) = image.dimensions();
+        let equirect = Texture::from_f32(width, height, TextureFormat::Rgb16F, image.as_raw())?;
+        let environment = Self::compute(&equirect)?;
+
+        if let Some(cache_path) = &cache_path {
+            match environment.write_cache(cache_path) {
+                Ok(()) => {
+                    ubiinfo!(
+                        "Cached IBL maps of {} in {}",
+                        path.display(),
+                        cache_path.display()
+                    );
+                }
+                Err(e) => {
+                    ubiwarn!("Can not write IBL cache {}: {}", cache_path.display(), e);
+                }
+            }
+        }
+        Ok(environment)
+    }
+
+    pub fn irradiance(&self) -> &CubeMap {
+        &self.irradiance
+    }
+
+    pub fn prefiltered(&self) -> &CubeMap {
+        &self.prefiltered
+    }
+
+    pub fn brdf_lut(&self) -> &Rc<Texture> {
+        &self.brdf_lut
+    }
+
+    fn compute(equirect: &Texture) -> Result<Self, UbiError> {
+        let saved = SavedTarget::save();
+        PipelineState::OPAQUE
+            .with_depth(DepthState::DISABLED)
+            .apply();
+
+        let cube = Mesh::cube();
+        let framebuffer = Framebuffer::gen();
+        let program = |fragment: &str| {
+            Program::from_sources(
+                include_str!("../../../assets/shaders/cubemap.vert"),
+                fragment,
+            )
+        };
+
+        let environment = CubeMap::new(
+            ENVIRONMENT_SIZE,
+            mip_count(ENVIRONMENT_SIZE),
+            TextureFormat::Rgb16F,
+        );
+        let to_cube = program(include_str!(
+            "../../../assets/shaders/equirect_to_cube.frag"
+        ))?;
+        to_cube.set();
+        to_cube.set_int("u_equirect", 0);
+        equirect.bind_unit(0);
+        render_faces(&framebuffer, &to_cube, &environment, 0, &cube)?;
+        environment.generate_mipmaps();
+
+        let irradiance = CubeMap::new(IRRADIANCE_SIZE, 1, TextureFormat::Rgb16F);
+        let convolve = program(include_str!("../../../assets/shaders/irradiance.frag"))?;
+        convolve.set();
+        convolve.set_int("u_environment", 0);
+        environment.bind_unit(0);
+        render_faces(&framebuffer, &convolve, &irradiance, 0, &cube)?;
+
+        let prefiltered = CubeMap::new(PREFILTERED_SIZE, PREFILTERED_MIPS, TextureFormat::Rgb16F);
+        let prefilter = program(include_str!("../../../assets/shaders/prefilter.frag"))?;
+        prefilter.set();
+        prefilter.set_int("u_environment", 0);
+        prefilter.set_float("u_resolution", ENVIRONMENT_SIZE as f32);
+        environment.bind_unit(0);
+        for level in 0..PREFILTERED_MIPS {
+            prefilter.set_float("u_roughness", level as f32 / (PREFILTERED_MIPS - 1) as f32);
+            render_faces(&framebuffer, &prefilter, &prefiltered, level, &cube)?;
+        }
+
+        let brdf_lut = Texture::with_format(BRDF_LUT_SIZE, BRDF_LUT_SIZE, TextureFormat::Rg16F)?;
+        let integrate = Program::from_sources(
+            include_str!("../../../assets/shaders/fullscreen.vert"),
+            include_str!("../../../assets/shaders/brdf_lut.frag"),
+        )?;
+        framebuffer.attach_color(0, gl::TEXTURE_2D, brdf_lut.id, 0);
+        framebuffer.check()?;
+        integrate.set();
+        let vao = Vao::gen();
+        vao.bind();
+        unsafe {
+            gl::Viewport(0, 0, BRDF_LUT_SIZE as i32, BRDF_LUT_SIZE as i32);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        vao.unbind();
+
+        saved.restore();
+        Ok(Self {
+            irradiance,
+            prefiltered,
+            brdf_lut: Rc::new(brdf_lut),
+            intensity: 1.0,
+        })
+    }
+
+    // magic, the four sizes as u32, then irradiance faces, prefiltered faces per mip and the
+    // BRDF table as little endian f32
+    fn write_cache(&self, path: &Path) -> Result<(), UbiError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut out = Vec::new();
+        out.extend_from_slice(CACHE_MAGIC);
+        for size in [
+            IRRADIANCE_SIZE,
+            PREFILTERED_SIZE,
+            PREFILTERED_MIPS,
+            BRDF_LUT_SIZE,
+        ] {
+            out.extend_from_slice(&size.to_le_bytes());
+        }
+        let mut push = |data: Vec<f32>| {
+            data.iter()
+                .for_each(|v| out.extend_from_slice(&v.to_le_bytes()))
+        };
+        for face in 0..CUBE_FACES {
+            push(self.irradiance.read_face(face, 0));
+        }
+        for level in 0..PREFILTERED_MIPS {
+            for face in 0..CUBE_FACES {
+                push(self.prefiltered.read_face(face, level));
+            }
+        }
+        push(self.brdf_lut.read_f32());
+        fs::File::create(path)?.write_all(&out)?;
+        Ok(())
+    }
+
+    fn read_cache(path: &Path) -> Result<Self, UbiError> {
+        let mut bytes = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut bytes)?;
+        let invalid = |message: &str| UbiError::ResourceError(format!("IBL cache: {}", message));
+
+        if bytes.len() < 24 || &bytes[..8] != CACHE_MAGIC {
+            return Err(invalid("unknown format"));
+        }
+        let sizes: Vec<u32> = bytes[8..24]
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        if sizes
+            != [
+                IRRADIANCE_SIZE,
+                PREFILTERED_SIZE,
+                PREFILTERED_MIPS,
+                BRDF_LUT_SIZE,
+            ]
+        {
+            return Err(invalid("map sizes changed"));
+        }
+        let floats: Vec<f32> = bytes[24..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let irradiance = CubeMap::new(IRRADIANCE_SIZE, 1, TextureFormat::Rgb16F);
+        let prefiltered = CubeMap::new(PREFILTERED_SIZE, PREFILTERED_MIPS, TextureFormat::Rgb16F);
+        let mut offset = 0;
+        let mut take = |count: usize| -> Result<&[f32], UbiError> {
+            let slice = floats
+                .get(offset..offset + count)
+                .ok_or_else(|| invalid("file is truncated"))?;
+            offset += count;
+            Ok(slice)
+        };
+        let face_floats = |cube: &CubeMap, level: u32| {
+            let size = cube.level_size(level) as usize;
+            size * size * cube.format().channels()
+        };
+        for face in 0..CUBE_FACES {
+            irradiance.write_face(face, 0, take(face_floats(&irradiance, 0))?);
+        }
+        for level in 0..PREFILTERED_MIPS {
+            for face in 0..CUBE_FACES {
+                prefiltered.write_face(face, level, take(face_floats(&prefiltered, level))?);
+            }
+        }
+        let lut_floats = (BRDF_LUT_SIZE * BRDF_LUT_SIZE) as usize * TextureFormat::Rg16F.channels();
+        let brdf_lut = Texture::from_f32(
+            BRDF_LUT_SIZE,
+            BRDF_LUT_SIZE,
+            TextureFormat::Rg16F,
+            take(lut_floats)?,
+        )?;
+
+        Ok(Self {
+            irradiance,
+            prefiltered,
+            brdf_lut: Rc::new(brdf_lut),
+            intensity: 1.0,
+        })
+    }
+}
+
+// Point the IBL samplers of a program in use at their units and bind the maps.
+// Without an environment u_ibl_intensity is 0 and only the flat ambient term remains.
+pub fn bind_environment(program: &Program, environment: Option<&Environment>) {
+    program.set_int("u_brdf_lut", BRDF_LUT_UNIT as i32);
+    program.set_int("u_irradiance_map", IRRADIANCE_UNIT as i32);
+    program.set_int("u_prefiltered_map", PREFILTERED_UNIT as i32);
+    program.set_float("u_prefilter_mips", PREFILTERED_MIPS as f32);
+    match environment {
+        Some(environment) => {
+            environment.brdf_lut.bind_unit(BRDF_LUT_UNIT);
+            environment.irradiance.bind_unit(IRRADIANCE_UNIT);
+            environment.prefiltered.bind_unit(PREFILTERED_UNIT);
+            program.set_float("u_ibl_intensity", environment.intensity);
+        }
+        None => program.set_float("u_ibl_intensity", 0.0),
+    }
+}
+
+fn mip_count(size: u32) -> u32 {
+    32 - size.max(1).leading_zeros()
+}
+
+// Cache files are keyed by the name and the content of the source image
+fn cache_file(dir: &Path, source: &Path, bytes: &[u8]) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "environment".to_string());
+    dir.join(format!("{}-{:016x}.ibl", stem, fnv1a(bytes)))
+}
+
+// 64 bit FNV-1a, stable across Rust releases unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Render the cube mesh seen from its center into the six faces of mip `level`
+fn render_faces(
+    framebuffer: &Framebuffer,
+    program: &Program,
+    target: &CubeMap,
+    level: u32,
+    cube: &Mesh,
+) -> Result<(), UbiError> {
+    // target and up vector of each face, in GL face order
+    const FACES: [([f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+    ];
+    let mut projection = Mat4::new();
+    projection.perspective(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 10.0);
+    let size = target.level_size(level) as i32;
+
+    program.set();
+    for (face, (look, up)) in FACES.iter().enumerate() {
+        let mut view_projection = Mat4::new();
+        view_projection.lookat(
+            0.0, 0.0, 0.0, look[0], look[1], look[2], up[0], up[1], up[2],
+        );
+        view_projection.mult(projection);
+        program.set_mat4("u_view_projection", &view_projection);
+
+        framebuffer.attach_color(
+            0,
+            gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+            target.id,
+            level as i32,
+        );
+        framebuffer.check()?;
+        unsafe {
+            gl::Viewport(0, 0, size, size);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        cube.draw();
+    }
+    Ok(())
+}
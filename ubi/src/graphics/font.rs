@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use ab_glyph::{Font as _, FontVec, GlyphId, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+
+use crate::core::custom_error::UbiError;
+
+use super::texture::Texture;
+
+const INITIAL_ATLAS_SIZE: u32 = 256;
+const MAX_ATLAS_SIZE: u32 = 4096;
+// Empty pixels between glyphs so linear filtering does not bleed
+const GLYPH_PADDING: u32 = 1;
+
+// How glyphs are rasterized into the atlas
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FontRenderMode {
+    // Coverage bitmaps, one per glyph and pixel size
+    #[default]
+    Bitmap,
+    // Signed distance fields rasterized once at `base_size` and scaled at draw time.
+    // `spread` is the distance in pixels encoded around the outline.
+    Sdf {
+        base_size: f32,
+        spread: u32,
+    },
+}
+
+// A glyph stored in the atlas, offsets are relative to the pen position on the baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasGlyph {
+    // Pixel rectangle in the atlas, (0, 0) is the bottom left corner
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Left edge relative to the pen position
+    pub left: f32,
+    // Top edge above the baseline
+    pub top: f32,
+    // Atlas pixels per layout pixel, only differs from 1.0 for SDF glyphs
+    pub scale: f32,
+}
+
+// Line metrics of a font at one pixel size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    // Negative, below the baseline
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+impl FontMetrics {
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    id: u16,
+    // Pixel size in 1/64th, always 0 for SDF glyphs
+    size: u32,
+}
+
+// A TrueType/OpenType font with the atlas of the glyphs drawn so far
+pub struct Font {
+    font: FontVec,
+    mode: FontRenderMode,
+    atlas: GlyphAtlas,
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+}
+
+impl Font {
+    pub fn from_file(path: &str) -> Result<Self, UbiError> {
+        let data = std::fs::read(path)?;
+        Self::from_bytes(data).map_err(|e| match e {
+            UbiError::ResourceError(message) => {
+                UbiError::ResourceError(format!("{}: {}", path, message))
+            }
+            other => other,
+        })
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, UbiError> {
+        let font = FontVec::try_from_vec(data)
+            .map_err(|e| UbiError::ResourceError(format!("invalid font: {}", e)))?;
+        Ok(Self {
+            font,
+            mode: FontRenderMode::default(),
+            atlas: GlyphAtlas::new()?,
+            glyphs: HashMap::new(),
+        })
+    }
+
+    // Switching the mode drops every glyph rasterized so far
+    pub fn set_render_mode(&mut self, mode: FontRenderMode) {
+        if self.mode != mode {
+            self.mode = mode;
+            self.glyphs.clear();
+            self.atlas.clear();
+        }
+    }
+
+    pub fn render_mode(&self) -> FontRenderMode {
+        self.mode
+    }
+
+    pub fn metrics(&self, size: f32) -> FontMetrics {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        FontMetrics {
+            ascent: scaled.ascent(),
+            descent: scaled.descent(),
+            line_gap: scaled.line_gap(),
+        }
+    }
+
+    pub fn glyph_id(&self, c: char) -> u16 {
+        self.font.glyph_id(c).0
+    }
+
+    pub fn advance(&self, glyph: u16, size: f32) -> f32 {
+        self.font
+            .as_scaled(PxScale::from(size))
+            .h_advance(GlyphId(glyph))
+    }
+
+    pub fn kerning(&self, first: u16, second: u16, size: f32) -> f32 {
+        self.font
+            .as_scaled(PxScale::from(size))
+            .kern(GlyphId(first), GlyphId(second))
+    }
+
+    // The atlas texture, only valid until the next call to `glyph`
+    pub fn texture(&self) -> &Texture {
+        &self.atlas.texture
+    }
+
+    pub fn atlas_size(&self) -> (u32, u32) {
+        (self.atlas.width, self.atlas.height)
+    }
+
+    // Rasterize `glyph` at `size` pixels if needed, None for glyphs without an outline
+    pub fn glyph(&mut self, glyph: u16, size: f32) -> Result<Option<AtlasGlyph>, UbiError> {
+        let key = match self.mode {
+            FontRenderMode::Bitmap => GlyphKey {
+                id: glyph,
+                size: (size * 64.0).round() as u32,
+            },
+            FontRenderMode::Sdf { .. } => GlyphKey { id: glyph, size: 0 },
+        };
+        if let Some(cached) = self.glyphs.get(&key) {
+            return Ok(cached.map(|g| scale_sdf_glyph(g, self.mode, size)));
+        }
+
+        let stored = match self.mode {
+            FontRenderMode::Bitmap => self.rasterize(glyph, size, 0)?,
+            FontRenderMode::Sdf { base_size, spread } => {
+                self.rasterize(glyph, base_size, spread)?
+            }
+        };
+        self.glyphs.insert(key, stored);
+        Ok(stored.map(|g| scale_sdf_glyph(g, self.mode, size)))
+    }
+
+    // Upload pending glyphs to the atlas texture
+    pub fn flush_atlas(&mut self) -> Result<(), UbiError> {
+        self.atlas.upload()
+    }
+
+    fn rasterize(
+        &mut self,
+        glyph: u16,
+        size: f32,
+        spread: u32,
+    ) -> Result<Option<AtlasGlyph>, UbiError> {
+        let glyph = GlyphId(glyph).with_scale(PxScale::from(size));
+        let Some(outlined) = self.font.outline_glyph(glyph) else {
+            return Ok(None);
+        };
+
+        let bounds = outlined.px_bounds();
+        let (w, h) = (bounds.width() as u32, bounds.height() as u32);
+        let mut coverage = vec![0.0f32; (w * h) as usize];
+        outlined.draw(|x, y, c| {
+            if x < w && y < h {
+                coverage[(y * w + x) as usize] = c;
+            }
+        });
+
+        // Distance fields need room around the outline to fade out
+        let (width, height) = (w + 2 * spread, h + 2 * spread);
+        let pixels = if spread == 0 {
+            coverage
+        } else {
+            signed_distance_field(&coverage, w, h, spread)
+        };
+
+        let (x, y) = self.allocate(width, height)?;
+        // Coverage rows go top to bottom, the atlas is stored bottom row first
+        for row in 0..height {
+            for column in 0..width {
+                let alpha = pixels[(row * width + column) as usize];
+                self.atlas.image.put_pixel(
+                    x + column,
+                    y + height - 1 - row,
+                    Rgba([255, 255, 255, (alpha.clamp(0.0, 1.0) * 255.0) as u8]),
+                );
+            }
+        }
+        self.atlas.dirty = true;
+
+        Ok(Some(AtlasGlyph {
+            x,
+            y,
+            width,
+            height,
+            left: bounds.min.x - spread as f32,
+            top: -bounds.min.y + spread as f32,
+            scale: 1.0,
+        }))
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Result<(u32, u32), UbiError> {
+        loop {
+            if let Some(position) = self.atlas.allocate(width, height) {
+                return Ok(position);
+            }
+            self.atlas.grow()?;
+        }
+    }
+}
+
+// SDF glyphs are stored at the base size, report them at the requested one
+fn scale_sdf_glyph(glyph: AtlasGlyph, mode: FontRenderMode, size: f32) -> AtlasGlyph {
+    match mode {
+        FontRenderMode::Bitmap => glyph,
+        FontRenderMode::Sdf { base_size, .. } => {
+            let factor = size / base_size;
+            AtlasGlyph {
+                left: glyph.left * factor,
+                top: glyph.top * factor,
+                scale: 1.0 / factor,
+                ..glyph
+            }
+        }
+    }
+}
+
+// Map coverage to a distance field where 0.5 lies on the outline
+fn signed_distance_field(coverage: &[f32], w: u32, h: u32, spread: u32) -> Vec<f32> {
+    let (width, height) = (w + 2 * spread, h + 2 * spread);
+    let inside = |x: i64, y: i64| -> bool {
+        x >= 0
+            && y >= 0
+            && x < w as i64
+            && y < h as i64
+            && coverage[(y as u32 * w + x as u32) as usize] >= 0.5
+    };
+
+    let spread = spread as i64;
+    let mut field = vec![0.0; (width * height) as usize];
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let (cx, cy) = (x - spread, y - spread);
+            let is_inside = inside(cx, cy);
+            let mut nearest = (spread * spread) as f32;
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    if inside(cx + dx, cy + dy) != is_inside {
+                        nearest = nearest.min((dx * dx + dy * dy) as f32);
+                    }
+                }
+            }
+            let distance = nearest.sqrt() / spread as f32;
+            let signed = if is_inside { distance } else { -distance };
+            field[(y * width as i64 + x) as usize] = 0.5 + signed * 0.5;
+        }
+    }
+    field
+}
+
+// Glyph images packed in shelves from the bottom of a texture that doubles when full
+struct GlyphAtlas {
+    texture: Texture,
+    image: RgbaImage,
+    width: u32,
+    height: u32,
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+    dirty: bool,
+}
+
+impl GlyphAtlas {
+    fn new() -> Result<Self, UbiError> {
+        let image = RgbaImage::new(INITIAL_ATLAS_SIZE, INITIAL_ATLAS_SIZE);
+        let mut texture =
+            Texture::from_rgba(INITIAL_ATLAS_SIZE, INITIAL_ATLAS_SIZE, image.as_raw())?;
+        texture.set_sampling(gl::LINEAR);
+        Ok(Self {
+            texture,
+            image,
+            width: INITIAL_ATLAS_SIZE,
+            height: INITIAL_ATLAS_SIZE,
+            shelf_x: GLYPH_PADDING,
+            shelf_y: GLYPH_PADDING,
+            shelf_height: 0,
+            dirty: false,
+        })
+    }
+
+    fn clear(&mut self) {
+        self.image = RgbaImage::new(self.width, self.height);
+        self.shelf_x = GLYPH_PADDING;
+        self.shelf_y = GLYPH_PADDING;
+        self.shelf_height = 0;
+        self.dirty = true;
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.shelf_x + width + GLYPH_PADDING > self.width {
+            self.shelf_y += self.shelf_height + GLYPH_PADDING;
+            self.shelf_x = GLYPH_PADDING;
+            self.shelf_height = 0;
+        }
+        if self.shelf_x + width + GLYPH_PADDING > self.width
+            || self.shelf_y + height + GLYPH_PADDING > self.height
+        {
+            return None;
+        }
+        let position = (self.shelf_x, self.shelf_y);
+        self.shelf_x += width + GLYPH_PADDING;
+        self.shelf_height = self.shelf_height.max(height);
+        Some(position)
+    }
+
+    // Double both sides, existing glyphs keep their pixel positions
+    fn grow(&mut self) -> Result<(), UbiError> {
+        if self.width >= MAX_ATLAS_SIZE && self.height >= MAX_ATLAS_SIZE {
+            return Err(UbiError::TextureError(format!(
+                "glyph atlas is full at {}x{}",
+                self.width, self.height
+            )));
+        }
+        let width = (self.width * 2).min(MAX_ATLAS_SIZE);
+        let height = (self.height * 2).min(MAX_ATLAS_SIZE);
+        let mut image = RgbaImage::new(width, height);
+        image::imageops::replace(&mut image, &self.image, 0, 0);
+        self.image = image;
+        // The current shelf can now extend to the right
+        self.width = width;
+        self.height = height;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn upload(&mut self) -> Result<(), UbiError> {
+        if self.dirty {
+            self.texture
+                .set_rgba(self.width, self.height, self.image.as_raw())?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::core::custom_error::UbiError;
+use crate::core::ecs::transform::GlobalTransform;
+use crate::core::ecs::world::World;
+use crate::core::math::bounds::Frustum;
+use crate::core::math::vector::distance;
+
+use super::camera::Camera;
+use super::culling::{is_visible, CullingStats};
+use super::environment::{bind_environment, Environment};
+use super::instancing::{draw_instanced, InstanceBuffer, MeshRenderer};
+use super::light::{
+    gather_lights, select_lights, upload_lights, GatheredLight, MAX_LIGHTS_PER_OBJECT,
+};
+use super::material::Material;
+use super::mesh::Mesh;
+use super::shader::Program;
+use super::shadow::{bind_shadows, ShadowPass, ShadowSettings};
+use super::texture::Texture;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ForwardStats {
+    pub objects: u32,
+    pub lights: u32,
+    pub draw_calls: u32,
+    pub culling: CullingStats,
+}
+
+struct Batch {
+    material: Rc<Material>,
+    mesh: Rc<Mesh>,
+    lights: Vec<usize>,
+    transforms: Vec<f32>,
+}
+
+// Draws every `MeshRenderer` lit by the `DirectionalLight`, `PointLight` and `SpotLight`
+// components of the world. Each object gets its most relevant lights, objects sharing a
+// mesh, a material and the same lights are instanced together. Transparent materials
+// are blended after the opaque objects, farthest first.
+pub struct ForwardPass {
+    default_material: Rc<Material>,
+    pbr_material: Rc<Material>,
+    environment: Option<Rc<Environment>>,
+    shadows: Option<ShadowPass>,
+    instances: InstanceBuffer,
+    pub ambient: [f32; 3],
+    // Tone mapping exposure of the PBR shader
+    pub exposure: f32,
+    // Lights per object, at most MAX_LIGHTS_PER_OBJECT
+    pub max_lights: usize,
+    stats: ForwardStats,
+}
+
+impl ForwardPass {
+    pub fn new() -> Result<Self, UbiError> {
+        let program = Program::from_sources(
+            include_str!("../../../assets/shaders/lit.vert"),
+            include_str!("../../../assets/shaders/lit.frag"),
+        )?;
+        let white = Rc::new(Texture::from_rgba(1, 1, &[255, 255, 255, 255])?);
+        let flat_normal = Rc::new(Texture::from_rgba(1, 1, &[128, 128, 255, 255])?);
+
+        let mut material = Material::new(Rc::new(program));
+        material.set("u_diffuse_color", [1.0, 1.0, 1.0, 1.0])?;
+        material.set("u_specular_color", [0.5, 0.5, 0.5])?;
+        material.set("u_shininess", 32.0)?;
+        material.set_texture("u_diffuse_map", white.clone())?;
+        material.set_texture("u_specular_map", white.clone())?;
+        material.set_texture("u_normal_map", flat_normal.clone())?;
+
+        let pbr_program = Program::from_sources(
+            include_str!("../../../assets/shaders/lit.vert"),
+            include_str!("../../../assets/shaders/pbr.frag"),
+        )?;
+        // glTF defaults, except for a dielectric surface instead of a rough metal
+        let mut pbr = Material::new(Rc::new(pbr_program));
+        pbr.set("u_base_color_factor", [1.0, 1.0, 1.0, 1.0])?;
+        pbr.set("u_metallic_factor", 0.0)?;
+        pbr.set("u_roughness_factor", 0.5)?;
+        pbr.set("u_normal_scale", 1.0)?;
+        pbr.set("u_occlusion_strength", 1.0)?;
+        pbr.set("u_emissive_factor", [0.0, 0.0, 0.0])?;
+        pbr.set_texture("u_base_color_map", white.clone())?;
+        pbr.set_texture("u_metallic_roughness_map", white.clone())?;
+        pbr.set_texture("u_normal_map", flat_normal)?;
+        pbr.set_texture("u_occlusion_map", white.clone())?;
+        pbr.set_texture("u_emissive_map", white)?;
+
+        Ok(Self {
+            default_material: Rc::new(material),
+            pbr_material: Rc::new(pbr),
+            environment: None,
+            shadows: None,
+            instances: InstanceBuffer::transforms(),
+            ambient: [0.05, 0.05, 0.05],
+            exposure: 1.0,
+            max_lights: MAX_LIGHTS_PER_OBJECT,
+            stats: ForwardStats::default(),
+        })
+    }
+
+    // Blinn-Phong material, make instances of it to change colors and maps:
+    // u_diffuse_color (vec4), u_specular_color (vec3), u_shininess (float),
+    // u_diffuse_map, u_specular_map and u_normal_map (tangent space)
+    pub fn default_material(&self) -> &Rc<Material> {
+        &self.default_material
+    }
+
+    // Metallic-roughness material with the glTF parameters:
+    // u_base_color_factor (vec4), u_metallic_factor, u_roughness_factor, u_normal_scale,
+    // u_occlusion_strength (floats), u_emissive_factor (vec3), u_base_color_map (sRGB),
+    // u_metallic_roughness_map (g roughness, b metallic), u_normal_map, u_occlusion_map (r)
+    // and u_emissive_map (sRGB)
+    pub fn pbr_material(&self) -> &Rc<Material> {
+        &self.pbr_material
+    }
+
+    // Image based lighting for PBR materials, `None` leaves only the flat ambient term
+    pub fn set_environment(&mut self, environment: Option<Rc<Environment>>) {
+        self.environment = environment;
+    }
+
+    pub fn environment(&self) -> Option<&Rc<Environment>> {
+        self.environment.as_ref()
+    }
+
+    // Shadows of the lights with `casts_shadows`, replaces the settings if already enabled
+    pub fn enable_shadows(&mut self, settings: ShadowSettings) -> Result<(), UbiError> {
+        match &mut self.shadows {
+            Some(shadows) => shadows.set_settings(settings),
+            None => {
+                self.shadows = Some(ShadowPass::new(settings)?);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn disable_shadows(&mut self) {
+        self.shadows = None;
+    }
+
+    pub fn shadows(&self) -> Option<&ShadowPass> {
+        self.shadows.as_ref()
+    }
+
+    // Stats of the last `render`
+    pub fn stats(&self) -> ForwardStats {
+        self.stats
+    }
+
+    pub fn render(&mut self, world: &World, camera: &Camera) {
+        self.render_filtered(world, camera, |_| true);
+    }
+
+    // `render` restricted to the renderers `filter` accepts
+    pub fn render_filtered(
+        &mut self,
+        world: &World,
+        camera: &Camera,
+        filter: impl Fn(&MeshRenderer) -> bool,
+    ) {
+        self.stats = ForwardStats::default();
+        let mut lights = gather_lights(world);
+        if let Some(shadows) = &mut self.shadows {
+            shadows.render(world, camera, &mut lights);
+        }
+        let max_lights = self.max_lights.min(MAX_LIGHTS_PER_OBJECT);
+        let frustum = Frustum::from_matrix(&camera.view_projection());
+
+        let mut batches: Vec<Batch> = Vec::new();
+        let mut transparent: Vec<(f32, Batch)> = Vec::new();
+        let mut lookup: HashMap<(*const Material, *const Mesh, Vec<usize>), usize> = HashMap::new();
+        for (_, renderer, transform) in world.query2::<MeshRenderer, GlobalTransform>() {
+            if !filter(renderer) {
+                continue;
+            }
+            let visible = is_visible(&frustum, &renderer.mesh, &transform.0);
+            if !self.stats.culling.record(visible) {
+                continue;
+            }
+            let position = transform.0.translation();
+            let selected = select_lights(&lights, position, max_lights);
+            self.stats.objects += 1;
+            // Blending needs them one by one, in order
+            if renderer.material.is_transparent() {
+                transparent.push((
+                    distance(position, camera.position),
+                    Batch {
+                        material: renderer.material.clone(),
+                        mesh: renderer.mesh.clone(),
+                        lights: selected,
+                        transforms: transform.0.as_array().to_vec(),
+                    },
+                ));
+                continue;
+            }
+            let key = (
+                Rc::as_ptr(&renderer.material),
+                Rc::as_ptr(&renderer.mesh),
+                selected,
+            );
+            let index = match lookup.get(&key) {
+                Some(index) => *index,
+                None => {
+                    batches.push(Batch {
+                        material: renderer.material.clone(),
+                        mesh: renderer.mesh.clone(),
+                        lights: key.2.clone(),
+                        transforms: Vec::new(),
+                    });
+                    lookup.insert(key, batches.len() - 1);
+                    batches.len() - 1
+                }
+            };
+            batches[index]
+                .transforms
+                .extend_from_slice(transform.0.as_array());
+        }
+        batches.sort_by(|a, b| {
+            (a.material.sort_key(), Rc::as_ptr(&a.mesh), &a.lights).cmp(&(
+                b.material.sort_key(),
+                Rc::as_ptr(&b.mesh),
+                &b.lights,
+            ))
+        });
+        self.stats.lights = lights.len() as u32;
+
+        // Blending and depth writes come from the pipeline of each material
+        self.draw_batches(&batches, &lights, camera);
+        if !transparent.is_empty() {
+            transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let transparent: Vec<Batch> = transparent.into_iter().map(|(_, batch)| batch).collect();
+            self.draw_batches(&transparent, &lights, camera);
+        }
+    }
+
+    fn draw_batches(&mut self, batches: &[Batch], lights: &[GatheredLight], camera: &Camera) {
+        let view_projection = camera.view_projection();
+        let mut current_material: Option<*const Material> = None;
+        let mut current_program: Option<*const Program> = None;
+        let mut current_lights: Option<&Vec<usize>> = None;
+        for batch in batches {
+            if current_material != Some(Rc::as_ptr(&batch.material)) {
+                batch.material.apply();
+                current_material = Some(Rc::as_ptr(&batch.material));
+            }
+            let program = batch.material.program();
+            if current_program != Some(Rc::as_ptr(program)) {
+                program.set_mat4("u_view_projection", &view_projection);
+                program.set_vec3("u_camera_position", camera.position);
+                program.set_vec3("u_ambient", self.ambient);
+                program.set_float("u_exposure", self.exposure);
+                bind_environment(program, self.environment.as_deref());
+                bind_shadows(program, self.shadows.as_ref());
+                current_program = Some(Rc::as_ptr(program));
+                current_lights = None;
+            }
+            if current_lights != Some(&batch.lights) {
+                upload_lights(program, lights, &batch.lights);
+                current_lights = Some(&batch.lights);
+            }
+            self.instances.set(&batch.transforms);
+            draw_instanced(&batch.mesh, &self.instances, self.instances.len());
+            self.stats.draw_calls += 1;
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+
+use crate::core::custom_error::UbiError;
+
+use super::gl_state;
+
+// OpenGL Framebuffer Object, renders into textures instead of the window
+pub struct Framebuffer {
+    id: GLuint,
+    depth_renderbuffer: Option<GLuint>,
+}
+
+impl Framebuffer {
+    pub fn gen() -> Self {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        Framebuffer {
+            id,
+            depth_renderbuffer: None,
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn bind(&self) {
+        gl_state::bind_framebuffer(self.id);
+    }
+
+    // Back to the window
+    pub fn unbind(&self) {
+        gl_state::bind_framebuffer(0);
+    }
+
+    // Attach mip `level` of a texture as color attachment `index`.
+    // `target` is gl::TEXTURE_2D or one of the gl::TEXTURE_CUBE_MAP_* faces.
+    pub fn attach_color(&self, index: u32, target: GLenum, texture: GLuint, level: i32) {
+        self.bind();
+        unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0 + index,
+                target,
+                texture,
+                level,
+            );
+        }
+    }
+
+    pub fn attach_depth(&self, target: GLenum, texture: GLuint, level: i32) {
+        self.bind();
+        unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                target,
+                texture,
+                level,
+            );
+        }
+    }
+
+    // Depth storage that is never sampled, replaced when called again
+    pub fn attach_depth_renderbuffer(&mut self, width: u32, height: u32) {
+        self.bind();
+        unsafe {
+            let mut id = self.depth_renderbuffer.unwrap_or(0);
+            if id == 0 {
+                gl::GenRenderbuffers(1, &mut id);
+                self.depth_renderbuffer = Some(id);
+            }
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH_COMPONENT24,
+                width as GLsizei,
+                height as GLsizei,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                id,
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+    }
+
+    // Write to the first `count` color attachments, 0 for depth only rendering
+    pub fn set_draw_buffers(&self, count: u32) {
+        self.bind();
+        let buffers: Vec<GLenum> = (0..count).map(|i| gl::COLOR_ATTACHMENT0 + i).collect();
+        unsafe {
+            if buffers.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+            }
+        }
+    }
+
+    pub fn check(&self) -> Result<(), UbiError> {
+        self.bind();
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(UbiError::GlError(format!(
+                "framebuffer {} is incomplete (status 0x{:x})",
+                self.id, status
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        gl_state::forget_framebuffer(self.id);
+        unsafe {
+            if let Some(id) = self.depth_renderbuffer {
+                gl::DeleteRenderbuffers(1, &id);
+            }
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+// Framebuffer and viewport in use, to put them back after rendering offscreen
+pub(crate) struct SavedTarget {
+    framebuffer: GLint,
+    viewport: [GLint; 4],
+}
+
+impl SavedTarget {
+    pub(crate) fn save() -> Self {
+        let mut framebuffer = 0;
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        Self {
+            framebuffer,
+            viewport,
+        }
+    }
+
+    // x, y, width, height
+    pub(crate) fn viewport(&self) -> [GLint; 4] {
+        self.viewport
+    }
+
+    pub(crate) fn restore(&self) {
+        let [x, y, w, h] = self.viewport;
+        unsafe {
+            gl_state::bind_framebuffer(self.framebuffer as GLuint);
+            gl::Viewport(x, y, w, h);
+        }
+    }
+}
@@ -0,0 +1,456 @@
+use std::cell::RefCell;
+
+use gl::types::{GLenum, GLint, GLuint};
+
+// Texture units whose bindings are tracked, higher ones always call GL
+pub const MAX_TRACKED_TEXTURE_UNITS: usize = 16;
+
+// Capabilities toggled through `set_enabled`, others are passed to GL as is
+const CAPABILITIES: [GLenum; 6] = [
+    gl::BLEND,
+    gl::DEPTH_TEST,
+    gl::CULL_FACE,
+    gl::STENCIL_TEST,
+    gl::SCISSOR_TEST,
+    gl::POLYGON_OFFSET_FILL,
+];
+
+// Last values given to GL, `None` when unknown so the next call goes through
+#[derive(Debug, Clone)]
+struct GlState {
+    program: Option<GLuint>,
+    vertex_array: Option<GLuint>,
+    array_buffer: Option<GLuint>,
+    // Part of the vertex array state, unknown again when the vao changes
+    element_buffer: Option<GLuint>,
+    framebuffer: Option<GLuint>,
+    active_unit: Option<u32>,
+    // 2D and cube map texture of each unit
+    textures: [[Option<GLuint>; 2]; MAX_TRACKED_TEXTURE_UNITS],
+    capabilities: [Option<bool>; CAPABILITIES.len()],
+    depth_mask: Option<bool>,
+    depth_func: Option<GLenum>,
+    // Color source and destination, then alpha ones
+    blend_func: Option<(GLenum, GLenum, GLenum, GLenum)>,
+    blend_equation: Option<(GLenum, GLenum)>,
+    cull_face: Option<GLenum>,
+    polygon_offset: Option<(f32, f32)>,
+    polygon_mode: Option<GLenum>,
+    scissor: Option<[GLint; 4]>,
+    stencil_func: Option<(GLenum, GLint, GLuint)>,
+    stencil_op: Option<(GLenum, GLenum, GLenum)>,
+    stencil_mask: Option<GLuint>,
+}
+
+impl GlState {
+    // Values of a fresh context
+    fn defaults() -> Self {
+        Self {
+            program: Some(0),
+            vertex_array: Some(0),
+            array_buffer: Some(0),
+            element_buffer: Some(0),
+            framebuffer: Some(0),
+            active_unit: Some(0),
+            textures: [[Some(0); 2]; MAX_TRACKED_TEXTURE_UNITS],
+            capabilities: [Some(false); CAPABILITIES.len()],
+            depth_mask: Some(true),
+            depth_func: Some(gl::LESS),
+            blend_func: Some((gl::ONE, gl::ZERO, gl::ONE, gl::ZERO)),
+            blend_equation: Some((gl::FUNC_ADD, gl::FUNC_ADD)),
+            cull_face: Some(gl::BACK),
+            polygon_offset: Some((0.0, 0.0)),
+            polygon_mode: Some(gl::FILL),
+            // The window size, unknown here
+            scissor: None,
+            stencil_func: Some((gl::ALWAYS, 0, !0)),
+            stencil_op: Some((gl::KEEP, gl::KEEP, gl::KEEP)),
+            stencil_mask: Some(!0),
+        }
+    }
+
+    fn unknown() -> Self {
+        Self {
+            program: None,
+            vertex_array: None,
+            array_buffer: None,
+            element_buffer: None,
+            framebuffer: None,
+            active_unit: None,
+            textures: [[None; 2]; MAX_TRACKED_TEXTURE_UNITS],
+            capabilities: [None; CAPABILITIES.len()],
+            depth_mask: None,
+            depth_func: None,
+            blend_func: None,
+            blend_equation: None,
+            cull_face: None,
+            polygon_offset: None,
+            polygon_mode: None,
+            scissor: None,
+            stencil_func: None,
+            stencil_op: None,
+            stencil_mask: None,
+        }
+    }
+}
+
+thread_local! {
+    // GL contexts are current on one thread, the engine uses a single one
+    static STATE: RefCell<GlState> = RefCell::new(GlState::defaults());
+}
+
+// Run `apply` if `value` differs from the cached one, then remember it
+fn update<T: PartialEq + Copy>(
+    field: impl FnOnce(&mut GlState) -> &mut Option<T>,
+    value: T,
+    apply: impl FnOnce(T),
+) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let cached = field(&mut state);
+        if *cached != Some(value) {
+            *cached = Some(value);
+            apply(value);
+        }
+    });
+}
+
+fn texture_target_index(target: GLenum) -> Option<usize> {
+    match target {
+        gl::TEXTURE_2D => Some(0),
+        gl::TEXTURE_CUBE_MAP => Some(1),
+        _ => None,
+    }
+}
+
+pub fn use_program(id: GLuint) {
+    update(|s| &mut s.program, id, |id| unsafe { gl::UseProgram(id) });
+}
+
+pub fn bind_vertex_array(id: GLuint) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.vertex_array != Some(id) {
+            state.vertex_array = Some(id);
+            state.element_buffer = None;
+            unsafe { gl::BindVertexArray(id) }
+        }
+    });
+}
+
+// Array and element array buffers are tracked, other targets always call GL
+pub fn bind_buffer(target: GLenum, id: GLuint) {
+    match target {
+        gl::ARRAY_BUFFER => update(
+            |s| &mut s.array_buffer,
+            id,
+            |id| unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, id) },
+        ),
+        gl::ELEMENT_ARRAY_BUFFER => update(
+            |s| &mut s.element_buffer,
+            id,
+            |id| unsafe { gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, id) },
+        ),
+        _ => unsafe { gl::BindBuffer(target, id) },
+    }
+}
+
+// Both read and draw framebuffer
+pub fn bind_framebuffer(id: GLuint) {
+    update(
+        |s| &mut s.framebuffer,
+        id,
+        |id| unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, id) },
+    );
+}
+
+pub fn active_texture(unit: u32) {
+    update(
+        |s| &mut s.active_unit,
+        unit,
+        |unit| unsafe { gl::ActiveTexture(gl::TEXTURE0 + unit) },
+    );
+}
+
+// Bind to the active texture unit
+pub fn bind_texture(target: GLenum, id: GLuint) {
+    let unit = STATE.with(|state| state.borrow().active_unit);
+    match (unit, texture_target_index(target)) {
+        (Some(unit), Some(index)) if (unit as usize) < MAX_TRACKED_TEXTURE_UNITS => update(
+            |s| &mut s.textures[unit as usize][index],
+            id,
+            |id| unsafe { gl::BindTexture(target, id) },
+        ),
+        _ => unsafe { gl::BindTexture(target, id) },
+    }
+}
+
+// Bind to texture unit `unit`, leaves that unit active
+pub fn bind_texture_unit(unit: u32, target: GLenum, id: GLuint) {
+    active_texture(unit);
+    bind_texture(target, id);
+}
+
+pub fn set_enabled(capability: GLenum, enabled: bool) {
+    let apply = |enabled| unsafe {
+        if enabled {
+            gl::Enable(capability);
+        } else {
+            gl::Disable(capability);
+        }
+    };
+    match CAPABILITIES
+        .iter()
+        .position(|tracked| *tracked == capability)
+    {
+        Some(index) => update(|s| &mut s.capabilities[index], enabled, apply),
+        None => apply(enabled),
+    }
+}
+
+pub fn depth_mask(write: bool) {
+    update(
+        |s| &mut s.depth_mask,
+        write,
+        |write| unsafe { gl::DepthMask(if write { gl::TRUE } else { gl::FALSE }) },
+    );
+}
+
+pub fn depth_func(func: GLenum) {
+    update(
+        |s| &mut s.depth_func,
+        func,
+        |func| unsafe { gl::DepthFunc(func) },
+    );
+}
+
+pub fn blend_func(source: GLenum, destination: GLenum) {
+    blend_func_separate(source, destination, source, destination);
+}
+
+pub fn blend_func_separate(
+    source_color: GLenum,
+    destination_color: GLenum,
+    source_alpha: GLenum,
+    destination_alpha: GLenum,
+) {
+    update(
+        |s| &mut s.blend_func,
+        (
+            source_color,
+            destination_color,
+            source_alpha,
+            destination_alpha,
+        ),
+        |(source_color, destination_color, source_alpha, destination_alpha)| unsafe {
+            gl::BlendFuncSeparate(
+                source_color,
+                destination_color,
+                source_alpha,
+                destination_alpha,
+            )
+        },
+    );
+}
+
+pub fn blend_equation_separate(color: GLenum, alpha: GLenum) {
+    update(
+        |s| &mut s.blend_equation,
+        (color, alpha),
+        |(color, alpha)| unsafe { gl::BlendEquationSeparate(color, alpha) },
+    );
+}
+
+pub fn cull_face(face: GLenum) {
+    update(
+        |s| &mut s.cull_face,
+        face,
+        |face| unsafe { gl::CullFace(face) },
+    );
+}
+
+pub fn polygon_offset(factor: f32, units: f32) {
+    update(
+        |s| &mut s.polygon_offset,
+        (factor, units),
+        |(factor, units)| unsafe { gl::PolygonOffset(factor, units) },
+    );
+}
+
+// Front and back faces
+pub fn polygon_mode(mode: GLenum) {
+    update(
+        |s| &mut s.polygon_mode,
+        mode,
+        |mode| unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, mode) },
+    );
+}
+
+// x, y, width, height
+pub fn scissor(rect: [GLint; 4]) {
+    update(
+        |s| &mut s.scissor,
+        rect,
+        |[x, y, width, height]| unsafe { gl::Scissor(x, y, width, height) },
+    );
+}
+
+pub fn stencil_func(func: GLenum, reference: GLint, mask: GLuint) {
+    update(
+        |s| &mut s.stencil_func,
+        (func, reference, mask),
+        |(func, reference, mask)| unsafe { gl::StencilFunc(func, reference, mask) },
+    );
+}
+
+pub fn stencil_op(stencil_fail: GLenum, depth_fail: GLenum, pass: GLenum) {
+    update(
+        |s| &mut s.stencil_op,
+        (stencil_fail, depth_fail, pass),
+        |(stencil_fail, depth_fail, pass)| unsafe { gl::StencilOp(stencil_fail, depth_fail, pass) },
+    );
+}
+
+pub fn stencil_mask(mask: GLuint) {
+    update(
+        |s| &mut s.stencil_mask,
+        mask,
+        |mask| unsafe { gl::StencilMask(mask) },
+    );
+}
+
+// GL puts the bindings of deleted objects back to 0, the forget_* functions mirror it
+pub fn forget_program(id: GLuint) {
+    // Except the current program, which stays in use until another one is. Unknown so the
+    // next `use_program` goes through, even with a new program that got the same name
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.program == Some(id) {
+            state.program = None;
+        }
+    });
+}
+
+pub fn forget_vertex_array(id: GLuint) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.vertex_array == Some(id) {
+            state.vertex_array = Some(0);
+            state.element_buffer = None;
+        }
+    });
+}
+
+pub fn forget_buffer(id: GLuint) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.array_buffer == Some(id) {
+            state.array_buffer = Some(0);
+        }
+        if state.element_buffer == Some(id) {
+            state.element_buffer = Some(0);
+        }
+    });
+}
+
+pub fn forget_framebuffer(id: GLuint) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if state.framebuffer == Some(id) {
+            state.framebuffer = Some(0);
+        }
+    });
+}
+
+pub fn forget_texture(id: GLuint) {
+    STATE.with(|state| {
+        for binding in state.borrow_mut().textures.iter_mut().flatten() {
+            if *binding == Some(id) {
+                *binding = Some(0);
+            }
+        }
+    });
+}
+
+// Forget everything, to call after GL was used directly so the next calls go through
+pub fn invalidate() {
+    STATE.with(|state| *state.borrow_mut() = GlState::unknown());
+}
+
+// Issue every known value again, to put the engine state back after code that changes
+// GL state behind the cache like the egui painter
+pub fn restore() {
+    let known = STATE.with(|state| std::mem::replace(&mut *state.borrow_mut(), GlState::unknown()));
+    if let Some(id) = known.program {
+        use_program(id);
+    }
+    if let Some(id) = known.vertex_array {
+        bind_vertex_array(id);
+    }
+    if let Some(id) = known.array_buffer {
+        bind_buffer(gl::ARRAY_BUFFER, id);
+    }
+    if let Some(id) = known.element_buffer {
+        bind_buffer(gl::ELEMENT_ARRAY_BUFFER, id);
+    }
+    if let Some(id) = known.framebuffer {
+        bind_framebuffer(id);
+    }
+    for (unit, targets) in known.textures.iter().enumerate() {
+        for (target, id) in [gl::TEXTURE_2D, gl::TEXTURE_CUBE_MAP].iter().zip(targets) {
+            if let Some(id) = id {
+                bind_texture_unit(unit as u32, *target, *id);
+            }
+        }
+    }
+    // The active unit last, the loop above changed it
+    STATE.with(|state| state.borrow_mut().active_unit = None);
+    if let Some(unit) = known.active_unit {
+        active_texture(unit);
+    }
+    for (capability, enabled) in CAPABILITIES.iter().zip(known.capabilities) {
+        if let Some(enabled) = enabled {
+            set_enabled(*capability, enabled);
+        }
+    }
+    if let Some(write) = known.depth_mask {
+        depth_mask(write);
+    }
+    if let Some(func) = known.depth_func {
+        depth_func(func);
+    }
+    if let Some((source_color, destination_color, source_alpha, destination_alpha)) =
+        known.blend_func
+    {
+        blend_func_separate(
+            source_color,
+            destination_color,
+            source_alpha,
+            destination_alpha,
+        );
+    }
+    if let Some((color, alpha)) = known.blend_equation {
+        blend_equation_separate(color, alpha);
+    }
+    if let Some(face) = known.cull_face {
+        cull_face(face);
+    }
+    if let Some((factor, units)) = known.polygon_offset {
+        polygon_offset(factor, units);
+    }
+    if let Some(mode) = known.polygon_mode {
+        polygon_mode(mode);
+    }
+    if let Some(rect) = known.scissor {
+        scissor(rect);
+    }
+    if let Some((func, reference, mask)) = known.stencil_func {
+        stencil_func(func, reference, mask);
+    }
+    if let Some((stencil_fail, depth_fail, pass)) = known.stencil_op {
+        stencil_op(stencil_fail, depth_fail, pass);
+    }
+    if let Some(mask) = known.stencil_mask {
+        stencil_mask(mask);
+    }
+}
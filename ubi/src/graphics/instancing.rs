@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::mem;
+use std::ptr::null;
+use std::rc::Rc;
+
+use crate::core::custom_error::UbiError;
+use crate::core::ecs::transform::GlobalTransform;
+use crate::core::ecs::world::World;
+use crate::core::math::bounds::Frustum;
+use crate::core::math::transform::Mat4;
+
+use super::buffer::{Vbo, VertexLayout};
+use super::culling::{is_visible, CullingStats};
+use super::material::Material;
+use super::mesh::{Mesh, INSTANCE_LOCATION};
+use super::shader::Program;
+
+// Per instance attributes stored in their own vertex buffer
+pub struct InstanceBuffer {
+    vbo: Vbo,
+    layout: VertexLayout,
+    // Bytes reserved on the GPU
+    capacity: usize,
+    count: usize,
+}
+
+impl InstanceBuffer {
+    // The layout is switched to per instance, its locations must not overlap the mesh ones
+    pub fn new(layout: VertexLayout) -> Self {
+        Self {
+            vbo: Vbo::gen(),
+            layout: layout.per_instance(),
+            capacity: 0,
+            count: 0,
+        }
+    }
+
+    // One model matrix per instance, read by the shader at `INSTANCE_LOCATION`..+4
+    pub fn transforms() -> Self {
+        Self::new(VertexLayout::new().with_mat4(INSTANCE_LOCATION))
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    // Number of instances uploaded by the last `set`
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // Replace the instance data, the buffer grows to the next power of two when needed
+    pub fn set(&mut self, data: &[f32]) {
+        let size = mem::size_of_val(data);
+        if size > self.capacity {
+            self.capacity = size.next_power_of_two();
+            self.vbo.allocate(self.capacity);
+        }
+        self.vbo.sub_data(0, data);
+        self.count = data.len() / self.layout.floats_per_vertex().max(1);
+    }
+}
+
+// Draw `count` instances of `mesh` with the program currently in use
+pub fn draw_instanced(mesh: &Mesh, instances: &InstanceBuffer, count: usize) {
+    let count = count.min(instances.len());
+    if count == 0 {
+        return;
+    }
+    let vao = mesh.vao();
+    vao.bind();
+    instances.vbo.bind();
+    vao.set_layout(instances.layout());
+    unsafe {
+        if mesh.index_count() > 0 {
+            gl::DrawElementsInstanced(
+                gl::TRIANGLES,
+                mesh.index_count() as i32,
+                gl::UNSIGNED_INT,
+                null(),
+                count as i32,
+            );
+        } else {
+            gl::DrawArraysInstanced(gl::TRIANGLES, 0, mesh.vertex_count() as i32, count as i32);
+        }
+    }
+    // The mesh may later be drawn without instances
+    vao.disable_layout(instances.layout());
+    vao.unbind();
+}
+
+// Component drawing `mesh` with `material` at the entity's `GlobalTransform`.
+// Entities sharing both are drawn together with one instanced call.
+#[derive(Clone)]
+pub struct MeshRenderer {
+    pub mesh: Rc<Mesh>,
+    pub material: Rc<Material>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeshPassStats {
+    pub instances: u32,
+    pub draw_calls: u32,
+    pub culling: CullingStats,
+}
+
+struct Batch {
+    material: Rc<Material>,
+    mesh: Rc<Mesh>,
+    transforms: Vec<f32>,
+}
+
+// Draws every entity with a `MeshRenderer` and a `GlobalTransform`, instanced by mesh and material.
+// Material programs receive `u_view_projection` and read the model matrix from the instance attributes.
+pub struct MeshPass {
+    instances: InstanceBuffer,
+    stats: MeshPassStats,
+}
+
+impl MeshPass {
+    pub fn new() -> Self {
+        Self {
+            instances: InstanceBuffer::transforms(),
+            stats: MeshPassStats::default(),
+        }
+    }
+
+    // Unlit material for `MeshRenderer`s, tinted by its `u_color` parameter
+    pub fn default_material() -> Result<Material, UbiError> {
+        let program = Program::from_sources(
+            include_str!("../../../assets/shaders/mesh.vert"),
+            include_str!("../../../assets/shaders/mesh.frag"),
+        )?;
+        let mut material = Material::new(Rc::new(program));
+        material.set("u_color", [1.0, 1.0, 1.0, 1.0])?;
+        Ok(material)
+    }
+
+    // Stats of the last `render`
+    pub fn stats(&self) -> MeshPassStats {
+        self.stats
+    }
+
+    pub fn render(&mut self, world: &World, view_projection: &Mat4) {
+        self.stats = MeshPassStats::default();
+        let frustum = Frustum::from_matrix(view_projection);
+
+        let mut batches: Vec<Batch> = Vec::new();
+        let mut lookup: HashMap<(*const Material, *const Mesh), usize> = HashMap::new();
+        for (_, renderer, transform) in world.query2::<MeshRenderer, GlobalTransform>() {
+            let visible = is_visible(&frustum, &renderer.mesh, &transform.0);
+            if !self.stats.culling.record(visible) {
+                continue;
+            }
+            let key = (Rc::as_ptr(&renderer.material), Rc::as_ptr(&renderer.mesh));
+            let index = *lookup.entry(key).or_insert_with(|| {
+                batches.push(Batch {
+                    material: renderer.material.clone(),
+                    mesh: renderer.mesh.clone(),
+                    transforms: Vec::new(),
+                });
+                batches.len() - 1
+            });
+            batches[index]
+                .transforms
+                .extend_from_slice(transform.0.as_array());
+        }
+        // Fewest program and texture switches first, then group the meshes of a material
+        batches.sort_by_key(|batch| (batch.material.sort_key(), Rc::as_ptr(&batch.mesh)));
+
+        let mut current_material: Option<*const Material> = None;
+        let mut current_program: Option<*const Program> = None;
+        for batch in &batches {
+            if current_material != Some(Rc::as_ptr(&batch.material)) {
+                batch.material.apply();
+                current_material = Some(Rc::as_ptr(&batch.material));
+            }
+            let program = batch.material.program();
+            if current_program != Some(Rc::as_ptr(program)) {
+                program.set_mat4("u_view_projection", view_projection);
+                current_program = Some(Rc::as_ptr(program));
+            }
+            self.instances.set(&batch.transforms);
+            draw_instanced(&batch.mesh, &self.instances, self.instances.len());
+            self.stats.instances += self.instances.len() as u32;
+            self.stats.draw_calls += 1;
+        }
+    }
+}
+
+impl Default for MeshPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
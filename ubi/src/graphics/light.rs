@@ -0,0 +1,225 @@
+use crate::core::ecs::transform::GlobalTransform;
+use crate::core::ecs::world::World;
+use crate::core::math::vector::{distance, dot, normalize, sub};
+
+use super::shader::Program;
+
+// Must match MAX_LIGHTS in lit.frag
+pub const MAX_LIGHTS_PER_OBJECT: usize = 8;
+
+// Light coming from infinitely far away, like the sun
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    // Direction the light travels in, world space
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // Rendered with cascaded shadow maps when the `ForwardPass` has shadows enabled
+    pub casts_shadows: bool,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: [0.0, -1.0, 0.0],
+            color: [1.0; 3],
+            intensity: 1.0,
+            casts_shadows: false,
+        }
+    }
+}
+
+// Light shining in all directions from the entity's `GlobalTransform`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // Distance at which the light fades out completely
+    pub range: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            color: [1.0; 3],
+            intensity: 1.0,
+            range: 10.0,
+        }
+    }
+}
+
+// Cone of light from the entity's `GlobalTransform`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotLight {
+    // Direction the cone points at, world space
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    // Half angles in radians, full intensity inside `inner_angle`, none past `outer_angle`
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    // Gets a view in the shadow atlas when the `ForwardPass` has shadows enabled
+    pub casts_shadows: bool,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            direction: [0.0, -1.0, 0.0],
+            color: [1.0; 3],
+            intensity: 1.0,
+            range: 10.0,
+            inner_angle: 0.3,
+            outer_angle: 0.5,
+            casts_shadows: false,
+        }
+    }
+}
+
+// Values of u_light_type in lit.frag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightKind {
+    Directional = 0,
+    Point = 1,
+    Spot = 2,
+}
+
+// A light component resolved to world space, as the shaders receive it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GatheredLight {
+    pub kind: LightKind,
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    // Color multiplied by intensity
+    pub radiance: [f32; 3],
+    pub range: f32,
+    // Cosines of the inner and outer angles
+    pub cone: [f32; 2],
+    pub casts_shadows: bool,
+    // First view of the light in the shadow atlas, -1 when it has none
+    pub shadow: i32,
+}
+
+// Windowed inverse square falloff, reaches 0 at `range`. Same formula as lit.frag.
+pub fn attenuation(distance: f32, range: f32) -> f32 {
+    if range <= 0.0 {
+        return 0.0;
+    }
+    let window = (1.0 - (distance / range).powi(4)).clamp(0.0, 1.0);
+    window * window / (distance * distance + 1.0)
+}
+
+// Every light of the world, lights without a `GlobalTransform` sit at the origin
+pub fn gather_lights(world: &World) -> Vec<GatheredLight> {
+    let position = |entity| {
+        world
+            .get::<GlobalTransform>(entity)
+            .map(|transform| transform.0.translation())
+            .unwrap_or([0.0; 3])
+    };
+
+    let mut lights = Vec::new();
+    for (_, light) in world.query::<DirectionalLight>() {
+        lights.push(GatheredLight {
+            kind: LightKind::Directional,
+            position: [0.0; 3],
+            direction: normalize(light.direction),
+            radiance: light.color.map(|c| c * light.intensity),
+            range: 0.0,
+            cone: [0.0; 2],
+            casts_shadows: light.casts_shadows,
+            shadow: -1,
+        });
+    }
+    for (entity, light) in world.query::<PointLight>() {
+        lights.push(GatheredLight {
+            kind: LightKind::Point,
+            position: position(entity),
+            direction: [0.0; 3],
+            radiance: light.color.map(|c| c * light.intensity),
+            range: light.range,
+            cone: [0.0; 2],
+            casts_shadows: false,
+            shadow: -1,
+        });
+    }
+    for (entity, light) in world.query::<SpotLight>() {
+        // smoothstep in lit.frag needs the outer cone strictly wider
+        let outer = light.outer_angle.max(light.inner_angle + 1e-3);
+        lights.push(GatheredLight {
+            kind: LightKind::Spot,
+            position: position(entity),
+            direction: normalize(light.direction),
+            radiance: light.color.map(|c| c * light.intensity),
+            range: light.range,
+            cone: [light.inner_angle.cos(), outer.cos()],
+            casts_shadows: light.casts_shadows,
+            shadow: -1,
+        });
+    }
+    lights
+}
+
+// How much `light` can contribute at `point`, 0 when it can not reach it
+fn relevance(light: &GatheredLight, point: [f32; 3]) -> f32 {
+    let brightness =
+        light.radiance[0] * 0.2126 + light.radiance[1] * 0.7152 + light.radiance[2] * 0.0722;
+    match light.kind {
+        LightKind::Directional => f32::INFINITY,
+        LightKind::Point => brightness * attenuation(distance(point, light.position), light.range),
+        LightKind::Spot => {
+            let to_point = normalize(sub(point, light.position));
+            if dot(to_point, light.direction) < light.cone[1] {
+                return 0.0;
+            }
+            brightness * attenuation(distance(point, light.position), light.range)
+        }
+    }
+}
+
+// Indices of the `max` lights that matter most at `point`, sorted by index.
+// Directional lights are always picked first.
+pub fn select_lights(lights: &[GatheredLight], point: [f32; 3], max: usize) -> Vec<usize> {
+    let mut scored: Vec<(usize, f32)> = lights
+        .iter()
+        .enumerate()
+        .map(|(index, light)| (index, relevance(light, point)))
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut selected: Vec<usize> = scored
+        .into_iter()
+        .take(max)
+        .map(|(index, _)| index)
+        .collect();
+    selected.sort_unstable();
+    selected
+}
+
+// Upload `selected` lights to the u_light_* uniforms of a program in use
+pub fn upload_lights(program: &Program, lights: &[GatheredLight], selected: &[usize]) {
+    let selected: Vec<&GatheredLight> = selected
+        .iter()
+        .take(MAX_LIGHTS_PER_OBJECT)
+        .map(|&index| &lights[index])
+        .collect();
+    program.set_int("u_light_count", selected.len() as i32);
+    if selected.is_empty() {
+        return;
+    }
+    let kinds: Vec<i32> = selected.iter().map(|light| light.kind as i32).collect();
+    let positions: Vec<[f32; 3]> = selected.iter().map(|light| light.position).collect();
+    let directions: Vec<[f32; 3]> = selected.iter().map(|light| light.direction).collect();
+    let radiances: Vec<[f32; 3]> = selected.iter().map(|light| light.radiance).collect();
+    let ranges: Vec<f32> = selected.iter().map(|light| light.range).collect();
+    let cones: Vec<[f32; 2]> = selected.iter().map(|light| light.cone).collect();
+    let shadows: Vec<i32> = selected.iter().map(|light| light.shadow).collect();
+    program.set_int_array("u_light_type", &kinds);
+    program.set_vec3_array("u_light_position", &positions);
+    program.set_vec3_array("u_light_direction", &directions);
+    program.set_vec3_array("u_light_color", &radiances);
+    program.set_float_array("u_light_range", &ranges);
+    program.set_vec2_array("u_light_cone", &cones);
+    program.set_int_array("u_light_shadow", &shadows);
+}
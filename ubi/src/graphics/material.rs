@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use gl::types::{GLenum, GLint};
+
+use crate::core::custom_error::UbiError;
+use crate::core::math::transform::Mat4;
+
+use super::pipeline::PipelineState;
+use super::shader::{Program, UniformInfo};
+use super::texture::Texture;
+
+static NEXT_MATERIAL_ID: AtomicU32 = AtomicU32::new(1);
+
+// A uniform value stored by a material
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterialParam {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat4(Mat4),
+}
+
+impl MaterialParam {
+    // GL uniform types the value can be written to
+    fn accepts(&self, kind: GLenum) -> bool {
+        match self {
+            MaterialParam::Int(_) => kind == gl::INT || kind == gl::BOOL,
+            MaterialParam::Float(_) => kind == gl::FLOAT,
+            MaterialParam::Vec2(_) => kind == gl::FLOAT_VEC2,
+            MaterialParam::Vec3(_) => kind == gl::FLOAT_VEC3,
+            MaterialParam::Vec4(_) => kind == gl::FLOAT_VEC4,
+            MaterialParam::Mat4(_) => kind == gl::FLOAT_MAT4,
+        }
+    }
+
+    // Expects the owning program to be in use
+    fn upload(&self, location: GLint) {
+        unsafe {
+            match self {
+                MaterialParam::Int(v) => gl::Uniform1i(location, *v),
+                MaterialParam::Float(v) => gl::Uniform1f(location, *v),
+                MaterialParam::Vec2(v) => gl::Uniform2f(location, v[0], v[1]),
+                MaterialParam::Vec3(v) => gl::Uniform3f(location, v[0], v[1], v[2]),
+                MaterialParam::Vec4(v) => gl::Uniform4f(location, v[0], v[1], v[2], v[3]),
+                // Mat4 is stored row major so GL has to transpose it
+                MaterialParam::Mat4(v) => gl::UniformMatrix4fv(location, 1, gl::TRUE, v.ptr()),
+            }
+        }
+    }
+}
+
+impl From<i32> for MaterialParam {
+    fn from(value: i32) -> Self {
+        MaterialParam::Int(value)
+    }
+}
+
+impl From<bool> for MaterialParam {
+    fn from(value: bool) -> Self {
+        MaterialParam::Int(value as i32)
+    }
+}
+
+impl From<f32> for MaterialParam {
+    fn from(value: f32) -> Self {
+        MaterialParam::Float(value)
+    }
+}
+
+impl From<[f32; 2]> for MaterialParam {
+    fn from(value: [f32; 2]) -> Self {
+        MaterialParam::Vec2(value)
+    }
+}
+
+impl From<[f32; 3]> for MaterialParam {
+    fn from(value: [f32; 3]) -> Self {
+        MaterialParam::Vec3(value)
+    }
+}
+
+impl From<[f32; 4]> for MaterialParam {
+    fn from(value: [f32; 4]) -> Self {
+        MaterialParam::Vec4(value)
+    }
+}
+
+impl From<Mat4> for MaterialParam {
+    fn from(value: Mat4) -> Self {
+        MaterialParam::Mat4(value)
+    }
+}
+
+fn gl_type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::INT => "int",
+        gl::BOOL => "bool",
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        _ => "unsupported type",
+    }
+}
+
+fn param_type_name(param: &MaterialParam) -> &'static str {
+    match param {
+        MaterialParam::Int(_) => "int",
+        MaterialParam::Float(_) => "float",
+        MaterialParam::Vec2(_) => "vec2",
+        MaterialParam::Vec3(_) => "vec3",
+        MaterialParam::Vec4(_) => "vec4",
+        MaterialParam::Mat4(_) => "mat4",
+    }
+}
+
+// What a program exposes to materials, shared by a material and its instances
+struct ProgramInterface {
+    uniforms: HashMap<String, UniformInfo>,
+    // Samplers get consecutive texture units in name order
+    texture_units: BTreeMap<String, u32>,
+}
+
+// A program with the uniform values and textures to draw with it.
+// Values are checked against the active uniforms of the program when they are set.
+pub struct Material {
+    id: u32,
+    program: Rc<Program>,
+    interface: Rc<ProgramInterface>,
+    parent: Option<Rc<Material>>,
+    params: BTreeMap<String, MaterialParam>,
+    textures: BTreeMap<String, Rc<Texture>>,
+    // None inherits from the parent
+    transparent: Option<bool>,
+    pipeline: Option<PipelineState>,
+}
+
+impl Material {
+    pub fn new(program: Rc<Program>) -> Self {
+        let uniforms: HashMap<String, UniformInfo> = program
+            .active_uniforms()
+            .into_iter()
+            .map(|uniform| (uniform.name.clone(), uniform))
+            .collect();
+        let mut samplers: Vec<&UniformInfo> = uniforms
+            .values()
+            .filter(|uniform| uniform.kind == gl::SAMPLER_2D)
+            .collect();
+        samplers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Sampler units never change, write them once
+        program.set();
+        let texture_units = samplers
+            .iter()
+            .enumerate()
+            .map(|(unit, sampler)| {
+                unsafe { gl::Uniform1i(sampler.location, unit as GLint) };
+                (sampler.name.clone(), unit as u32)
+            })
+            .collect();
+
+        Self {
+            id: NEXT_MATERIAL_ID.fetch_add(1, Ordering::Relaxed),
+            program,
+            interface: Rc::new(ProgramInterface {
+                uniforms,
+                texture_units,
+            }),
+            parent: None,
+            params: BTreeMap::new(),
+            textures: BTreeMap::new(),
+            transparent: None,
+            pipeline: None,
+        }
+    }
+
+    // A material that reads every value from `parent` unless it overrides it
+    pub fn instance(parent: &Rc<Material>) -> Self {
+        Self {
+            id: NEXT_MATERIAL_ID.fetch_add(1, Ordering::Relaxed),
+            program: parent.program.clone(),
+            interface: parent.interface.clone(),
+            parent: Some(parent.clone()),
+            params: BTreeMap::new(),
+            textures: BTreeMap::new(),
+            transparent: None,
+            pipeline: None,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn program(&self) -> &Rc<Program> {
+        &self.program
+    }
+
+    pub fn parent(&self) -> Option<&Rc<Material>> {
+        self.parent.as_ref()
+    }
+
+    // Active uniforms of the program, samplers included
+    pub fn uniforms(&self) -> impl Iterator<Item = &UniformInfo> {
+        self.interface.uniforms.values()
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<MaterialParam>) -> Result<(), UbiError> {
+        let value = value.into();
+        let uniform = self.uniform(name)?;
+        if !value.accepts(uniform.kind) {
+            return Err(UbiError::ShaderError(format!(
+                "material: uniform \"{}\" is a {}, got a {}",
+                name,
+                gl_type_name(uniform.kind),
+                param_type_name(&value)
+            )));
+        }
+        self.params.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn set_texture(&mut self, name: &str, texture: Rc<Texture>) -> Result<(), UbiError> {
+        let uniform = self.uniform(name)?;
+        if uniform.kind != gl::SAMPLER_2D {
+            return Err(UbiError::ShaderError(format!(
+                "material: uniform \"{}\" is a {}, not a sampler2D",
+                name,
+                gl_type_name(uniform.kind)
+            )));
+        }
+        self.textures.insert(name.to_string(), texture);
+        Ok(())
+    }
+
+    // Drop an override so the value comes from the parent again
+    pub fn reset(&mut self, name: &str) {
+        self.params.remove(name);
+        self.textures.remove(name);
+    }
+
+    // Value set on this material or inherited from its parents
+    pub fn param(&self, name: &str) -> Option<&MaterialParam> {
+        self.params
+            .get(name)
+            .or_else(|| self.parent.as_ref()?.param(name))
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Rc<Texture>> {
+        self.textures
+            .get(name)
+            .or_else(|| self.parent.as_ref()?.texture(name))
+    }
+
+    // Transparent materials are blended over the opaque ones, back to front
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = Some(transparent);
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.transparent
+            .or_else(|| Some(self.parent.as_ref()?.is_transparent()))
+            .unwrap_or(false)
+    }
+
+    // Blending, depth and culling to draw with, inherited from the parent
+    pub fn set_pipeline(&mut self, pipeline: PipelineState) {
+        self.pipeline = Some(pipeline);
+    }
+
+    // `PipelineState::TRANSPARENT` or `OPAQUE` following `is_transparent` when none is set
+    pub fn pipeline(&self) -> PipelineState {
+        self.explicit_pipeline()
+            .unwrap_or(if self.is_transparent() {
+                PipelineState::TRANSPARENT
+            } else {
+                PipelineState::OPAQUE
+            })
+    }
+
+    fn explicit_pipeline(&self) -> Option<PipelineState> {
+        self.pipeline
+            .or_else(|| self.parent.as_ref()?.explicit_pipeline())
+    }
+
+    // Apply the pipeline, use the program, upload the values and bind the textures to
+    // their units
+    pub fn apply(&self) {
+        self.pipeline().apply();
+        self.program.set();
+        self.upload_params(self);
+        for (name, unit) in &self.interface.texture_units {
+            if let Some(texture) = self.texture(name) {
+                texture.bind_unit(*unit);
+            }
+        }
+    }
+
+    // Orders draws so the program changes least often, then the first texture, then the values.
+    // program (16 bits) | texture of the first unit (24 bits) | material (24 bits)
+    pub fn sort_key(&self) -> u64 {
+        let texture = self
+            .interface
+            .texture_units
+            .keys()
+            .next()
+            .and_then(|name| self.texture(name))
+            .map(|texture| texture.id)
+            .unwrap_or(0);
+        ((self.program.id() as u64 & 0xffff) << 48)
+            | ((texture as u64 & 0xff_ffff) << 24)
+            | (self.id as u64 & 0xff_ffff)
+    }
+
+    fn uniform(&self, name: &str) -> Result<&UniformInfo, UbiError> {
+        self.interface.uniforms.get(name).ok_or_else(|| {
+            UbiError::ShaderError(format!(
+                "material: uniform \"{}\" is not active in program {}",
+                name,
+                self.program.id()
+            ))
+        })
+    }
+
+    // Values of `self` and its parents not overridden closer to `material`, parents first.
+    // Walks the chain on each apply instead of merging the params into a new map.
+    fn upload_params(&self, material: &Material) {
+        if let Some(parent) = &self.parent {
+            parent.upload_params(material);
+        }
+        for (name, param) in &self.params {
+            if !material.overrides(self, name) {
+                param.upload(material.interface.uniforms[name].location);
+            }
+        }
+    }
+
+    // Whether `self` or a parent below `ancestor` sets `name`
+    fn overrides(&self, ancestor: &Material, name: &str) -> bool {
+        let mut material = self;
+        while !std::ptr::eq(material, ancestor) {
+            if material.params.contains_key(name) {
+                return true;
+            }
+            match &material.parent {
+                Some(parent) => material = parent,
+                None => return false,
+            }
+        }
+        false
+    }
+}
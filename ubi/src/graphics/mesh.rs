@@ -0,0 +1,191 @@
+use std::f32::consts::PI;
+use std::ptr::null;
+
+use crate::core::math::bounds::{Aabb, BoundingSphere};
+
+use super::buffer::{Ibo, Vao, Vbo, VertexLayout};
+
+// Attribute locations of `Mesh::standard_layout`, shaders declare the same ones
+pub const POSITION_LOCATION: u32 = 0;
+pub const NORMAL_LOCATION: u32 = 1;
+pub const UV_LOCATION: u32 = 2;
+pub const TANGENT_LOCATION: u32 = 3;
+// First location left free for per instance attributes
+pub const INSTANCE_LOCATION: u32 = 4;
+
+// Geometry uploaded to the GPU, drawn as indexed triangles
+pub struct Mesh {
+    vao: Vao,
+    _vbo: Vbo,
+    _ibo: Ibo,
+    layout: VertexLayout,
+    vertex_count: usize,
+    index_count: usize,
+    // Local space, None without a position attribute
+    bounds: Option<(Aabb, BoundingSphere)>,
+}
+
+impl Mesh {
+    // position(3) normal(3) uv(2) tangent(4), the tangent w is the bitangent sign
+    pub fn standard_layout() -> VertexLayout {
+        VertexLayout::new()
+            .with(POSITION_LOCATION, 3)
+            .with(NORMAL_LOCATION, 3)
+            .with(UV_LOCATION, 2)
+            .with(TANGENT_LOCATION, 4)
+    }
+
+    // Interleaved `vertices` described by `layout`, an empty `indices` draws the vertices in order
+    pub fn new(vertices: &[f32], indices: &[u32], layout: VertexLayout) -> Self {
+        let vao = Vao::gen();
+        let vbo = Vbo::gen();
+        let ibo = Ibo::gen();
+        vao.bind();
+        vbo.set(vertices);
+        vao.set_layout(&layout);
+        if !indices.is_empty() {
+            ibo.set(indices);
+        }
+        vao.unbind();
+
+        let vertex_count = vertices.len() / layout.floats_per_vertex().max(1);
+        let bounds = Self::compute_bounds(vertices, &layout);
+        Self {
+            vao,
+            _vbo: vbo,
+            _ibo: ibo,
+            layout,
+            vertex_count,
+            index_count: indices.len(),
+            bounds,
+        }
+    }
+
+    // Positions are the 3 first floats of the attribute at POSITION_LOCATION
+    fn compute_bounds(vertices: &[f32], layout: &VertexLayout) -> Option<(Aabb, BoundingSphere)> {
+        let attribute = layout.attributes().iter().find(|attribute| {
+            attribute.location == POSITION_LOCATION && attribute.components >= 3
+        })?;
+        let offset = attribute.offset / std::mem::size_of::<f32>();
+        let positions = vertices
+            .chunks_exact(layout.floats_per_vertex().max(1))
+            .map(move |vertex| [vertex[offset], vertex[offset + 1], vertex[offset + 2]]);
+        Some((
+            Aabb::from_points(positions.clone())?,
+            BoundingSphere::from_points(positions)?,
+        ))
+    }
+
+    // Unit cube centered on the origin with the standard layout
+    pub fn cube() -> Self {
+        // normal, tangent and bitangent of each face
+        let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ];
+        let mut vertices = Vec::with_capacity(6 * 4 * 12);
+        let mut indices = Vec::with_capacity(6 * 6);
+        for (face, (n, t, b)) in faces.iter().enumerate() {
+            for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                let (su, sv) = (u - 0.5, v - 0.5);
+                for axis in 0..3 {
+                    vertices.push(n[axis] * 0.5 + t[axis] * su + b[axis] * sv);
+                }
+                vertices.extend_from_slice(n);
+                vertices.extend_from_slice(&[u, v]);
+                vertices.extend_from_slice(t);
+                vertices.push(1.0);
+            }
+            let base = face as u32 * 4;
+            indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+        }
+        Self::new(&vertices, &indices, Self::standard_layout())
+    }
+
+    // Square of side `size` in the xz plane facing +y, with the standard layout
+    pub fn plane(size: f32) -> Self {
+        let h = size * 0.5;
+        #[rustfmt::skip]
+        let vertices = [
+            -h, 0.0,  h,  0.0, 1.0, 0.0,  0.0, 0.0,  1.0, 0.0, 0.0, 1.0,
+             h, 0.0,  h,  0.0, 1.0, 0.0,  1.0, 0.0,  1.0, 0.0, 0.0, 1.0,
+             h, 0.0, -h,  0.0, 1.0, 0.0,  1.0, 1.0,  1.0, 0.0, 0.0, 1.0,
+            -h, 0.0, -h,  0.0, 1.0, 0.0,  0.0, 1.0,  1.0, 0.0, 0.0, 1.0,
+        ];
+        Self::new(&vertices, &[0, 1, 2, 2, 3, 0], Self::standard_layout())
+    }
+
+    // Sphere of diameter 1 centered on the origin, with the standard layout
+    pub fn sphere(segments: u32, rings: u32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        let mut vertices = Vec::with_capacity(((segments + 1) * (rings + 1) * 12) as usize);
+        for ring in 0..=rings {
+            let v = ring as f32 / rings as f32;
+            let (sin_theta, cos_theta) = (v * PI).sin_cos();
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let (sin_phi, cos_phi) = (u * 2.0 * PI).sin_cos();
+                let normal = [sin_theta * cos_phi, cos_theta, -sin_theta * sin_phi];
+                vertices.extend(normal.iter().map(|n| n * 0.5));
+                vertices.extend_from_slice(&normal);
+                vertices.extend_from_slice(&[u, 1.0 - v]);
+                vertices.extend_from_slice(&[-sin_phi, 0.0, -cos_phi, 1.0]);
+            }
+        }
+        let mut indices = Vec::with_capacity((segments * rings * 6) as usize);
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let a = ring * (segments + 1) + segment;
+                let b = a + segments + 1;
+                indices.extend_from_slice(&[a, b, a + 1, a + 1, b, b + 1]);
+            }
+        }
+        Self::new(&vertices, &indices, Self::standard_layout())
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds.map(|(aabb, _)| aabb)
+    }
+
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.bounds.map(|(_, sphere)| sphere)
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    pub fn vao(&self) -> &Vao {
+        &self.vao
+    }
+
+    // Draw once with the program currently in use
+    pub fn draw(&self) {
+        self.vao.bind();
+        unsafe {
+            if self.index_count > 0 {
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    self.index_count as i32,
+                    gl::UNSIGNED_INT,
+                    null(),
+                );
+            } else {
+                gl::DrawArrays(gl::TRIANGLES, 0, self.vertex_count as i32);
+            }
+        }
+        self.vao.unbind();
+    }
+}
@@ -1,4 +1,30 @@
 pub mod shader;
 pub mod texture;
 pub mod buffer;
-pub mod render;
\ No newline at end of file
+pub mod render;
+pub mod sprite_batch;
+pub mod texture_atlas;
+pub mod sprite_sheet;
+pub mod sprite_animation;
+pub mod font;
+pub mod text;
+pub mod tilemap;
+pub mod tiled;
+pub mod tilemap_renderer;
+pub mod mesh;
+pub mod instancing;
+pub mod material;
+pub mod camera;
+pub mod light;
+pub mod forward;
+pub mod obj;
+pub mod framebuffer;
+pub mod cubemap;
+pub mod environment;
+pub mod shadow;
+pub mod deferred;
+pub mod render_queue;
+pub mod render_graph;
+pub mod gl_state;
+pub mod pipeline;
+pub mod culling;
\ No newline at end of file
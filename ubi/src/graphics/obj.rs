@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::core::custom_error::UbiError;
+use crate::core::math::vector::{add, cross, dot, normalize, scale, sub};
+use crate::ubiwarn;
+
+use super::instancing::MeshRenderer;
+use super::material::Material;
+use super::mesh::Mesh;
+use super::texture::Texture;
+
+// Floats per vertex of `Mesh::standard_layout`
+const VERTEX_FLOATS: usize = 12;
+
+// A material of a .mtl file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    // 1 is opaque
+    pub opacity: f32,
+    // Resolved against the .mtl file
+    pub diffuse_map: Option<PathBuf>,
+    pub specular_map: Option<PathBuf>,
+    pub normal_map: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse: [1.0; 3],
+            specular: [0.5; 3],
+            shininess: 32.0,
+            opacity: 1.0,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+        }
+    }
+}
+
+// The faces of a model sharing one material, in `Mesh::standard_layout`
+#[derive(Debug, Clone, Default)]
+pub struct ObjMesh {
+    pub name: String,
+    // Index into `ObjModel::materials`
+    pub material: Option<usize>,
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl ObjMesh {
+    pub fn upload(&self) -> Mesh {
+        Mesh::new(&self.vertices, &self.indices, Mesh::standard_layout())
+    }
+}
+
+// A Wavefront .obj model with the materials of its .mtl libraries
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl ObjModel {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, UbiError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| UbiError::ResourceError(format!("{}: {}", path.display(), e)))?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&source, base_dir).map_err(|e| match e {
+            UbiError::ResourceError(message) => {
+                UbiError::ResourceError(format!("{}: {}", path.display(), message))
+            }
+            other => other,
+        })
+    }
+
+    // `base_dir` resolves the material libraries and their textures
+    pub fn parse(source: &str, base_dir: &Path) -> Result<Self, UbiError> {
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut materials: Vec<ObjMaterial> = Vec::new();
+        let mut builders: Vec<MeshBuilder> = Vec::new();
+        let mut name = String::new();
+        let mut material: Option<usize> = None;
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let error = |message: &str| {
+                UbiError::ResourceError(format!("obj: line {}: {}", number + 1, message))
+            };
+            match keyword {
+                "v" => positions.push(parse_floats(tokens).map_err(|e| error(&e))?),
+                "vn" => normals.push(parse_floats(tokens).map_err(|e| error(&e))?),
+                "vt" => {
+                    let [u, v] = parse_floats(tokens.take(2)).map_err(|e| error(&e))?;
+                    uvs.push([u, v]);
+                }
+                "o" | "g" => name = tokens.collect::<Vec<_>>().join(" "),
+                "usemtl" => {
+                    let wanted = tokens.next().unwrap_or("");
+                    material = materials.iter().position(|m| m.name == wanted);
+                    if material.is_none() {
+                        ubiwarn!("obj: unknown material \"{}\"", wanted);
+                    }
+                }
+                "mtllib" => {
+                    for library in tokens {
+                        let path = base_dir.join(library);
+                        match fs::read_to_string(&path) {
+                            Ok(mtl) => {
+                                materials.extend(parse_mtl(&mtl, path.parent().unwrap_or(base_dir)))
+                            }
+                            Err(e) => {
+                                ubiwarn!("obj: can not read {}: {}", path.display(), e);
+                            }
+                        }
+                    }
+                }
+                "f" => {
+                    let corners = tokens
+                        .map(|corner| {
+                            parse_corner(corner, positions.len(), uvs.len(), normals.len())
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| error(&e))?;
+                    if corners.len() < 3 {
+                        return Err(error("a face needs at least 3 vertices"));
+                    }
+                    let builder = match builders
+                        .iter_mut()
+                        .position(|b| b.material == material && b.name == name)
+                    {
+                        Some(index) => &mut builders[index],
+                        None => {
+                            builders.push(MeshBuilder::new(&name, material));
+                            builders.last_mut().unwrap()
+                        }
+                    };
+                    // Triangle fan, fine for the convex polygons exporters write
+                    for i in 1..corners.len() - 1 {
+                        builder.triangle(
+                            [corners[0], corners[i], corners[i + 1]],
+                            &positions,
+                            &uvs,
+                            &normals,
+                        );
+                    }
+                }
+                // Smoothing groups, lines and points are not drawn
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            meshes: builders.into_iter().map(MeshBuilder::finish).collect(),
+            materials,
+        })
+    }
+
+    // One `MeshRenderer` per mesh, with instances of `base` carrying the .mtl values.
+    // `base` is expected to be `ForwardPass::default_material` or use the same uniforms.
+    pub fn instantiate(&self, base: &Rc<Material>) -> Result<Vec<MeshRenderer>, UbiError> {
+        let mut textures: HashMap<PathBuf, Rc<Texture>> = HashMap::new();
+        let mut load = |path: &PathBuf| -> Result<Rc<Texture>, UbiError> {
+            if let Some(texture) = textures.get(path) {
+                return Ok(texture.clone());
+            }
+            let texture = Rc::new(Texture::from_file(&path.to_string_lossy())?);
+            textures.insert(path.clone(), texture.clone());
+            Ok(texture)
+        };
+
+        let mut materials = Vec::with_capacity(self.materials.len());
+        for obj in &self.materials {
+            let mut material = Material::instance(base);
+            let [r, g, b] = obj.diffuse;
+            material.set("u_diffuse_color", [r, g, b, obj.opacity])?;
+            material.set("u_specular_color", obj.specular)?;
+            material.set("u_shininess", obj.shininess.max(1.0))?;
+            // MTL `d` and `Tr`, blended and sorted with the other transparent draws
+            if obj.opacity < 1.0 {
+                material.set_transparent(true);
+            }
+            if let Some(path) = &obj.diffuse_map {
+                material.set_texture("u_diffuse_map", load(path)?)?;
+            }
+            if let Some(path) = &obj.specular_map {
+                material.set_texture("u_specular_map", load(path)?)?;
+            }
+            if let Some(path) = &obj.normal_map {
+                material.set_texture("u_normal_map", load(path)?)?;
+            }
+            materials.push(Rc::new(material));
+        }
+
+        Ok(self
+            .meshes
+            .iter()
+            .map(|mesh| MeshRenderer {
+                mesh: Rc::new(mesh.upload()),
+                material: mesh
+                    .material
+                    .map(|index| materials[index].clone())
+                    .unwrap_or_else(|| base.clone()),
+            })
+            .collect())
+    }
+}
+
+fn parse_floats<'a, const N: usize>(
+    tokens: impl Iterator<Item = &'a str>,
+) -> Result<[f32; N], String> {
+    let mut values = [0.0; N];
+    let mut count = 0;
+    for token in tokens.take(N) {
+        values[count] = token
+            .parse()
+            .map_err(|_| format!("\"{}\" is not a number", token))?;
+        count += 1;
+    }
+    if count < N {
+        return Err(format!("expected {} numbers, got {}", N, count));
+    }
+    Ok(values)
+}
+
+// 1 based and negative (relative) indices to 0 based ones
+fn resolve_index(token: &str, count: usize) -> Result<Option<usize>, String> {
+    if token.is_empty() {
+        return Ok(None);
+    }
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("bad index \"{}\"", token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} out of range", index));
+    }
+    Ok(Some(resolved as usize))
+}
+
+// position/uv/normal indices of a "v", "v/vt", "v//vn" or "v/vt/vn" corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+fn parse_corner(
+    token: &str,
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+) -> Result<Corner, String> {
+    let mut parts = token.split('/');
+    let position = resolve_index(parts.next().unwrap_or(""), positions)?
+        .ok_or_else(|| format!("face corner \"{}\" has no position", token))?;
+    let uv = resolve_index(parts.next().unwrap_or(""), uvs)?;
+    let normal = resolve_index(parts.next().unwrap_or(""), normals)?;
+    Ok((position, uv, normal))
+}
+
+fn parse_mtl(source: &str, base_dir: &Path) -> Vec<ObjMaterial> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+    for line in source.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(tokens.next().unwrap_or("")));
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+        let rest: Vec<&str> = tokens.collect();
+        // Map options like "-bm 1.0" come before the file name
+        let map = || rest.last().map(|file| base_dir.join(file));
+        match keyword {
+            "Kd" => {
+                material.diffuse = parse_floats(rest.iter().copied()).unwrap_or(material.diffuse)
+            }
+            "Ks" => {
+                material.specular = parse_floats(rest.iter().copied()).unwrap_or(material.specular)
+            }
+            "Ns" => {
+                material.shininess = parse_floats::<1>(rest.iter().copied())
+                    .map(|[v]| v)
+                    .unwrap_or(material.shininess)
+            }
+            "d" => {
+                material.opacity = parse_floats::<1>(rest.iter().copied())
+                    .map(|[v]| v)
+                    .unwrap_or(material.opacity)
+            }
+            "Tr" => {
+                material.opacity = parse_floats::<1>(rest.iter().copied())
+                    .map(|[v]| 1.0 - v)
+                    .unwrap_or(material.opacity)
+            }
+            "map_Kd" => material.diffuse_map = map(),
+            "map_Ks" => material.specular_map = map(),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = map(),
+            _ => {}
+        }
+    }
+    materials
+}
+
+struct MeshBuilder {
+    name: String,
+    material: Option<usize>,
+    vertices: Vec<f32>,
+    indices: Vec<u32>,
+    lookup: HashMap<Corner, u32>,
+    // Vertices whose normal comes from their faces
+    missing_normals: Vec<bool>,
+}
+
+impl MeshBuilder {
+    fn new(name: &str, material: Option<usize>) -> Self {
+        Self {
+            name: name.to_string(),
+            material,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            lookup: HashMap::new(),
+            missing_normals: Vec::new(),
+        }
+    }
+
+    fn triangle(
+        &mut self,
+        corners: [Corner; 3],
+        positions: &[[f32; 3]],
+        uvs: &[[f32; 2]],
+        normals: &[[f32; 3]],
+    ) {
+        for corner in corners {
+            let index = match self.lookup.get(&corner) {
+                Some(index) => *index,
+                None => {
+                    let (position, uv, normal) = corner;
+                    let index = (self.vertices.len() / VERTEX_FLOATS) as u32;
+                    self.vertices.extend_from_slice(&positions[position]);
+                    self.vertices
+                        .extend_from_slice(&normal.map(|n| normals[n]).unwrap_or([0.0; 3]));
+                    self.vertices
+                        .extend_from_slice(&uv.map(|t| uvs[t]).unwrap_or([0.0; 2]));
+                    self.vertices.extend_from_slice(&[0.0; 4]);
+                    self.missing_normals.push(normal.is_none());
+                    self.lookup.insert(corner, index);
+                    index
+                }
+            };
+            self.indices.push(index);
+        }
+    }
+
+    fn finish(mut self) -> ObjMesh {
+        let vertex = |vertices: &[f32], index: u32, offset: usize| {
+            let start = index as usize * VERTEX_FLOATS + offset;
+            [vertices[start], vertices[start + 1], vertices[start + 2]]
+        };
+        let count = self.vertices.len() / VERTEX_FLOATS;
+        let mut face_normals = vec![[0.0f32; 3]; count];
+        let mut tangents = vec![[0.0f32; 3]; count];
+        let mut bitangents = vec![[0.0f32; 3]; count];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let p: Vec<[f32; 3]> = triangle
+                .iter()
+                .map(|&i| vertex(&self.vertices, i, 0))
+                .collect();
+            let t: Vec<[f32; 2]> = triangle
+                .iter()
+                .map(|&i| {
+                    let start = i as usize * VERTEX_FLOATS + 6;
+                    [self.vertices[start], self.vertices[start + 1]]
+                })
+                .collect();
+            let (e1, e2) = (sub(p[1], p[0]), sub(p[2], p[0]));
+            // Area weighted
+            let normal = cross(e1, e2);
+            let (du1, dv1) = (t[1][0] - t[0][0], t[1][1] - t[0][1]);
+            let (du2, dv2) = (t[2][0] - t[0][0], t[2][1] - t[0][1]);
+            let det = du1 * dv2 - du2 * dv1;
+            let (tangent, bitangent) = if det.abs() > f32::EPSILON {
+                let r = 1.0 / det;
+                (
+                    scale(sub(scale(e1, dv2), scale(e2, dv1)), r),
+                    scale(sub(scale(e2, du1), scale(e1, du2)), r),
+                )
+            } else {
+                ([0.0; 3], [0.0; 3])
+            };
+            for &i in triangle {
+                let i = i as usize;
+                face_normals[i] = add(face_normals[i], normal);
+                tangents[i] = add(tangents[i], tangent);
+                bitangents[i] = add(bitangents[i], bitangent);
+            }
+        }
+
+        for i in 0..count {
+            let start = i * VERTEX_FLOATS;
+            if self.missing_normals[i] {
+                let normal = normalize(face_normals[i]);
+                self.vertices[start + 3..start + 6].copy_from_slice(&normal);
+            }
+            let normal = normalize(vertex(&self.vertices, i as u32, 3));
+            // Gram-Schmidt, the w sign tells if the uv space is mirrored
+            let tangent = normalize(sub(tangents[i], scale(normal, dot(normal, tangents[i]))));
+            let handedness = if dot(cross(normal, tangent), bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            self.vertices[start + 8..start + 11].copy_from_slice(&tangent);
+            self.vertices[start + 11] = handedness;
+        }
+
+        ObjMesh {
+            name: self.name,
+            material: self.material,
+            vertices: self.vertices,
+            indices: self.indices,
+        }
+    }
+}
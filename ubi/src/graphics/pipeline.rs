@@ -0,0 +1,422 @@
+use gl::types::{GLenum, GLint, GLuint};
+
+use super::gl_state;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+impl BlendFactor {
+    fn gl(self) -> GLenum {
+        match self {
+            BlendFactor::Zero => gl::ZERO,
+            BlendFactor::One => gl::ONE,
+            BlendFactor::SrcColor => gl::SRC_COLOR,
+            BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+            BlendFactor::DstColor => gl::DST_COLOR,
+            BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+            BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+            BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DstAlpha => gl::DST_ALPHA,
+            BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    Add,
+    // source - destination
+    Subtract,
+    // destination - source
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendOp {
+    fn gl(self) -> GLenum {
+        match self {
+            BlendOp::Add => gl::FUNC_ADD,
+            BlendOp::Subtract => gl::FUNC_SUBTRACT,
+            BlendOp::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            BlendOp::Min => gl::MIN,
+            BlendOp::Max => gl::MAX,
+        }
+    }
+}
+
+// How a fragment is combined with the pixel under it, separately for color and alpha
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlendState {
+    pub color_op: BlendOp,
+    pub source_color: BlendFactor,
+    pub destination_color: BlendFactor,
+    pub alpha_op: BlendOp,
+    pub source_alpha: BlendFactor,
+    pub destination_alpha: BlendFactor,
+}
+
+impl BlendState {
+    // Straight alpha, e.g. PNG images with transparent pixels
+    pub const ALPHA: BlendState = BlendState {
+        color_op: BlendOp::Add,
+        source_color: BlendFactor::SrcAlpha,
+        destination_color: BlendFactor::OneMinusSrcAlpha,
+        alpha_op: BlendOp::Add,
+        source_alpha: BlendFactor::One,
+        destination_alpha: BlendFactor::OneMinusSrcAlpha,
+    };
+
+    // Color already multiplied by its alpha
+    pub const PREMULTIPLIED: BlendState = BlendState {
+        color_op: BlendOp::Add,
+        source_color: BlendFactor::One,
+        destination_color: BlendFactor::OneMinusSrcAlpha,
+        alpha_op: BlendOp::Add,
+        source_alpha: BlendFactor::One,
+        destination_alpha: BlendFactor::OneMinusSrcAlpha,
+    };
+
+    // Sum of the fragments, for lights and particles
+    pub const ADDITIVE: BlendState = BlendState {
+        color_op: BlendOp::Add,
+        source_color: BlendFactor::One,
+        destination_color: BlendFactor::One,
+        alpha_op: BlendOp::Add,
+        source_alpha: BlendFactor::One,
+        destination_alpha: BlendFactor::One,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunc {
+    fn gl(self) -> GLenum {
+        match self {
+            CompareFunc::Never => gl::NEVER,
+            CompareFunc::Less => gl::LESS,
+            CompareFunc::Equal => gl::EQUAL,
+            CompareFunc::LessEqual => gl::LEQUAL,
+            CompareFunc::Greater => gl::GREATER,
+            CompareFunc::NotEqual => gl::NOTEQUAL,
+            CompareFunc::GreaterEqual => gl::GEQUAL,
+            CompareFunc::Always => gl::ALWAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthState {
+    // Compare against the depth buffer with `func`
+    pub test: bool,
+    // Store the depth of the fragments that pass, also works without `test`
+    pub write: bool,
+    pub func: CompareFunc,
+    // Slope factor and constant units added to the depth, see glPolygonOffset
+    pub bias: Option<(f32, f32)>,
+}
+
+impl DepthState {
+    pub const DISABLED: DepthState = DepthState {
+        test: false,
+        write: false,
+        func: CompareFunc::Less,
+        bias: None,
+    };
+
+    pub const READ_WRITE: DepthState = DepthState {
+        test: true,
+        write: true,
+        func: CompareFunc::Less,
+        bias: None,
+    };
+
+    // Hidden behind what was drawn before but doesn't hide what comes after
+    pub const READ_ONLY: DepthState = DepthState {
+        test: true,
+        write: false,
+        func: CompareFunc::Less,
+        bias: None,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    fn gl(self) -> GLenum {
+        match self {
+            StencilOp::Keep => gl::KEEP,
+            StencilOp::Zero => gl::ZERO,
+            StencilOp::Replace => gl::REPLACE,
+            StencilOp::Increment => gl::INCR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::Decrement => gl::DECR,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert => gl::INVERT,
+        }
+    }
+}
+
+// Same test and operations for front and back faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    // Compares `reference & read_mask` with `stencil & read_mask`
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    // Bits the operations may change
+    pub write_mask: u32,
+    pub stencil_fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl StencilState {
+    // Set the stencil to `reference` wherever something is drawn
+    pub const fn write(reference: i32) -> Self {
+        Self {
+            func: CompareFunc::Always,
+            reference,
+            read_mask: !0,
+            write_mask: !0,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Replace,
+        }
+    }
+
+    // Only draw where the stencil equals `reference`, leaving it unchanged
+    pub const fn equal(reference: i32) -> Self {
+        Self {
+            func: CompareFunc::Equal,
+            reference,
+            read_mask: !0,
+            write_mask: 0,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+// Fixed function state of a draw: blending, depth, stencil, culling, rasterization and
+// scissor. Immutable, the `with_*` functions return a modified copy. `apply` sets all of
+// it through `gl_state`, so only what differs from the previous draw reaches GL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PipelineState {
+    blend: Option<BlendState>,
+    depth: DepthState,
+    stencil: Option<StencilState>,
+    cull: CullMode,
+    polygon_mode: PolygonMode,
+    // x, y, width, height in pixels from the bottom left corner
+    scissor: Option<[i32; 4]>,
+}
+
+impl PipelineState {
+    // Depth tested and written, no blending
+    pub const OPAQUE: PipelineState = PipelineState {
+        blend: None,
+        depth: DepthState::READ_WRITE,
+        stencil: None,
+        cull: CullMode::None,
+        polygon_mode: PolygonMode::Fill,
+        scissor: None,
+    };
+
+    // Alpha blended and depth tested without writing, drawn back to front after the opaque
+    pub const TRANSPARENT: PipelineState = PipelineState {
+        blend: Some(BlendState::ALPHA),
+        depth: DepthState::READ_ONLY,
+        ..PipelineState::OPAQUE
+    };
+
+    // Alpha blended without depth, drawn in order over everything, e.g. sprites and text
+    pub const OVERLAY: PipelineState = PipelineState {
+        blend: Some(BlendState::ALPHA),
+        depth: DepthState::DISABLED,
+        ..PipelineState::OPAQUE
+    };
+
+    pub const fn with_blend(self, blend: Option<BlendState>) -> Self {
+        Self { blend, ..self }
+    }
+
+    pub const fn with_depth(self, depth: DepthState) -> Self {
+        Self { depth, ..self }
+    }
+
+    pub const fn with_stencil(self, stencil: Option<StencilState>) -> Self {
+        Self { stencil, ..self }
+    }
+
+    pub const fn with_cull(self, cull: CullMode) -> Self {
+        Self { cull, ..self }
+    }
+
+    pub const fn with_polygon_mode(self, polygon_mode: PolygonMode) -> Self {
+        Self {
+            polygon_mode,
+            ..self
+        }
+    }
+
+    pub const fn with_scissor(self, scissor: Option<[i32; 4]>) -> Self {
+        Self { scissor, ..self }
+    }
+
+    pub fn blend(&self) -> Option<BlendState> {
+        self.blend
+    }
+
+    pub fn depth(&self) -> DepthState {
+        self.depth
+    }
+
+    pub fn stencil(&self) -> Option<StencilState> {
+        self.stencil
+    }
+
+    pub fn cull(&self) -> CullMode {
+        self.cull
+    }
+
+    pub fn polygon_mode(&self) -> PolygonMode {
+        self.polygon_mode
+    }
+
+    pub fn scissor(&self) -> Option<[i32; 4]> {
+        self.scissor
+    }
+
+    pub fn apply(&self) {
+        match self.blend {
+            Some(blend) => {
+                gl_state::set_enabled(gl::BLEND, true);
+                gl_state::blend_equation_separate(blend.color_op.gl(), blend.alpha_op.gl());
+                gl_state::blend_func_separate(
+                    blend.source_color.gl(),
+                    blend.destination_color.gl(),
+                    blend.source_alpha.gl(),
+                    blend.destination_alpha.gl(),
+                );
+            }
+            None => gl_state::set_enabled(gl::BLEND, false),
+        }
+
+        // GL only writes depth with the test enabled, an always passing test stands for none
+        let depth = self.depth;
+        let func = if depth.test {
+            depth.func
+        } else {
+            CompareFunc::Always
+        };
+        gl_state::set_enabled(gl::DEPTH_TEST, depth.test || depth.write);
+        if depth.test || depth.write {
+            gl_state::depth_func(func.gl());
+        }
+        gl_state::depth_mask(depth.write);
+        match depth.bias {
+            Some((factor, units)) => {
+                gl_state::set_enabled(gl::POLYGON_OFFSET_FILL, true);
+                gl_state::polygon_offset(factor, units);
+            }
+            None => gl_state::set_enabled(gl::POLYGON_OFFSET_FILL, false),
+        }
+
+        match self.stencil {
+            Some(stencil) => {
+                gl_state::set_enabled(gl::STENCIL_TEST, true);
+                gl_state::stencil_func(
+                    stencil.func.gl(),
+                    stencil.reference as GLint,
+                    stencil.read_mask as GLuint,
+                );
+                gl_state::stencil_op(
+                    stencil.stencil_fail.gl(),
+                    stencil.depth_fail.gl(),
+                    stencil.pass.gl(),
+                );
+                gl_state::stencil_mask(stencil.write_mask as GLuint);
+            }
+            None => gl_state::set_enabled(gl::STENCIL_TEST, false),
+        }
+
+        match self.cull {
+            CullMode::None => gl_state::set_enabled(gl::CULL_FACE, false),
+            CullMode::Front => {
+                gl_state::set_enabled(gl::CULL_FACE, true);
+                gl_state::cull_face(gl::FRONT);
+            }
+            CullMode::Back => {
+                gl_state::set_enabled(gl::CULL_FACE, true);
+                gl_state::cull_face(gl::BACK);
+            }
+        }
+
+        gl_state::polygon_mode(match self.polygon_mode {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        });
+
+        match self.scissor {
+            Some(rect) => {
+                gl_state::set_enabled(gl::SCISSOR_TEST, true);
+                gl_state::scissor(rect);
+            }
+            None => gl_state::set_enabled(gl::SCISSOR_TEST, false),
+        }
+    }
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        PipelineState::OPAQUE
+    }
+}
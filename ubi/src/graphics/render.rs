@@ -1,18 +1,44 @@
 use crate::core::custom_error::UbiError;
 //use crate::core::logger::init;
 
+use std::rc::Rc;
+
+use crate::core::ecs::world::World;
+
 use super::{buffer::{Ibo, Uniform, Vao, Vbo}, shader::create_program};
+use super::camera::Camera;
+use super::deferred::DeferredPass;
+use super::forward::ForwardPass;
+use super::material::Material;
+use super::render_queue::{RenderCommand, RenderQueue, RenderQueueStats};
 use super::shader::Program;
 
+// How `Renderer::render_scene` shades meshes, chosen when the renderer is created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderPath {
+    #[default]
+    Forward,
+    // G-buffer and light volumes, for scenes with many lights
+    Deferred,
+}
+
 pub struct Renderer {
     pub program: Program,
     pub vao: Vao,
     pub vbo: Vbo,
     pub ibo: Ibo,
+    path: RenderPath,
+    forward: ForwardPass,
+    deferred: Option<DeferredPass>,
+    queue: RenderQueue,
 }
 
 impl Renderer {
     pub fn new() -> Result<Self, UbiError> {
+        Self::with_path(RenderPath::Forward)
+    }
+
+    pub fn with_path(path: RenderPath) -> Result<Self, UbiError> {
         let program = create_program().unwrap();
         program.set();
         let vbo = Vbo::gen();
@@ -20,14 +46,74 @@ impl Renderer {
         vao.set();
         let ibo = Ibo::gen();
 
+        // The forward pass also draws the transparent objects of the deferred path
+        let forward = ForwardPass::new()?;
+        let deferred = match path {
+            RenderPath::Forward => None,
+            RenderPath::Deferred => Some(DeferredPass::new()?),
+        };
+
         Ok(Renderer{
             program: program,
             vao: vao,
             vbo: vbo,
             ibo: ibo,
+            path,
+            forward,
+            deferred,
+            queue: RenderQueue::new(),
         })
     }
 
+    pub fn path(&self) -> RenderPath {
+        self.path
+    }
+
+    pub fn forward(&self) -> &ForwardPass {
+        &self.forward
+    }
+
+    pub fn forward_mut(&mut self) -> &mut ForwardPass {
+        &mut self.forward
+    }
+
+    pub fn deferred(&self) -> Option<&DeferredPass> {
+        self.deferred.as_ref()
+    }
+
+    // Metallic-roughness material of the path: the G-buffer one when deferred
+    pub fn scene_material(&self) -> &Rc<Material> {
+        match &self.deferred {
+            Some(deferred) => deferred.gbuffer_material(),
+            None => self.forward.pbr_material(),
+        }
+    }
+
+    // Draw the `MeshRenderer`s of `world` seen from `camera` into the current framebuffer
+    pub fn render_scene(&mut self, world: &World, camera: &Camera) -> Result<(), UbiError> {
+        match &mut self.deferred {
+            Some(deferred) => deferred.render(world, camera, &mut self.forward),
+            None => {
+                self.forward.render(world, camera);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn queue_mut(&mut self) -> &mut RenderQueue {
+        &mut self.queue
+    }
+
+    pub fn submit(&mut self, pass: u8, command: RenderCommand) {
+        self.queue.submit(pass, command);
+    }
+
+    // Sort and run the commands submitted this frame
+    pub fn execute_queue(&mut self) -> RenderQueueStats {
+        self.queue.execute();
+        self.queue.stats()
+    }
+
     pub fn set_vertices() {
 
     }
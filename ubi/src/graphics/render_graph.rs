@@ -0,0 +1,796 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use gl::types::GLuint;
+
+use crate::core::custom_error::UbiError;
+
+use super::framebuffer::{Framebuffer, SavedTarget};
+use super::texture::{Texture, TextureFormat};
+
+// Handle of a texture declared in a `RenderGraph`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+// Size and format of a transient render target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+}
+
+impl TextureDesc {
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+        }
+    }
+}
+
+impl fmt::Display for TextureDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{} {:?}", self.width, self.height, self.format)
+    }
+}
+
+enum ResourceKind {
+    // Allocated by the graph, only alive between its first and last use
+    Transient(TextureDesc),
+    // Owned by the caller, e.g. a texture shown later by egui
+    Imported(Rc<Texture>),
+    // Framebuffer bound when the graph is executed, usually the window
+    Backbuffer,
+}
+
+struct ResourceNode {
+    name: String,
+    kind: ResourceKind,
+}
+
+type PassFn<'a> = Box<dyn FnMut(&PassContext) + 'a>;
+
+struct PassNode<'a> {
+    name: String,
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>,
+    side_effect: bool,
+    execute: Option<PassFn<'a>>,
+}
+
+struct Compiled {
+    // Passes in execution order, culled ones included
+    order: Vec<usize>,
+    culled: Vec<bool>,
+    // Physical target of each transient resource
+    slots: Vec<Option<usize>>,
+    slot_descs: Vec<TextureDesc>,
+}
+
+// Textures available to a pass while it runs, with the size of its target
+pub struct PassContext<'p> {
+    textures: &'p [Option<Rc<Texture>>],
+    reads: &'p [ResourceId],
+    writes: &'p [ResourceId],
+    width: u32,
+    height: u32,
+}
+
+impl PassContext<'_> {
+    // Texture of a resource the pass declared, `None` for the backbuffer
+    pub fn texture(&self, id: ResourceId) -> Option<&Rc<Texture>> {
+        if !self.reads.contains(&id) && !self.writes.contains(&id) {
+            return None;
+        }
+        self.textures[id.0].as_ref()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+// Declares what a pass reads and writes, returned by `RenderGraph::add_pass`
+pub struct PassBuilder<'g, 'a> {
+    graph: &'g mut RenderGraph<'a>,
+    index: usize,
+}
+
+impl<'a> PassBuilder<'_, 'a> {
+    // Sampled by the pass. Passes declared before that write it run before, passes declared
+    // after that write it wait until it was read
+    pub fn read(self, id: ResourceId) -> Self {
+        self.graph.passes[self.index].reads.push(id);
+        self
+    }
+
+    // Rendered into by the pass. Color textures are attached in the order they are
+    // written, a depth texture goes to the depth attachment.
+    pub fn write(self, id: ResourceId) -> Self {
+        self.graph.passes[self.index].writes.push(id);
+        self
+    }
+
+    // Never culled, for passes with effects outside of the graph like readbacks
+    pub fn side_effect(self) -> Self {
+        self.graph.passes[self.index].side_effect = true;
+        self
+    }
+
+    pub fn execute(self, execute: impl FnMut(&PassContext) + 'a) {
+        self.graph.passes[self.index].execute = Some(Box::new(execute));
+    }
+}
+
+// Frame described as passes and the textures they read and write. `compile` orders the
+// passes from their dependencies, culls the ones that don't contribute to the backbuffer,
+// an output or a side effect, and assigns transient textures with the same description
+// and disjoint lifetimes to the same target. Built every frame, the targets are kept
+// between frames by a `RenderTargetPool`.
+pub struct RenderGraph<'a> {
+    resources: Vec<ResourceNode>,
+    passes: Vec<PassNode<'a>>,
+    outputs: Vec<ResourceId>,
+    compiled: Option<Compiled>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self {
+            resources: vec![ResourceNode {
+                name: "backbuffer".to_string(),
+                kind: ResourceKind::Backbuffer,
+            }],
+            passes: Vec::new(),
+            outputs: Vec::new(),
+            compiled: None,
+        }
+    }
+
+    pub fn backbuffer(&self) -> ResourceId {
+        ResourceId(0)
+    }
+
+    pub fn create_texture(&mut self, name: &str, desc: TextureDesc) -> ResourceId {
+        self.add_resource(name, ResourceKind::Transient(desc))
+    }
+
+    pub fn import_texture(&mut self, name: &str, texture: Rc<Texture>) -> ResourceId {
+        self.add_resource(name, ResourceKind::Imported(texture))
+    }
+
+    // Keep the passes writing `id` even if nothing reads it
+    pub fn mark_output(&mut self, id: ResourceId) {
+        self.outputs.push(id);
+        self.compiled = None;
+    }
+
+    pub fn add_pass(&mut self, name: &str) -> PassBuilder<'_, 'a> {
+        self.passes.push(PassNode {
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+            side_effect: false,
+            execute: None,
+        });
+        self.compiled = None;
+        PassBuilder {
+            index: self.passes.len() - 1,
+            graph: self,
+        }
+    }
+
+    fn add_resource(&mut self, name: &str, kind: ResourceKind) -> ResourceId {
+        self.resources.push(ResourceNode {
+            name: name.to_string(),
+            kind,
+        });
+        self.compiled = None;
+        ResourceId(self.resources.len() - 1)
+    }
+
+    pub fn compile(&mut self) -> Result<(), UbiError> {
+        let backbuffer = self.backbuffer();
+        let mut writers: Vec<Vec<usize>> = vec![Vec::new(); self.resources.len()];
+        let mut readers: Vec<Vec<usize>> = vec![Vec::new(); self.resources.len()];
+        for (index, pass) in self.passes.iter().enumerate() {
+            for id in pass.reads.iter().chain(&pass.writes) {
+                if id.0 >= self.resources.len() {
+                    return Err(UbiError::ResourceError(format!(
+                        "pass `{}` uses an unknown resource {}",
+                        pass.name, id.0
+                    )));
+                }
+            }
+            if pass.writes.contains(&backbuffer) && pass.writes.len() > 1 {
+                return Err(UbiError::ResourceError(format!(
+                    "pass `{}` writes the backbuffer and textures",
+                    pass.name
+                )));
+            }
+            let depth_writes = pass
+                .writes
+                .iter()
+                .filter(|id| self.format(**id) == Some(TextureFormat::Depth32F))
+                .count();
+            if depth_writes > 1 {
+                return Err(UbiError::ResourceError(format!(
+                    "pass `{}` writes {} depth textures",
+                    pass.name, depth_writes
+                )));
+            }
+            for id in &pass.reads {
+                readers[id.0].push(index);
+            }
+            for id in &pass.writes {
+                writers[id.0].push(index);
+            }
+        }
+        for (id, resource) in self.resources.iter().enumerate() {
+            if let ResourceKind::Transient(_) = resource.kind {
+                if writers[id].is_empty() && !readers[id].is_empty() {
+                    return Err(UbiError::ResourceError(format!(
+                        "pass `{}` reads `{}` that no pass writes",
+                        self.passes[readers[id][0]].name, resource.name
+                    )));
+                }
+                if let (Some(&reader), Some(&writer)) = (readers[id].first(), writers[id].first()) {
+                    if reader < writer {
+                        return Err(UbiError::ResourceError(format!(
+                            "pass `{}` reads `{}` before any pass writes it",
+                            self.passes[reader].name, resource.name
+                        )));
+                    }
+                }
+            }
+        }
+
+        // Accesses to a resource keep their declaration order: a reader runs after the writers
+        // declared before it and before the ones declared after, writers keep their order
+        let mut successors: Vec<Vec<usize>> = vec![Vec::new(); self.passes.len()];
+        let mut incoming = vec![0; self.passes.len()];
+        for id in 0..self.resources.len() {
+            for &writer in &writers[id] {
+                for &reader in &readers[id] {
+                    let (first, second) = match reader.cmp(&writer) {
+                        std::cmp::Ordering::Greater => (writer, reader),
+                        std::cmp::Ordering::Less => (reader, writer),
+                        std::cmp::Ordering::Equal => continue,
+                    };
+                    successors[first].push(second);
+                    incoming[second] += 1;
+                }
+            }
+            for pair in writers[id].windows(2) {
+                if pair[0] != pair[1] {
+                    successors[pair[0]].push(pair[1]);
+                    incoming[pair[1]] += 1;
+                }
+            }
+        }
+        // Kahn's algorithm, ties broken by declaration order
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut ready: Vec<usize> = (0..self.passes.len())
+            .filter(|pass| incoming[*pass] == 0)
+            .collect();
+        while let Some(position) = (0..ready.len()).min_by_key(|i| ready[*i]) {
+            let pass = ready.swap_remove(position);
+            order.push(pass);
+            for &next in &successors[pass] {
+                incoming[next] -= 1;
+                if incoming[next] == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+        if order.len() != self.passes.len() {
+            let cycle: Vec<&str> = (0..self.passes.len())
+                .filter(|pass| incoming[*pass] > 0)
+                .map(|pass| self.passes[pass].name.as_str())
+                .collect();
+            return Err(UbiError::Other(format!(
+                "render graph cycle between passes {}",
+                cycle.join(", ")
+            )));
+        }
+
+        // Walk back from the outputs, a pass is kept if something kept reads what it writes
+        let mut needed = vec![false; self.resources.len()];
+        needed[backbuffer.0] = true;
+        for id in &self.outputs {
+            needed[id.0] = true;
+        }
+        let mut culled = vec![true; self.passes.len()];
+        for &index in order.iter().rev() {
+            let pass = &self.passes[index];
+            if pass.side_effect || pass.writes.iter().any(|id| needed[id.0]) {
+                culled[index] = false;
+                for id in &pass.reads {
+                    needed[id.0] = true;
+                }
+            }
+        }
+
+        // Lifetimes of the transient textures over the kept passes
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; self.resources.len()];
+        let kept: Vec<usize> = order.iter().copied().filter(|i| !culled[*i]).collect();
+        for (position, &index) in kept.iter().enumerate() {
+            let pass = &self.passes[index];
+            for id in pass.reads.iter().chain(&pass.writes) {
+                let lifetime = lifetimes[id.0].get_or_insert((position, position));
+                lifetime.1 = position;
+            }
+        }
+        // Aliasing: a target is reused once the last pass using its texture ran
+        let mut slots: Vec<Option<usize>> = vec![None; self.resources.len()];
+        let mut slot_descs: Vec<TextureDesc> = Vec::new();
+        let mut slot_ends: Vec<usize> = Vec::new();
+        for position in 0..kept.len() {
+            for (id, resource) in self.resources.iter().enumerate() {
+                let desc = match resource.kind {
+                    ResourceKind::Transient(desc) => desc,
+                    _ => continue,
+                };
+                let (first, last) = match lifetimes[id] {
+                    Some(lifetime) if lifetime.0 == position => lifetime,
+                    _ => continue,
+                };
+                let free = (0..slot_descs.len())
+                    .find(|slot| slot_descs[*slot] == desc && slot_ends[*slot] < first);
+                let slot = match free {
+                    Some(slot) => slot,
+                    None => {
+                        slot_descs.push(desc);
+                        slot_ends.push(0);
+                        slot_descs.len() - 1
+                    }
+                };
+                slot_ends[slot] = last;
+                slots[id] = Some(slot);
+            }
+        }
+
+        self.compiled = Some(Compiled {
+            order,
+            culled,
+            slots,
+            slot_descs,
+        });
+        Ok(())
+    }
+
+    // Compile if needed, then run the kept passes with their targets bound
+    pub fn execute(&mut self, pool: &mut RenderTargetPool) -> Result<(), UbiError> {
+        if self.compiled.is_none() {
+            self.compile()?;
+        }
+        let compiled = match self.compiled.take() {
+            Some(compiled) => compiled,
+            None => return Ok(()),
+        };
+        let result = self.run(&compiled, pool);
+        self.compiled = Some(compiled);
+        pool.end_frame();
+        result
+    }
+
+    fn run(&mut self, compiled: &Compiled, pool: &mut RenderTargetPool) -> Result<(), UbiError> {
+        let targets = pool.acquire(&compiled.slot_descs)?;
+        let textures: Vec<Option<Rc<Texture>>> = self
+            .resources
+            .iter()
+            .enumerate()
+            .map(|(id, resource)| match &resource.kind {
+                ResourceKind::Transient(_) => compiled.slots[id].map(|slot| targets[slot].clone()),
+                ResourceKind::Imported(texture) => Some(texture.clone()),
+                ResourceKind::Backbuffer => None,
+            })
+            .collect();
+
+        let saved = SavedTarget::save();
+        let [_, _, saved_width, saved_height] = saved.viewport();
+        for &index in &compiled.order {
+            if compiled.culled[index] {
+                continue;
+            }
+            let PassNode {
+                reads,
+                writes,
+                execute,
+                ..
+            } = &mut self.passes[index];
+            let attachments: Vec<Rc<Texture>> = writes
+                .iter()
+                .filter_map(|id| textures[id.0].clone())
+                .collect();
+            let (width, height) = match attachments.first() {
+                Some(first) => {
+                    pool.framebuffer(&attachments)?.bind();
+                    unsafe {
+                        gl::Viewport(0, 0, first.width() as i32, first.height() as i32);
+                    }
+                    (first.width(), first.height())
+                }
+                None => {
+                    saved.restore();
+                    (saved_width as u32, saved_height as u32)
+                }
+            };
+            if let Some(execute) = execute {
+                execute(&PassContext {
+                    textures: &textures,
+                    reads,
+                    writes,
+                    width,
+                    height,
+                });
+            }
+        }
+        saved.restore();
+        Ok(())
+    }
+
+    fn format(&self, id: ResourceId) -> Option<TextureFormat> {
+        match &self.resources.get(id.0)?.kind {
+            ResourceKind::Transient(desc) => Some(desc.format),
+            ResourceKind::Imported(texture) => Some(texture.format()),
+            ResourceKind::Backbuffer => None,
+        }
+    }
+
+    fn resource_label(&self, id: ResourceId) -> String {
+        let resource = &self.resources[id.0];
+        let slot = self
+            .compiled
+            .as_ref()
+            .and_then(|compiled| compiled.slots[id.0]);
+        match (&resource.kind, slot) {
+            (ResourceKind::Transient(desc), Some(slot)) => {
+                format!("{} ({}, target {})", resource.name, desc, slot)
+            }
+            (ResourceKind::Transient(desc), None) => format!("{} ({})", resource.name, desc),
+            (ResourceKind::Imported(_), _) => format!("{} (imported)", resource.name),
+            (ResourceKind::Backbuffer, _) => resource.name.clone(),
+        }
+    }
+
+    // Graphviz description, culled passes are dashed
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph RenderGraph {\n    rankdir=LR;\n");
+        for (id, resource) in self.resources.iter().enumerate() {
+            let shape = match resource.kind {
+                ResourceKind::Transient(_) => "ellipse",
+                _ => "box3d",
+            };
+            dot.push_str(&format!(
+                "    r{} [shape={}, label=\"{}\"];\n",
+                id,
+                shape,
+                self.resource_label(ResourceId(id))
+            ));
+        }
+        for (index, pass) in self.passes.iter().enumerate() {
+            let culled = self
+                .compiled
+                .as_ref()
+                .map(|compiled| compiled.culled[index])
+                .unwrap_or(false);
+            let style = if culled { "dashed" } else { "filled" };
+            dot.push_str(&format!(
+                "    p{} [shape=box, style={}, label=\"{}\"];\n",
+                index, style, pass.name
+            ));
+            for id in &pass.reads {
+                dot.push_str(&format!("    r{} -> p{};\n", id.0, index));
+            }
+            for id in &pass.writes {
+                dot.push_str(&format!("    p{} -> r{};\n", index, id.0));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Default for RenderGraph<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Passes in execution order with their resources, once compiled
+impl fmt::Display for RenderGraph<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let compiled = match &self.compiled {
+            Some(compiled) => compiled,
+            None => {
+                return writeln!(
+                    f,
+                    "render graph: {} passes, not compiled",
+                    self.passes.len()
+                )
+            }
+        };
+        writeln!(
+            f,
+            "render graph: {} passes, {} culled, {} targets",
+            self.passes.len(),
+            compiled.culled.iter().filter(|culled| **culled).count(),
+            compiled.slot_descs.len()
+        )?;
+        for &index in &compiled.order {
+            let pass = &self.passes[index];
+            let culled = if compiled.culled[index] {
+                " (culled)"
+            } else {
+                ""
+            };
+            writeln!(f, "  {}{}", pass.name, culled)?;
+            for id in &pass.reads {
+                writeln!(f, "    reads  {}", self.resource_label(*id))?;
+            }
+            for id in &pass.writes {
+                writeln!(f, "    writes {}", self.resource_label(*id))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct PooledTexture {
+    desc: TextureDesc,
+    texture: Rc<Texture>,
+    used: bool,
+}
+
+struct PooledFramebuffer {
+    framebuffer: Framebuffer,
+    // Keeps the attached textures, and so their ids, alive
+    _attachments: Vec<Rc<Texture>>,
+    used: bool,
+}
+
+// Render targets and framebuffers of the transient textures, reused from one frame to the
+// next. Those a frame didn't use are deleted at its end.
+pub struct RenderTargetPool {
+    textures: Vec<PooledTexture>,
+    framebuffers: HashMap<Vec<GLuint>, PooledFramebuffer>,
+}
+
+impl RenderTargetPool {
+    pub fn new() -> Self {
+        Self {
+            textures: Vec::new(),
+            framebuffers: HashMap::new(),
+        }
+    }
+
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    fn acquire(&mut self, descs: &[TextureDesc]) -> Result<Vec<Rc<Texture>>, UbiError> {
+        let mut acquired = Vec::with_capacity(descs.len());
+        for desc in descs {
+            let free = self
+                .textures
+                .iter_mut()
+                .find(|pooled| !pooled.used && pooled.desc == *desc);
+            let texture = match free {
+                Some(pooled) => {
+                    pooled.used = true;
+                    pooled.texture.clone()
+                }
+                None => {
+                    let texture =
+                        Rc::new(Texture::with_format(desc.width, desc.height, desc.format)?);
+                    self.textures.push(PooledTexture {
+                        desc: *desc,
+                        texture: texture.clone(),
+                        used: true,
+                    });
+                    texture
+                }
+            };
+            acquired.push(texture);
+        }
+        Ok(acquired)
+    }
+
+    fn framebuffer(&mut self, attachments: &[Rc<Texture>]) -> Result<&Framebuffer, UbiError> {
+        let key: Vec<GLuint> = attachments.iter().map(|texture| texture.id).collect();
+        if !self.framebuffers.contains_key(&key) {
+            let framebuffer = Framebuffer::gen();
+            let mut colors = 0;
+            for texture in attachments {
+                if texture.format() == TextureFormat::Depth32F {
+                    framebuffer.attach_depth(gl::TEXTURE_2D, texture.id, 0);
+                } else {
+                    framebuffer.attach_color(colors, gl::TEXTURE_2D, texture.id, 0);
+                    colors += 1;
+                }
+            }
+            framebuffer.set_draw_buffers(colors);
+            framebuffer.check()?;
+            self.framebuffers.insert(
+                key.clone(),
+                PooledFramebuffer {
+                    framebuffer,
+                    _attachments: attachments.to_vec(),
+                    used: false,
+                },
+            );
+        }
+        let pooled = self.framebuffers.get_mut(&key).unwrap();
+        pooled.used = true;
+        Ok(&pooled.framebuffer)
+    }
+
+    fn end_frame(&mut self) {
+        self.framebuffers.retain(|_, pooled| pooled.used);
+        self.textures.retain(|pooled| pooled.used);
+        for pooled in self.framebuffers.values_mut() {
+            pooled.used = false;
+        }
+        for pooled in &mut self.textures {
+            pooled.used = false;
+        }
+    }
+}
+
+impl Default for RenderTargetPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc() -> TextureDesc {
+        TextureDesc::new(64, 64, TextureFormat::Rgba8)
+    }
+
+    fn order(graph: &RenderGraph) -> Vec<String> {
+        let compiled = graph.compiled.as_ref().unwrap();
+        compiled
+            .order
+            .iter()
+            .filter(|index| !compiled.culled[**index])
+            .map(|index| graph.passes[*index].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn reader_runs_before_the_next_writer() {
+        let mut graph = RenderGraph::new();
+        let x = graph.create_texture("x", desc());
+        let backbuffer = graph.backbuffer();
+        graph.add_pass("a").write(x).execute(|_| {});
+        graph
+            .add_pass("b")
+            .read(x)
+            .write(backbuffer)
+            .execute(|_| {});
+        graph.add_pass("c").write(x).execute(|_| {});
+        graph
+            .add_pass("d")
+            .read(x)
+            .write(backbuffer)
+            .execute(|_| {});
+        graph.compile().unwrap();
+        assert_eq!(order(&graph), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn writers_run_before_later_readers() {
+        let mut graph = RenderGraph::new();
+        let x = graph.create_texture("x", desc());
+        let y = graph.create_texture("y", desc());
+        let backbuffer = graph.backbuffer();
+        graph.add_pass("x").write(x).execute(|_| {});
+        graph.add_pass("y").write(y).execute(|_| {});
+        graph
+            .add_pass("combine")
+            .read(y)
+            .read(x)
+            .write(backbuffer)
+            .execute(|_| {});
+        graph.compile().unwrap();
+        assert_eq!(order(&graph), ["x", "y", "combine"]);
+    }
+
+    #[test]
+    fn reading_before_any_write_is_an_error() {
+        let mut graph = RenderGraph::new();
+        let x = graph.create_texture("x", desc());
+        let backbuffer = graph.backbuffer();
+        graph
+            .add_pass("read")
+            .read(x)
+            .write(backbuffer)
+            .execute(|_| {});
+        graph.add_pass("write").write(x).execute(|_| {});
+        assert!(graph.compile().is_err());
+    }
+
+    #[test]
+    fn unused_passes_are_culled() {
+        let mut graph = RenderGraph::new();
+        let x = graph.create_texture("x", desc());
+        let unused = graph.create_texture("unused", desc());
+        let backbuffer = graph.backbuffer();
+        graph.add_pass("x").write(x).execute(|_| {});
+        graph.add_pass("unused").write(unused).execute(|_| {});
+        graph.add_pass("effect").side_effect().execute(|_| {});
+        graph
+            .add_pass("final")
+            .read(x)
+            .write(backbuffer)
+            .execute(|_| {});
+        graph.compile().unwrap();
+        assert_eq!(order(&graph), ["x", "effect", "final"]);
+
+        graph.mark_output(unused);
+        graph.compile().unwrap();
+        assert_eq!(order(&graph), ["x", "unused", "effect", "final"]);
+    }
+
+    #[test]
+    fn disjoint_lifetimes_share_a_target() {
+        let mut graph = RenderGraph::new();
+        let x = graph.create_texture("x", desc());
+        let y = graph.create_texture("y", desc());
+        let z = graph.create_texture("z", desc());
+        let small = graph.create_texture("small", TextureDesc::new(32, 32, TextureFormat::Rgba8));
+        let backbuffer = graph.backbuffer();
+        graph.add_pass("a").write(x).execute(|_| {});
+        graph.add_pass("b").read(x).write(y).execute(|_| {});
+        graph
+            .add_pass("c")
+            .read(y)
+            .write(z)
+            .write(small)
+            .execute(|_| {});
+        graph
+            .add_pass("d")
+            .read(z)
+            .read(small)
+            .write(backbuffer)
+            .execute(|_| {});
+        graph.compile().unwrap();
+        let compiled = graph.compiled.as_ref().unwrap();
+        // x is done before z is first written, y overlaps both
+        assert_eq!(compiled.slots[x.0], compiled.slots[z.0]);
+        assert_ne!(compiled.slots[x.0], compiled.slots[y.0]);
+        // Only textures with the same description are aliased
+        assert_ne!(compiled.slots[small.0], compiled.slots[x.0]);
+        assert_eq!(compiled.slot_descs.len(), 3);
+    }
+
+    #[test]
+    fn passes_can_read_and_write_in_turn() {
+        let mut graph = RenderGraph::new();
+        let x = graph.create_texture("x", desc());
+        let y = graph.create_texture("y", desc());
+        let backbuffer = graph.backbuffer();
+        graph.add_pass("x").write(x).execute(|_| {});
+        graph.add_pass("y").read(x).write(y).execute(|_| {});
+        graph.add_pass("both").read(y).write(x).execute(|_| {});
+        graph
+            .add_pass("final")
+            .read(x)
+            .write(backbuffer)
+            .execute(|_| {});
+        graph.compile().unwrap();
+        assert_eq!(order(&graph), ["x", "y", "both", "final"]);
+    }
+}
@@ -0,0 +1,295 @@
+use std::rc::Rc;
+
+use gl::types::GLint;
+
+use crate::core::math::bounds::Frustum;
+use crate::core::math::transform::Mat4;
+use crate::core::math::vector::{dot, normalize, sub};
+
+use super::camera::Camera;
+use super::culling::{is_visible, CullingStats};
+use super::gl_state;
+use super::instancing::{draw_instanced, InstanceBuffer};
+use super::material::Material;
+use super::mesh::Mesh;
+
+// Bits of the packed sort key, from the most significant:
+// pass (8) | stage (2) | depth (24) | material (30)
+const STAGE_SHIFT: u32 = 54;
+const DEPTH_SHIFT: u32 = 30;
+const DEPTH_MAX: u32 = (1 << 24) - 1;
+const MATERIAL_MASK: u64 = (1 << 30) - 1;
+
+// Order of the commands inside a pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderStage {
+    // Clears, viewports and cameras, kept in submission order
+    Setup = 0,
+    // Front to back
+    Opaque = 1,
+    // Back to front, blended
+    Translucent = 2,
+}
+
+// Pack a sort key, `depth` keeps its low 24 bits and `material` its low 30
+pub fn sort_key(pass: u8, stage: RenderStage, depth: u32, material: u32) -> u64 {
+    ((pass as u64) << 56)
+        | ((stage as u64) << STAGE_SHIFT)
+        | (((depth & DEPTH_MAX) as u64) << DEPTH_SHIFT)
+        | (material as u64 & MATERIAL_MASK)
+}
+
+// Something to do with the GL context, deferred until the queue is executed
+#[derive(Clone)]
+pub enum RenderCommand {
+    // Clear the color buffer to `color` if any, and the depth buffer if `depth`
+    Clear {
+        color: Option<[f32; 4]>,
+        depth: bool,
+    },
+    Viewport {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    // View of the draws of the pass, sets u_view_projection and u_camera_position
+    Camera(Camera),
+    // The model matrix goes to the instance attributes at INSTANCE_LOCATION
+    DrawMesh {
+        mesh: Rc<Mesh>,
+        material: Rc<Material>,
+        transform: Mat4,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderQueueStats {
+    pub commands: u32,
+    pub draw_calls: u32,
+    pub culling: CullingStats,
+}
+
+struct Queued {
+    pass: u8,
+    key: u64,
+    command: RenderCommand,
+}
+
+// Commands submitted during a frame, sorted by their packed key then executed at once.
+// Passes run in increasing order. Within a pass the setup commands come first, then the
+// opaque draws front to back and the translucent ones back to front, using the last camera
+// of the pass for the depth. Equal keys keep their submission order. Draws outside the
+// frustum of that camera are dropped before sorting.
+pub struct RenderQueue {
+    commands: Vec<Queued>,
+    instances: InstanceBuffer,
+    stats: RenderQueueStats,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+            instances: InstanceBuffer::transforms(),
+            stats: RenderQueueStats::default(),
+        }
+    }
+
+    pub fn submit(&mut self, pass: u8, command: RenderCommand) {
+        self.commands.push(Queued {
+            pass,
+            key: 0,
+            command,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // Drop the commands without executing them
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    // Stats of the last `execute`
+    pub fn stats(&self) -> RenderQueueStats {
+        self.stats
+    }
+
+    // Run every command in key order and empty the queue
+    pub fn execute(&mut self) {
+        let cameras = self.pass_cameras();
+        self.stats = RenderQueueStats::default();
+        self.cull(&cameras);
+        self.sort(&cameras);
+        self.stats.commands = self.commands.len() as u32;
+
+        let commands = std::mem::take(&mut self.commands);
+        let mut camera: Option<Camera> = None;
+        let mut current_material: Option<*const Material> = None;
+        let mut camera_changed = true;
+        let mut index = 0;
+        while index < commands.len() {
+            let queued = &commands[index];
+            index += 1;
+            match &queued.command {
+                RenderCommand::Clear { color, depth } => unsafe {
+                    if let Some(color) = color {
+                        gl::ClearBufferfv(gl::COLOR, 0, color.as_ptr());
+                    }
+                    if *depth {
+                        gl_state::depth_mask(true);
+                        gl::ClearBufferfv(gl::DEPTH, 0, &1.0);
+                    }
+                },
+                RenderCommand::Viewport {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => unsafe {
+                    gl::Viewport(*x, *y, *width as GLint, *height as GLint);
+                },
+                RenderCommand::Camera(new_camera) => {
+                    camera = Some(*new_camera);
+                    camera_changed = true;
+                }
+                RenderCommand::DrawMesh {
+                    mesh,
+                    material,
+                    transform,
+                } => {
+                    // Following draws of the same mesh and material become instances
+                    let mut transforms = transform.as_array().to_vec();
+                    while let Some(RenderCommand::DrawMesh {
+                        mesh: next_mesh,
+                        material: next_material,
+                        transform: next_transform,
+                    }) = commands.get(index).map(|next| &next.command)
+                    {
+                        if !Rc::ptr_eq(mesh, next_mesh)
+                            || !Rc::ptr_eq(material, next_material)
+                            || commands[index].pass != queued.pass
+                        {
+                            break;
+                        }
+                        transforms.extend_from_slice(next_transform.as_array());
+                        index += 1;
+                    }
+
+                    if current_material != Some(Rc::as_ptr(material)) {
+                        material.apply();
+                        current_material = Some(Rc::as_ptr(material));
+                        camera_changed = true;
+                    }
+                    if camera_changed {
+                        if let Some(camera) = &camera {
+                            let program = material.program();
+                            program.set_mat4("u_view_projection", &camera.view_projection());
+                            program.set_vec3("u_camera_position", camera.position);
+                        }
+                        camera_changed = false;
+                    }
+                    self.instances.set(&transforms);
+                    draw_instanced(mesh, &self.instances, self.instances.len());
+                    self.stats.draw_calls += 1;
+                }
+            }
+        }
+        // Keep the allocation for the next frame
+        self.commands = commands;
+        self.commands.clear();
+    }
+
+    // Each pass sees its draws through its last camera
+    fn pass_cameras(&self) -> [Option<Camera>; 256] {
+        let mut cameras: [Option<Camera>; 256] = [None; 256];
+        for queued in &self.commands {
+            if let RenderCommand::Camera(camera) = &queued.command {
+                cameras[queued.pass as usize] = Some(*camera);
+            }
+        }
+        cameras
+    }
+
+    // Drop the draws outside the frustum of their pass, passes without a camera keep all
+    fn cull(&mut self, cameras: &[Option<Camera>; 256]) {
+        let frustums = cameras
+            .map(|camera| camera.map(|camera| Frustum::from_matrix(&camera.view_projection())));
+        let stats = &mut self.stats.culling;
+        self.commands.retain(|queued| match &queued.command {
+            RenderCommand::DrawMesh {
+                mesh, transform, ..
+            } => match &frustums[queued.pass as usize] {
+                Some(frustum) => stats.record(is_visible(frustum, mesh, transform)),
+                None => true,
+            },
+            _ => true,
+        });
+    }
+
+    fn sort(&mut self, cameras: &[Option<Camera>; 256]) {
+        for queued in &mut self.commands {
+            queued.key = match &queued.command {
+                RenderCommand::DrawMesh {
+                    material,
+                    transform,
+                    ..
+                } => {
+                    let depth = cameras[queued.pass as usize]
+                        .as_ref()
+                        .map(|camera| quantized_depth(camera, transform.translation()))
+                        .unwrap_or(0);
+                    if material.is_transparent() {
+                        sort_key(
+                            queued.pass,
+                            RenderStage::Translucent,
+                            DEPTH_MAX - depth,
+                            material_bits(material),
+                        )
+                    } else {
+                        sort_key(
+                            queued.pass,
+                            RenderStage::Opaque,
+                            depth,
+                            material_bits(material),
+                        )
+                    }
+                }
+                _ => sort_key(queued.pass, RenderStage::Setup, 0, 0),
+            };
+        }
+        // Stable, so equal keys stay in submission order
+        self.commands.sort_by_key(|queued| queued.key);
+    }
+}
+
+impl Default for RenderQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Distance along the view direction between the near and far planes, on 24 bits
+fn quantized_depth(camera: &Camera, point: [f32; 3]) -> u32 {
+    let forward = normalize(sub(camera.target, camera.position));
+    let distance = dot(sub(point, camera.position), forward);
+    let (near, far) = (camera.near(), camera.far());
+    let t = ((distance - near) / (far - near).max(f32::EPSILON)).clamp(0.0, 1.0);
+    (t * DEPTH_MAX as f32) as u32
+}
+
+// Program (10 bits) | first texture (10 bits) | material (10 bits), from `Material::sort_key`
+fn material_bits(material: &Material) -> u32 {
+    let key = material.sort_key();
+    let program = (key >> 48) & 0x3ff;
+    let texture = (key >> 24) & 0x3ff;
+    let id = key & 0x3ff;
+    ((program << 20) | (texture << 10) | id) as u32
+}
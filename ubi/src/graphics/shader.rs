@@ -5,6 +5,11 @@ use std::{
 
 use gl::types::{GLchar, GLenum, GLint, GLuint};
 
+use crate::core::custom_error::UbiError;
+use crate::core::math::transform::Mat4;
+
+use super::gl_state;
+
 // An OpenGL Shader
 pub struct Shader {
     id: GLuint,
@@ -105,19 +110,178 @@ impl Program {
         Ok(Program { id })
     }
 
+    // Compile and link a program from vertex and fragment shader sources
+    pub fn from_sources(vertex: &str, fragment: &str) -> Result<Self, UbiError> {
+        let to_cstring =
+            |source: &str| CString::new(source).map_err(|e| UbiError::ShaderError(e.to_string()));
+        let vert_shader = Shader::from_source(&to_cstring(vertex)?, gl::VERTEX_SHADER)
+            .map_err(UbiError::ShaderError)?;
+        let frag_shader = Shader::from_source(&to_cstring(fragment)?, gl::FRAGMENT_SHADER)
+            .map_err(UbiError::ShaderError)?;
+        Program::from_shaders(&[vert_shader, frag_shader]).map_err(UbiError::ShaderError)
+    }
+
     pub fn set(&self) {
+        gl_state::use_program(self.id);
+    }
+
+    // Returns -1 when the uniform is not active, GL ignores writes to -1
+    pub fn uniform_location(&self, name: &str) -> GLint {
+        let cname = CString::new(name).expect("CString::new failed");
+        unsafe { gl::GetUniformLocation(self.id, cname.as_ptr()) }
+    }
+
+    // The following setters expect the program to be in use
+    pub fn set_int(&self, name: &str, value: i32) {
+        unsafe { gl::Uniform1i(self.uniform_location(name), value) }
+    }
+
+    pub fn set_int_array(&self, name: &str, values: &[i32]) {
+        unsafe {
+            gl::Uniform1iv(
+                self.uniform_location(name),
+                values.len() as GLint,
+                values.as_ptr(),
+            )
+        }
+    }
+
+    pub fn set_float(&self, name: &str, value: f32) {
+        unsafe { gl::Uniform1f(self.uniform_location(name), value) }
+    }
+
+    pub fn set_vec2(&self, name: &str, value: [f32; 2]) {
+        unsafe { gl::Uniform2f(self.uniform_location(name), value[0], value[1]) }
+    }
+
+    pub fn set_vec3(&self, name: &str, value: [f32; 3]) {
+        unsafe { gl::Uniform3f(self.uniform_location(name), value[0], value[1], value[2]) }
+    }
+
+    pub fn set_vec4(&self, name: &str, value: [f32; 4]) {
+        unsafe {
+            gl::Uniform4f(
+                self.uniform_location(name),
+                value[0],
+                value[1],
+                value[2],
+                value[3],
+            )
+        }
+    }
+
+    pub fn set_float_array(&self, name: &str, values: &[f32]) {
+        unsafe {
+            gl::Uniform1fv(
+                self.uniform_location(name),
+                values.len() as GLint,
+                values.as_ptr(),
+            )
+        }
+    }
+
+    pub fn set_vec2_array(&self, name: &str, values: &[[f32; 2]]) {
+        unsafe {
+            gl::Uniform2fv(
+                self.uniform_location(name),
+                values.len() as GLint,
+                values.as_ptr() as *const f32,
+            )
+        }
+    }
+
+    pub fn set_vec3_array(&self, name: &str, values: &[[f32; 3]]) {
+        unsafe {
+            gl::Uniform3fv(
+                self.uniform_location(name),
+                values.len() as GLint,
+                values.as_ptr() as *const f32,
+            )
+        }
+    }
+
+    pub fn set_vec4_array(&self, name: &str, values: &[[f32; 4]]) {
         unsafe {
-            gl::UseProgram(self.id);
+            gl::Uniform4fv(
+                self.uniform_location(name),
+                values.len() as GLint,
+                values.as_ptr() as *const f32,
+            )
         }
     }
 
+    // Mat4 is stored row major so GL has to transpose it
+    pub fn set_mat4(&self, name: &str, value: &Mat4) {
+        unsafe { gl::UniformMatrix4fv(self.uniform_location(name), 1, gl::TRUE, value.ptr()) }
+    }
+
+    pub fn set_mat4_array(&self, name: &str, values: &[Mat4]) {
+        unsafe {
+            gl::UniformMatrix4fv(
+                self.uniform_location(name),
+                values.len() as GLint,
+                gl::TRUE,
+                values.as_ptr() as *const f32,
+            )
+        }
+    }
+
+    // Uniforms the linker kept, arrays are listed once without their "[0]" suffix
+    pub fn active_uniforms(&self) -> Vec<UniformInfo> {
+        let mut count: GLint = 0;
+        let mut max_length: GLint = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+        }
+
+        let mut uniforms = Vec::with_capacity(count.max(0) as usize);
+        let mut buffer = vec![0u8; max_length.max(1) as usize];
+        for index in 0..count.max(0) as GLuint {
+            let (mut length, mut size, mut kind) = (0, 0, 0);
+            unsafe {
+                gl::GetActiveUniform(
+                    self.id,
+                    index,
+                    buffer.len() as GLint,
+                    &mut length,
+                    &mut size,
+                    &mut kind,
+                    buffer.as_mut_ptr() as *mut GLchar,
+                );
+            }
+            let name = String::from_utf8_lossy(&buffer[..length.max(0) as usize]);
+            let name = name.strip_suffix("[0]").unwrap_or(&name).to_string();
+            let location = self.uniform_location(&name);
+            uniforms.push(UniformInfo {
+                name,
+                kind,
+                size,
+                location,
+            });
+        }
+        uniforms
+    }
+
     pub fn id(&self) -> u32 {
         self.id
     }
 }
 
+// An active uniform of a linked program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformInfo {
+    pub name: String,
+    // GL type, e.g. gl::FLOAT_VEC3 or gl::SAMPLER_2D
+    pub kind: GLenum,
+    // Number of elements, 1 unless the uniform is an array
+    pub size: GLint,
+    pub location: GLint,
+}
+
 impl Drop for Program {
     fn drop(&mut self) {
+        gl_state::forget_program(self.id);
         unsafe {
             gl::DeleteProgram(self.id);
         }
@@ -148,7 +312,6 @@ pub fn create_program() -> Result<Program, &'static str> {
     Ok(shader_program)
 }
 
-
 fn check_gl_error() {
     unsafe {
         let error = gl::GetError();
@@ -157,4 +320,4 @@ fn check_gl_error() {
             panic!("OpenGL error occurred!");
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,543 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use gl::types::{GLint, GLuint};
+
+use crate::core::custom_error::UbiError;
+use crate::core::ecs::transform::GlobalTransform;
+use crate::core::ecs::world::World;
+use crate::core::math::bounds::Frustum;
+use crate::core::math::transform::Mat4;
+use crate::core::math::vector::{add, cross, distance, normalize, scale, sub};
+
+use super::camera::{Camera, Projection};
+use super::culling::{is_visible, CullingStats};
+use super::framebuffer::{Framebuffer, SavedTarget};
+use super::instancing::{draw_instanced, InstanceBuffer, MeshRenderer};
+use super::light::{GatheredLight, LightKind};
+use super::mesh::Mesh;
+use super::pipeline::{DepthState, PipelineState};
+use super::shader::Program;
+use super::texture::{Texture, TextureFormat};
+
+// Must match MAX_CASCADES and MAX_SHADOW_VIEWS in lit.frag and pbr.frag
+pub const MAX_CASCADES: usize = 4;
+pub const MAX_SHADOW_VIEWS: usize = 16;
+
+// Texture unit of the shadow atlas, between the material units and the IBL maps
+pub const SHADOW_ATLAS_UNIT: u32 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    // Side of the square depth atlas in texels
+    pub atlas_size: u32,
+    // Side of one shadow view in the atlas
+    pub tile_size: u32,
+    // Cascades of the directional light, 1 to MAX_CASCADES
+    pub cascade_count: usize,
+    // Distance from the camera the cascades cover, nothing is shadowed past it
+    pub shadow_distance: f32,
+    // 0 splits the cascades evenly, 1 logarithmically
+    pub split_lambda: f32,
+    // Constant offset of the compared depth, in atlas depth units
+    pub depth_bias: f32,
+    // Offset along the surface normal, in shadow texels
+    pub normal_bias: f32,
+    // Polygon offset factor of the depth pass, grows the bias on slopes
+    pub slope_bias: f32,
+    // PCF takes (2 * pcf_radius + 1)^2 samples, 0 for hard shadows
+    pub pcf_radius: i32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            atlas_size: 4096,
+            tile_size: 1024,
+            cascade_count: 4,
+            shadow_distance: 50.0,
+            split_lambda: 0.75,
+            depth_bias: 0.0005,
+            normal_bias: 1.5,
+            slope_bias: 1.5,
+            pcf_radius: 1,
+        }
+    }
+}
+
+impl ShadowSettings {
+    // Number of views the atlas holds
+    pub fn capacity(&self) -> usize {
+        let per_row = (self.atlas_size / self.tile_size.max(1)) as usize;
+        (per_row * per_row).min(MAX_SHADOW_VIEWS)
+    }
+
+    fn validate(&self) -> Result<(), UbiError> {
+        if self.tile_size == 0
+            || self.atlas_size < self.tile_size
+            || self.atlas_size % self.tile_size != 0
+        {
+            return Err(UbiError::Other(format!(
+                "shadow atlas of {} texels can not be split into tiles of {}",
+                self.atlas_size, self.tile_size
+            )));
+        }
+        if !(1..=MAX_CASCADES).contains(&self.cascade_count) {
+            return Err(UbiError::Other(format!(
+                "{} shadow cascades, expected 1 to {}",
+                self.cascade_count, MAX_CASCADES
+            )));
+        }
+        Ok(())
+    }
+}
+
+// One depth rendering of the scene in the atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowView {
+    // Index of the light in the gathered lights
+    pub light: usize,
+    pub view_projection: Mat4,
+    // Texels of the atlas the view renders to: x, y, width, height
+    pub viewport: [u32; 4],
+    // World size of a texel: constant part and growth per unit of distance to the light
+    pub texel: [f32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CascadeInfo {
+    // Distances from the camera along its view direction
+    pub near: f32,
+    pub far: f32,
+    // Radius of the sphere around the frustum slice
+    pub radius: f32,
+    pub viewport: [u32; 4],
+}
+
+// What the last render produced, shared with the `EguiLayer` debug view.
+// `show_cascades` goes the other way and tints the lit shaders by cascade.
+#[derive(Debug, Clone, Default)]
+pub struct ShadowDebug {
+    pub atlas: GLuint,
+    pub atlas_size: u32,
+    pub cascades: Vec<CascadeInfo>,
+    pub spot_views: usize,
+    pub show_cascades: bool,
+}
+
+// Renders the depth of every `MeshRenderer` into a shadow atlas: cascades fitted to the camera
+// frustum for the first directional light casting shadows, then one view per spot light casting
+// shadows, as long as the atlas has room.
+pub struct ShadowPass {
+    settings: ShadowSettings,
+    atlas: Texture,
+    framebuffer: Framebuffer,
+    program: Program,
+    instances: InstanceBuffer,
+    views: Vec<ShadowView>,
+    stats: CullingStats,
+    cascade_splits: [f32; MAX_CASCADES],
+    cascade_count: usize,
+    camera_forward: [f32; 3],
+    debug: Rc<RefCell<ShadowDebug>>,
+}
+
+impl ShadowPass {
+    pub fn new(settings: ShadowSettings) -> Result<Self, UbiError> {
+        settings.validate()?;
+        let program = Program::from_sources(
+            include_str!("../../../assets/shaders/shadow.vert"),
+            include_str!("../../../assets/shaders/shadow.frag"),
+        )?;
+        let (atlas, framebuffer) = create_atlas(settings.atlas_size)?;
+        Ok(Self {
+            settings,
+            atlas,
+            framebuffer,
+            program,
+            instances: InstanceBuffer::transforms(),
+            views: Vec::new(),
+            stats: CullingStats::default(),
+            cascade_splits: [0.0; MAX_CASCADES],
+            cascade_count: 0,
+            camera_forward: [0.0, 0.0, -1.0],
+            debug: Rc::new(RefCell::new(ShadowDebug::default())),
+        })
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    // Applies from the next render, a new atlas size reallocates the atlas
+    pub fn set_settings(&mut self, settings: ShadowSettings) -> Result<(), UbiError> {
+        settings.validate()?;
+        if settings.atlas_size != self.settings.atlas_size {
+            let (atlas, framebuffer) = create_atlas(settings.atlas_size)?;
+            self.atlas = atlas;
+            self.framebuffer = framebuffer;
+        }
+        self.settings = settings;
+        Ok(())
+    }
+
+    pub fn atlas(&self) -> &Texture {
+        &self.atlas
+    }
+
+    // Views of the last render, the cascades come first
+    pub fn views(&self) -> &[ShadowView] {
+        &self.views
+    }
+
+    // Casters tested against every view of the last render
+    pub fn stats(&self) -> CullingStats {
+        self.stats
+    }
+
+    // Handle for `EguiLayer::show_shadow_debug`
+    pub fn debug(&self) -> Rc<RefCell<ShadowDebug>> {
+        self.debug.clone()
+    }
+
+    // Give the lights casting shadows their views, written to `GatheredLight::shadow`,
+    // and render the atlas
+    pub fn render(&mut self, world: &World, camera: &Camera, lights: &mut [GatheredLight]) {
+        self.stats = CullingStats::default();
+        self.assign_views(camera, lights);
+        if self.views.is_empty() {
+            return;
+        }
+
+        // Casters grouped by mesh, then instanced per view with those inside its frustum
+        let mut meshes: Vec<(Rc<Mesh>, Vec<Mat4>)> = Vec::new();
+        let mut lookup: HashMap<*const Mesh, usize> = HashMap::new();
+        for (_, renderer, transform) in world.query2::<MeshRenderer, GlobalTransform>() {
+            let index = *lookup.entry(Rc::as_ptr(&renderer.mesh)).or_insert_with(|| {
+                meshes.push((renderer.mesh.clone(), Vec::new()));
+                meshes.len() - 1
+            });
+            meshes[index].1.push(transform.0);
+        }
+        let frustums: Vec<Frustum> = self
+            .views
+            .iter()
+            .map(|view| Frustum::from_matrix(&view.view_projection))
+            .collect();
+
+        let saved = SavedTarget::save();
+        let size = self.settings.atlas_size as GLint;
+        self.framebuffer.bind();
+        self.program.set();
+        PipelineState::OPAQUE
+            .with_depth(DepthState {
+                bias: Some((self.settings.slope_bias, 1.0)),
+                ..DepthState::READ_WRITE
+            })
+            .apply();
+        unsafe {
+            gl::Viewport(0, 0, size, size);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+        let mut visible = Vec::new();
+        for (mesh, transforms) in &meshes {
+            for (view, frustum) in self.views.iter().zip(&frustums) {
+                visible.clear();
+                for transform in transforms {
+                    if self.stats.record(is_visible(frustum, mesh, transform)) {
+                        visible.extend_from_slice(transform.as_array());
+                    }
+                }
+                if visible.is_empty() {
+                    continue;
+                }
+                self.instances.set(&visible);
+                let [x, y, width, height] = view.viewport.map(|v| v as GLint);
+                unsafe {
+                    gl::Viewport(x, y, width, height);
+                }
+                self.program
+                    .set_mat4("u_view_projection", &view.view_projection);
+                draw_instanced(mesh, &self.instances, self.instances.len());
+            }
+        }
+        saved.restore();
+    }
+
+    fn assign_views(&mut self, camera: &Camera, lights: &mut [GatheredLight]) {
+        self.views.clear();
+        self.cascade_count = 0;
+        self.camera_forward = normalize(sub(camera.target, camera.position));
+        let capacity = self.settings.capacity();
+        let tile = self.settings.tile_size as f32;
+        let mut cascades = Vec::new();
+
+        // The shaders have a single set of splits, so only one directional light gets cascades
+        if let Some(index) = lights
+            .iter()
+            .position(|light| light.kind == LightKind::Directional && light.casts_shadows)
+        {
+            let count = self.settings.cascade_count.min(capacity);
+            let near = camera.near().max(0.01);
+            let far = camera
+                .far()
+                .min(self.settings.shadow_distance)
+                .max(near * 2.0);
+            self.cascade_splits = cascade_splits(near, far, count, self.settings.split_lambda);
+            lights[index].shadow = 0;
+
+            let mut slice_near = near;
+            for cascade in 0..count {
+                let slice_far = self.cascade_splits[cascade];
+                let (view_projection, radius) = fit_cascade(
+                    camera,
+                    self.camera_forward,
+                    lights[index].direction,
+                    [slice_near, slice_far],
+                    &self.settings,
+                );
+                self.push_view(index, view_projection, [2.0 * radius / tile, 0.0]);
+                cascades.push(CascadeInfo {
+                    near: slice_near,
+                    far: slice_far,
+                    radius,
+                    viewport: self.views[cascade].viewport,
+                });
+                slice_near = slice_far;
+            }
+            self.cascade_count = count;
+        }
+
+        for (index, light) in lights.iter_mut().enumerate() {
+            if light.kind != LightKind::Spot || !light.casts_shadows {
+                continue;
+            }
+            if self.views.len() >= capacity {
+                break;
+            }
+            light.shadow = self.views.len() as i32;
+            // Past 85 degrees the perspective wastes most of the texels
+            let outer = light.cone[1].clamp(-1.0, 1.0).acos().clamp(0.01, 1.48);
+            let mut projection = Mat4::new();
+            let near = (light.range * 0.01).max(0.05);
+            projection.perspective(2.0 * outer, 1.0, near, light.range.max(near * 2.0));
+            let mut view_projection = look_along(light.position, light.direction);
+            view_projection.mult(projection);
+            self.push_view(index, view_projection, [0.0, 2.0 * outer.tan() / tile]);
+        }
+
+        let mut debug = self.debug.borrow_mut();
+        debug.atlas = self.atlas.id;
+        debug.atlas_size = self.settings.atlas_size;
+        debug.spot_views = self.views.len() - cascades.len();
+        debug.cascades = cascades;
+    }
+
+    fn push_view(&mut self, light: usize, view_projection: Mat4, texel: [f32; 2]) {
+        let tile = self.settings.tile_size;
+        let per_row = (self.settings.atlas_size / tile) as usize;
+        let index = self.views.len();
+        self.views.push(ShadowView {
+            light,
+            view_projection,
+            viewport: [
+                (index % per_row) as u32 * tile,
+                (index / per_row) as u32 * tile,
+                tile,
+                tile,
+            ],
+            texel,
+        });
+    }
+}
+
+// Upload the shadow atlas and views to a program in use, `None` turns shadows off
+pub fn bind_shadows(program: &Program, shadows: Option<&ShadowPass>) {
+    program.set_int("u_shadow_atlas", SHADOW_ATLAS_UNIT as i32);
+    let shadows = match shadows {
+        Some(shadows) => shadows,
+        None => {
+            program.set_int("u_cascade_count", 0);
+            program.set_int("u_shadow_show_cascades", 0);
+            return;
+        }
+    };
+    shadows.atlas.bind_unit(SHADOW_ATLAS_UNIT);
+
+    let size = shadows.settings.atlas_size as f32;
+    if !shadows.views.is_empty() {
+        let matrices: Vec<Mat4> = shadows
+            .views
+            .iter()
+            .map(|view| atlas_matrix(view, size))
+            .collect();
+        // Texel centers at the edges, so PCF never reads a neighbouring view
+        let tiles: Vec<[f32; 4]> = shadows
+            .views
+            .iter()
+            .map(|view| {
+                let [x, y, width, height] = view.viewport.map(|v| v as f32);
+                [
+                    (x + 0.5) / size,
+                    (y + 0.5) / size,
+                    (x + width - 0.5) / size,
+                    (y + height - 0.5) / size,
+                ]
+            })
+            .collect();
+        let texels: Vec<[f32; 2]> = shadows.views.iter().map(|view| view.texel).collect();
+        program.set_mat4_array("u_shadow_matrices", &matrices);
+        program.set_vec4_array("u_shadow_tiles", &tiles);
+        program.set_vec2_array("u_shadow_texels", &texels);
+    }
+    program.set_vec4("u_cascade_splits", shadows.cascade_splits);
+    program.set_int("u_cascade_count", shadows.cascade_count as i32);
+    program.set_vec3("u_camera_forward", shadows.camera_forward);
+    program.set_float("u_shadow_bias", shadows.settings.depth_bias);
+    program.set_float("u_shadow_normal_bias", shadows.settings.normal_bias);
+    program.set_int("u_pcf_radius", shadows.settings.pcf_radius.max(0));
+    program.set_int(
+        "u_shadow_show_cascades",
+        shadows.debug.borrow().show_cascades as i32,
+    );
+}
+
+fn create_atlas(size: u32) -> Result<(Texture, Framebuffer), UbiError> {
+    let atlas = Texture::with_format(size, size, TextureFormat::Depth32F)?;
+    atlas.bind();
+    unsafe {
+        // PCF compares single texels, the swizzle shows depth as gray in the debug view
+        for (param, value) in [
+            (gl::TEXTURE_MIN_FILTER, gl::NEAREST),
+            (gl::TEXTURE_MAG_FILTER, gl::NEAREST),
+            (gl::TEXTURE_SWIZZLE_G, gl::RED),
+            (gl::TEXTURE_SWIZZLE_B, gl::RED),
+            (gl::TEXTURE_SWIZZLE_A, gl::ONE),
+        ] {
+            gl::TexParameteri(gl::TEXTURE_2D, param, value as GLint);
+        }
+    }
+    atlas.unbind();
+
+    let framebuffer = Framebuffer::gen();
+    framebuffer.attach_depth(gl::TEXTURE_2D, atlas.id, 0);
+    framebuffer.set_draw_buffers(0);
+    let status = framebuffer.check();
+    framebuffer.unbind();
+    status?;
+    Ok((atlas, framebuffer))
+}
+
+// Far distance of each cascade, a blend of logarithmic and even splits
+fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> [f32; MAX_CASCADES] {
+    let mut splits = [far; MAX_CASCADES];
+    for (cascade, split) in splits.iter_mut().enumerate().take(count) {
+        let t = (cascade + 1) as f32 / count as f32;
+        let logarithmic = near * (far / near).powf(t);
+        let even = near + (far - near) * t;
+        *split = lambda * logarithmic + (1.0 - lambda) * even;
+    }
+    splits
+}
+
+// View matrix at `eye` looking along `direction`
+fn look_along(eye: [f32; 3], direction: [f32; 3]) -> Mat4 {
+    let up = if direction[1].abs() > 0.99 {
+        [0.0, 0.0, 1.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    let target = add(eye, direction);
+    let mut view = Mat4::new();
+    view.lookat(
+        eye[0], eye[1], eye[2], target[0], target[1], target[2], up[0], up[1], up[2],
+    );
+    view
+}
+
+// Light view projection covering the camera frustum between the distances of `slice`.
+// Bounding the slice with a sphere keeps the projection size constant while the camera turns,
+// and moving it in whole texels keeps the shadow edges from shimmering.
+fn fit_cascade(
+    camera: &Camera,
+    forward: [f32; 3],
+    direction: [f32; 3],
+    slice: [f32; 2],
+    settings: &ShadowSettings,
+) -> (Mat4, f32) {
+    let right = normalize(cross(forward, camera.up));
+    let up = cross(right, forward);
+    let mut corners = Vec::with_capacity(8);
+    for depth in slice {
+        let (half_width, half_height) = match camera.projection {
+            Projection::Perspective { fov_y, .. } => {
+                let half_height = depth * (fov_y * 0.5).tan();
+                (half_height * camera.aspect, half_height)
+            }
+            Projection::Orthographic { height, .. } => (height * camera.aspect * 0.5, height * 0.5),
+        };
+        let center = add(camera.position, scale(forward, depth));
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let offset = add(scale(right, half_width * x), scale(up, half_height * y));
+            corners.push(add(center, offset));
+        }
+    }
+    let center = scale(
+        corners
+            .iter()
+            .fold([0.0; 3], |sum, corner| add(sum, *corner)),
+        1.0 / corners.len() as f32,
+    );
+    let radius = corners
+        .iter()
+        .map(|corner| distance(*corner, center))
+        .fold(0.0, f32::max);
+    // Steps of 1/16 so rounding errors do not resize the texels every frame
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let light_view = look_along([0.0; 3], direction);
+    let center = light_view.transform_point(center);
+    let texel = 2.0 * radius / settings.tile_size as f32;
+    let x = (center[0] / texel).floor() * texel;
+    let y = (center[1] / texel).floor() * texel;
+    // The view looks down -z, casters up to `shadow_distance` in front of the sphere still count
+    let mut projection = Mat4::new();
+    projection.orthographic(
+        x - radius,
+        x + radius,
+        y - radius,
+        y + radius,
+        -center[2] - radius - settings.shadow_distance,
+        -center[2] + radius,
+    );
+    let mut view_projection = light_view;
+    view_projection.mult(projection);
+    (view_projection, radius)
+}
+
+// World space to atlas texture coordinates and depth of `view`
+fn atlas_matrix(view: &ShadowView, atlas_size: f32) -> Mat4 {
+    let [x, y, width, height] = view.viewport.map(|v| v as f32);
+    let (scale_x, scale_y) = (width / atlas_size * 0.5, height / atlas_size * 0.5);
+    let mut matrix = view.view_projection;
+    matrix.mult(Mat4::from_rows([
+        scale_x,
+        0.0,
+        0.0,
+        scale_x + x / atlas_size,
+        0.0,
+        scale_y,
+        0.0,
+        scale_y + y / atlas_size,
+        0.0,
+        0.0,
+        0.5,
+        0.5,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    ]));
+    matrix
+}
@@ -0,0 +1,141 @@
+use super::sprite_batch::Sprite;
+use super::sprite_sheet::{PlaybackMode, SpriteFrame, SpriteSheet};
+
+// Plays the clips of a `SpriteSheet`, one player per animated sprite
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    clip: Option<String>,
+    // Position inside the clip, not the sheet frame index
+    position: usize,
+    elapsed: f32,
+    forward: bool,
+    playing: bool,
+    pub speed: f32,
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self {
+            clip: None,
+            position: 0,
+            elapsed: 0.0,
+            forward: true,
+            playing: false,
+            speed: 1.0,
+        }
+    }
+
+    // Start `clip` from its first frame, does nothing if it is already playing
+    pub fn play(&mut self, clip: &str) {
+        if self.playing && self.clip.as_deref() == Some(clip) {
+            return;
+        }
+        self.clip = Some(clip.to_string());
+        self.restart();
+    }
+
+    pub fn restart(&mut self) {
+        self.position = 0;
+        self.elapsed = 0.0;
+        self.forward = true;
+        self.playing = self.clip.is_some();
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.playing = self.clip.is_some();
+    }
+
+    pub fn clip(&self) -> Option<&str> {
+        self.clip.as_deref()
+    }
+
+    // False once a `PlaybackMode::Once` clip reached its last frame
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    // Advance by `delta` seconds using the frame durations of `sheet`
+    pub fn update(&mut self, delta: f32, sheet: &SpriteSheet) {
+        if !self.playing {
+            return;
+        }
+        let Some(clip) = self.clip.as_deref().and_then(|name| sheet.clip(name)) else {
+            return;
+        };
+        if clip.frames.is_empty() {
+            return;
+        }
+        // The clip may have changed in the sheet since the position was set
+        if self.position >= clip.frames.len() {
+            self.position = clip.frames.len() - 1;
+        }
+
+        self.elapsed += delta * self.speed;
+        loop {
+            let duration = sheet
+                .frame(clip.frames[self.position])
+                .map(|frame| frame.duration)
+                .unwrap_or(0.0);
+            // Zero length frames would never let the loop end
+            if duration <= 0.0 || self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+
+            let last = clip.frames.len() - 1;
+            match clip.mode {
+                PlaybackMode::Loop => self.position = (self.position + 1) % clip.frames.len(),
+                PlaybackMode::Once => {
+                    if self.position == last {
+                        self.playing = false;
+                        self.elapsed = 0.0;
+                        break;
+                    }
+                    self.position += 1;
+                }
+                PlaybackMode::PingPong => {
+                    if last == 0 {
+                        continue;
+                    }
+                    if self.forward && self.position == last {
+                        self.forward = false;
+                    } else if !self.forward && self.position == 0 {
+                        self.forward = true;
+                    }
+                    if self.forward {
+                        self.position += 1;
+                    } else {
+                        self.position -= 1;
+                    }
+                }
+            }
+        }
+    }
+
+    // Index of the displayed frame in `sheet`
+    pub fn frame_index(&self, sheet: &SpriteSheet) -> Option<usize> {
+        let clip = sheet.clip(self.clip.as_deref()?)?;
+        clip.frames.get(self.position).copied()
+    }
+
+    pub fn current_frame<'a>(&self, sheet: &'a SpriteSheet) -> Option<&'a SpriteFrame> {
+        sheet.frame(self.frame_index(sheet)?)
+    }
+
+    // Point the uv rect of `sprite` at the displayed frame
+    pub fn apply(&self, sheet: &SpriteSheet, sprite: &mut Sprite) {
+        if let Some(frame) = self.current_frame(sheet) {
+            sprite.uv_rect = frame.uv_rect;
+        }
+    }
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
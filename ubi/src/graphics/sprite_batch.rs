@@ -0,0 +1,314 @@
+use gl::types::{GLint, GLuint};
+
+use crate::core::custom_error::UbiError;
+use crate::core::math::transform::Mat4;
+
+use super::buffer::{Ibo, Vao, Vbo, VertexLayout};
+use super::gl_state;
+use super::pipeline::PipelineState;
+use super::shader::Program;
+use super::texture::Texture;
+
+// Sprites drawn by a single draw call at most
+pub const MAX_SPRITES: usize = 10_000;
+// Must match the size of u_textures in sprite.frag
+pub const MAX_TEXTURE_SLOTS: usize = 16;
+
+// position(3) + uv(2) + color(4) + texture slot(1)
+const FLOATS_PER_VERTEX: usize = 10;
+const VERTICES_PER_SPRITE: usize = 4;
+const INDICES_PER_SPRITE: usize = 6;
+
+// A textured quad, `position` is where the `origin` of the quad ends up
+#[derive(Debug, Clone, Copy)]
+pub struct Sprite {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    // Counter-clockwise rotation in radians around `origin`
+    pub rotation: f32,
+    // Pivot relative to the quad, (0.0, 0.0) is bottom left and (1.0, 1.0) top right
+    pub origin: [f32; 2],
+    // Normalized texture region as (u, v, width, height)
+    pub uv_rect: [f32; 4],
+    pub tint: [f32; 4],
+    // Sprites with a lower depth are drawn behind those with a higher one
+    pub depth: f32,
+}
+
+impl Sprite {
+    pub fn new(position: [f32; 2], size: [f32; 2]) -> Self {
+        Self {
+            position,
+            size,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            size: [1.0, 1.0],
+            rotation: 0.0,
+            origin: [0.5, 0.5],
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            tint: [1.0, 1.0, 1.0, 1.0],
+            depth: 0.0,
+        }
+    }
+}
+
+// Order in which queued sprites are flushed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpriteSortMode {
+    // Submission order
+    Deferred,
+    // Group by texture first, then by depth, fewest texture switches
+    Texture,
+    // Lowest depth first, then by texture, needed for correct alpha blending
+    #[default]
+    BackToFront,
+    // Highest depth first, then by texture
+    FrontToBack,
+}
+
+// Counters of the last begin/end pair
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    pub sprites: u32,
+    pub draw_calls: u32,
+    pub texture_binds: u32,
+}
+
+struct QueuedSprite {
+    texture: GLuint,
+    sprite: Sprite,
+}
+
+pub struct SpriteBatch {
+    program: Program,
+    vao: Vao,
+    vbo: Vbo,
+    _ibo: Ibo,
+    projection: Mat4,
+    queue: Vec<QueuedSprite>,
+    vertices: Vec<f32>,
+    slots: Vec<GLuint>,
+    max_slots: usize,
+    sort_mode: SpriteSortMode,
+    pipeline: PipelineState,
+    stats: BatchStats,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Result<Self, UbiError> {
+        let program = Program::from_sources(
+            include_str!("../../../assets/shaders/sprite.vert"),
+            include_str!("../../../assets/shaders/sprite.frag"),
+        )?;
+
+        let layout = VertexLayout::new().with(0, 3).with(1, 2).with(2, 4).with(3, 1);
+        debug_assert_eq!(layout.floats_per_vertex(), FLOATS_PER_VERTEX);
+
+        let vao = Vao::gen();
+        let vbo = Vbo::gen();
+        let ibo = Ibo::gen();
+        vao.bind();
+        vbo.allocate(MAX_SPRITES * VERTICES_PER_SPRITE * layout.stride());
+        vao.set_layout(&layout);
+        ibo.set(&quad_indices(MAX_SPRITES));
+        vao.unbind();
+
+        let mut max_units: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_IMAGE_UNITS, &mut max_units);
+        }
+        let max_slots = (max_units.max(1) as usize).min(MAX_TEXTURE_SLOTS);
+
+        let units: Vec<i32> = (0..MAX_TEXTURE_SLOTS as i32).collect();
+        program.set();
+        program.set_int_array("u_textures", &units);
+
+        Ok(Self {
+            program,
+            vao,
+            vbo,
+            _ibo: ibo,
+            projection: Mat4::new(),
+            queue: Vec::new(),
+            vertices: Vec::with_capacity(MAX_SPRITES * VERTICES_PER_SPRITE * FLOATS_PER_VERTEX),
+            slots: Vec::with_capacity(max_slots),
+            max_slots,
+            sort_mode: SpriteSortMode::default(),
+            pipeline: PipelineState::OVERLAY,
+            stats: BatchStats::default(),
+        })
+    }
+
+    pub fn set_sort_mode(&mut self, sort_mode: SpriteSortMode) {
+        self.sort_mode = sort_mode;
+    }
+
+    pub fn sort_mode(&self) -> SpriteSortMode {
+        self.sort_mode
+    }
+
+    // `PipelineState::OVERLAY` by default, e.g. a depth tested one for sprites in a scene
+    pub fn set_pipeline(&mut self, pipeline: PipelineState) {
+        self.pipeline = pipeline;
+    }
+
+    pub fn pipeline(&self) -> PipelineState {
+        self.pipeline
+    }
+
+    // Statistics of the last finished batch
+    pub fn stats(&self) -> BatchStats {
+        self.stats
+    }
+
+    // Start collecting sprites that will be drawn with `projection`
+    pub fn begin(&mut self, projection: Mat4) {
+        self.projection = projection;
+        self.queue.clear();
+        self.stats = BatchStats::default();
+    }
+
+    pub fn draw(&mut self, texture: &Texture, sprite: &Sprite) {
+        self.queue.push(QueuedSprite {
+            texture: texture.id,
+            sprite: *sprite,
+        });
+    }
+
+    // Sort everything queued since `begin` and submit it in as few draw calls as possible
+    pub fn end(&mut self) {
+        if self.queue.is_empty() {
+            return;
+        }
+
+        match self.sort_mode {
+            SpriteSortMode::Deferred => {}
+            SpriteSortMode::Texture => self.queue.sort_by(|a, b| {
+                a.texture
+                    .cmp(&b.texture)
+                    .then(a.sprite.depth.total_cmp(&b.sprite.depth))
+            }),
+            SpriteSortMode::BackToFront => self.queue.sort_by(|a, b| {
+                a.sprite
+                    .depth
+                    .total_cmp(&b.sprite.depth)
+                    .then(a.texture.cmp(&b.texture))
+            }),
+            SpriteSortMode::FrontToBack => self.queue.sort_by(|a, b| {
+                b.sprite
+                    .depth
+                    .total_cmp(&a.sprite.depth)
+                    .then(a.texture.cmp(&b.texture))
+            }),
+        }
+
+        self.pipeline.apply();
+        self.program.set();
+        self.program.set_mat4("u_projection", &self.projection);
+        self.vao.bind();
+
+        let queue = std::mem::take(&mut self.queue);
+        self.vertices.clear();
+        self.slots.clear();
+        for queued in &queue {
+            if self.sprite_count() == MAX_SPRITES {
+                self.flush();
+            }
+
+            let slot = match self.slots.iter().position(|&id| id == queued.texture) {
+                Some(slot) => slot,
+                None => {
+                    if self.slots.len() == self.max_slots {
+                        self.flush();
+                    }
+                    self.slots.push(queued.texture);
+                    self.slots.len() - 1
+                }
+            };
+
+            push_sprite(&mut self.vertices, &queued.sprite, slot as f32);
+            self.stats.sprites += 1;
+        }
+        self.flush();
+        // Keep the allocation for the next frame
+        self.queue = queue;
+        self.queue.clear();
+
+        self.vao.unbind();
+    }
+
+    fn sprite_count(&self) -> usize {
+        self.vertices.len() / (FLOATS_PER_VERTEX * VERTICES_PER_SPRITE)
+    }
+
+    // Draw the pending vertices with the textures currently in the slots
+    fn flush(&mut self) {
+        let count = self.sprite_count();
+        if count > 0 {
+            self.vbo.sub_data(0, &self.vertices);
+            for (unit, &id) in self.slots.iter().enumerate() {
+                gl_state::active_texture(unit as u32);
+                gl_state::bind_texture(gl::TEXTURE_2D, id);
+            }
+            unsafe {
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    (count * INDICES_PER_SPRITE) as i32,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                );
+                gl_state::active_texture(0);
+            }
+            self.stats.draw_calls += 1;
+            self.stats.texture_binds += self.slots.len() as u32;
+        }
+        self.vertices.clear();
+        self.slots.clear();
+    }
+}
+
+// Append the four corners of `sprite` counter-clockwise from the bottom left
+fn push_sprite(vertices: &mut Vec<f32>, sprite: &Sprite, slot: f32) {
+    let (sin, cos) = sprite.rotation.sin_cos();
+    let [u, v, uv_width, uv_height] = sprite.uv_rect;
+    let corners = [
+        (0.0, 0.0, u, v),
+        (1.0, 0.0, u + uv_width, v),
+        (1.0, 1.0, u + uv_width, v + uv_height),
+        (0.0, 1.0, u, v + uv_height),
+    ];
+
+    for (cx, cy, tu, tv) in corners {
+        let local_x = (cx - sprite.origin[0]) * sprite.size[0];
+        let local_y = (cy - sprite.origin[1]) * sprite.size[1];
+        vertices.extend_from_slice(&[
+            sprite.position[0] + local_x * cos - local_y * sin,
+            sprite.position[1] + local_x * sin + local_y * cos,
+            sprite.depth,
+            tu,
+            tv,
+            sprite.tint[0],
+            sprite.tint[1],
+            sprite.tint[2],
+            sprite.tint[3],
+            slot,
+        ]);
+    }
+}
+
+// Two triangles per quad sharing the diagonal
+fn quad_indices(quads: usize) -> Vec<u32> {
+    let mut indices = Vec::with_capacity(quads * INDICES_PER_SPRITE);
+    for quad in 0..quads as u32 {
+        let base = quad * VERTICES_PER_SPRITE as u32;
+        indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+    }
+    indices
+}
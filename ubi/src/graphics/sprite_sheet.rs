@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::core::custom_error::UbiError;
+
+// Used for frames whose metadata has no duration
+pub const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+// A named rectangle of a sprite sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteFrame {
+    pub name: String,
+    // Pixel rectangle as (x, y, width, height), (0, 0) is the top left corner of the sheet
+    pub rect: [u32; 4],
+    // Normalized (u, v, width, height) ready to be used as `Sprite::uv_rect`
+    pub uv_rect: [f32; 4],
+    // Seconds the frame stays on screen when animated
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaybackMode {
+    #[default]
+    Loop,
+    Once,
+    // Forward then backward without repeating the end frames
+    PingPong,
+}
+
+// An ordered list of frames of a `SpriteSheet`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub frames: Vec<usize>,
+    pub mode: PlaybackMode,
+}
+
+impl AnimationClip {
+    pub fn new(frames: Vec<usize>, mode: PlaybackMode) -> Self {
+        Self { frames, mode }
+    }
+
+    // Frames `first..=last`, reversed when `first > last`
+    pub fn from_range(first: usize, last: usize, mode: PlaybackMode) -> Self {
+        let frames = if first <= last {
+            (first..=last).collect()
+        } else {
+            (last..=first).rev().collect()
+        };
+        Self { frames, mode }
+    }
+}
+
+// Frames and animation clips cut out of one texture
+#[derive(Debug, Clone, Default)]
+pub struct SpriteSheet {
+    width: u32,
+    height: u32,
+    frames: Vec<SpriteFrame>,
+    frame_names: HashMap<String, usize>,
+    clips: HashMap<String, AnimationClip>,
+}
+
+impl SpriteSheet {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    // Cut a sheet of `width`x`height` pixels into equally sized frames, row by row from the top.
+    // `margin` surrounds the whole grid and `spacing` separates the frames.
+    pub fn from_grid(
+        width: u32,
+        height: u32,
+        frame_width: u32,
+        frame_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> Result<Self, UbiError> {
+        if frame_width == 0 || frame_height == 0 {
+            return Err(UbiError::ResourceError(
+                "sprite sheet frames must not be empty".to_string(),
+            ));
+        }
+
+        let columns = (width.saturating_sub(2 * margin) + spacing) / (frame_width + spacing);
+        let rows = (height.saturating_sub(2 * margin) + spacing) / (frame_height + spacing);
+        let mut sheet = Self::new(width, height);
+        for row in 0..rows {
+            for column in 0..columns {
+                let rect = [
+                    margin + column * (frame_width + spacing),
+                    margin + row * (frame_height + spacing),
+                    frame_width,
+                    frame_height,
+                ];
+                let name = sheet.frames.len().to_string();
+                sheet.add_frame(&name, rect, DEFAULT_FRAME_DURATION);
+            }
+        }
+        Ok(sheet)
+    }
+
+    pub fn load_json(path: &str) -> Result<Self, UbiError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::from_json(&source).map_err(|e| match e {
+            UbiError::ResourceError(message) => {
+                UbiError::ResourceError(format!("{}: {}", path, message))
+            }
+            other => other,
+        })
+    }
+
+    // Parse the JSON exported by Aseprite or TexturePacker, both the hash and the array
+    // flavour. Aseprite frame tags and TexturePacker animations become clips.
+    pub fn from_json(source: &str) -> Result<Self, UbiError> {
+        let root: Value = serde_json::from_str(source)
+            .map_err(|e| UbiError::ResourceError(format!("invalid sprite sheet json: {}", e)))?;
+
+        let size = &root["meta"]["size"];
+        let mut sheet = Self::new(json_u32(size, "w")?, json_u32(size, "h")?);
+
+        let entries: Vec<(String, &Value)> = match &root["frames"] {
+            Value::Object(map) => map.iter().map(|(name, v)| (name.clone(), v)).collect(),
+            Value::Array(list) => list
+                .iter()
+                .map(|v| (v["filename"].as_str().unwrap_or_default().to_string(), v))
+                .collect(),
+            _ => return Err(resource_error("missing \"frames\"")),
+        };
+
+        for (name, entry) in entries {
+            if entry["rotated"].as_bool().unwrap_or(false) {
+                return Err(resource_error(&format!(
+                    "frame \"{}\" is rotated, export without rotation",
+                    name
+                )));
+            }
+            let frame = &entry["frame"];
+            let rect = [
+                json_u32(frame, "x")?,
+                json_u32(frame, "y")?,
+                json_u32(frame, "w")?,
+                json_u32(frame, "h")?,
+            ];
+            let duration = entry["duration"]
+                .as_f64()
+                .map(|ms| ms as f32 / 1000.0)
+                .unwrap_or(DEFAULT_FRAME_DURATION);
+            sheet.add_frame(&name, rect, duration);
+        }
+
+        // Aseprite
+        if let Some(tags) = root["meta"]["frameTags"].as_array() {
+            for tag in tags {
+                let name = tag["name"]
+                    .as_str()
+                    .ok_or_else(|| resource_error("frame tag without a name"))?;
+                let from = json_u32(tag, "from")? as usize;
+                let to = json_u32(tag, "to")? as usize;
+                if from >= sheet.frames.len() || to >= sheet.frames.len() {
+                    return Err(resource_error(&format!(
+                        "frame tag \"{}\" is out of range",
+                        name
+                    )));
+                }
+                let clip = match tag["direction"].as_str().unwrap_or("forward") {
+                    "reverse" => AnimationClip::from_range(to, from, PlaybackMode::Loop),
+                    "pingpong" => AnimationClip::from_range(from, to, PlaybackMode::PingPong),
+                    // Backward first, from the last frame of the tag
+                    "pingpong_reverse" => {
+                        AnimationClip::from_range(to, from, PlaybackMode::PingPong)
+                    }
+                    _ => AnimationClip::from_range(from, to, PlaybackMode::Loop),
+                };
+                sheet.add_clip(name, clip);
+            }
+        }
+
+        // TexturePacker
+        if let Some(animations) = root["animations"].as_object() {
+            for (name, frame_names) in animations {
+                let mut frames = Vec::new();
+                for frame_name in frame_names.as_array().into_iter().flatten() {
+                    let frame_name = frame_name.as_str().unwrap_or_default();
+                    let index = sheet.frame_index(frame_name).ok_or_else(|| {
+                        resource_error(&format!(
+                            "animation \"{}\" uses unknown frame \"{}\"",
+                            name, frame_name
+                        ))
+                    })?;
+                    frames.push(index);
+                }
+                sheet.add_clip(name, AnimationClip::new(frames, PlaybackMode::Loop));
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    // Add a frame and return its index
+    pub fn add_frame(&mut self, name: &str, rect: [u32; 4], duration: f32) -> usize {
+        let (width, height) = (self.width.max(1) as f32, self.height.max(1) as f32);
+        let [x, y, w, h] = rect;
+        // Textures are uploaded bottom row first, so v grows upwards
+        let uv_rect = [
+            x as f32 / width,
+            (self.height as f32 - (y + h) as f32) / height,
+            w as f32 / width,
+            h as f32 / height,
+        ];
+
+        let index = self.frames.len();
+        self.frames.push(SpriteFrame {
+            name: name.to_string(),
+            rect,
+            uv_rect,
+            duration,
+        });
+        self.frame_names.insert(name.to_string(), index);
+        index
+    }
+
+    pub fn add_clip(&mut self, name: &str, clip: AnimationClip) {
+        self.clips.insert(name.to_string(), clip);
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn frames(&self) -> &[SpriteFrame] {
+        &self.frames
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&SpriteFrame> {
+        self.frames.get(index)
+    }
+
+    pub fn frame_index(&self, name: &str) -> Option<usize> {
+        self.frame_names.get(name).copied()
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    pub fn clips(&self) -> &HashMap<String, AnimationClip> {
+        &self.clips
+    }
+}
+
+fn json_u32(value: &Value, key: &str) -> Result<u32, UbiError> {
+    value[key]
+        .as_u64()
+        .map(|v| v as u32)
+        .ok_or_else(|| resource_error(&format!("missing or invalid \"{}\"", key)))
+}
+
+fn resource_error(message: &str) -> UbiError {
+    UbiError::ResourceError(format!("sprite sheet: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_skips_margin_and_spacing() {
+        let sheet = SpriteSheet::from_grid(38, 22, 16, 8, 2, 2).unwrap();
+        let rects: Vec<[u32; 4]> = sheet.frames().iter().map(|f| f.rect).collect();
+        assert_eq!(
+            rects,
+            [
+                [2, 2, 16, 8],
+                [20, 2, 16, 8],
+                [2, 12, 16, 8],
+                [20, 12, 16, 8]
+            ]
+        );
+        assert_eq!(sheet.frame_index("3"), Some(3));
+        assert_eq!(sheet.frames()[0].uv_rect[1], 12.0 / 22.0);
+        assert!(SpriteSheet::from_grid(32, 32, 0, 8, 0, 0).is_err());
+    }
+
+    #[test]
+    fn aseprite_hash_with_tags() {
+        let source = r#"{
+            "frames": {
+                "run 0": {"frame": {"x": 0, "y": 0, "w": 16, "h": 16}, "duration": 50},
+                "run 1": {"frame": {"x": 16, "y": 0, "w": 16, "h": 16}, "duration": 150},
+                "run 2": {"frame": {"x": 32, "y": 0, "w": 16, "h": 16}}
+            },
+            "meta": {
+                "size": {"w": 64, "h": 32},
+                "frameTags": [
+                    {"name": "run", "from": 0, "to": 2, "direction": "forward"},
+                    {"name": "back", "from": 0, "to": 2, "direction": "reverse"},
+                    {"name": "swing", "from": 1, "to": 2, "direction": "pingpong"},
+                    {"name": "swing back", "from": 0, "to": 1, "direction": "pingpong_reverse"}
+                ]
+            }
+        }"#;
+        let sheet = SpriteSheet::from_json(source).unwrap();
+        assert_eq!(sheet.size(), (64, 32));
+        assert_eq!(sheet.frames().len(), 3);
+        let run_1 = sheet.frame(sheet.frame_index("run 1").unwrap()).unwrap();
+        assert_eq!(run_1.rect, [16, 0, 16, 16]);
+        assert_eq!(run_1.uv_rect, [0.25, 0.5, 0.25, 0.5]);
+        assert!((run_1.duration - 0.15).abs() < 1e-6);
+        let run_2 = sheet.frame(sheet.frame_index("run 2").unwrap()).unwrap();
+        assert_eq!(run_2.duration, DEFAULT_FRAME_DURATION);
+
+        let clip = |name| sheet.clip(name).unwrap().clone();
+        assert_eq!(
+            clip("run"),
+            AnimationClip::new(vec![0, 1, 2], PlaybackMode::Loop)
+        );
+        assert_eq!(
+            clip("back"),
+            AnimationClip::new(vec![2, 1, 0], PlaybackMode::Loop)
+        );
+        assert_eq!(
+            clip("swing"),
+            AnimationClip::new(vec![1, 2], PlaybackMode::PingPong)
+        );
+        assert_eq!(
+            clip("swing back"),
+            AnimationClip::new(vec![1, 0], PlaybackMode::PingPong)
+        );
+    }
+
+    #[test]
+    fn texture_packer_array_with_animations() {
+        let source = r#"{
+            "frames": [
+                {"filename": "idle_a", "frame": {"x": 0, "y": 0, "w": 8, "h": 8}},
+                {"filename": "idle_b", "frame": {"x": 8, "y": 0, "w": 8, "h": 8}}
+            ],
+            "animations": {"idle": ["idle_b", "idle_a"]},
+            "meta": {"size": {"w": 16, "h": 8}}
+        }"#;
+        let sheet = SpriteSheet::from_json(source).unwrap();
+        assert_eq!(sheet.frame_index("idle_b"), Some(1));
+        assert_eq!(sheet.clip("idle").unwrap().frames, [1, 0]);
+    }
+
+    #[test]
+    fn invalid_sheets_are_rejected() {
+        let sources = [
+            "[]",
+            r#"{"meta": {"size": {"w": 8, "h": 8}}}"#,
+            r#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "rotated": true}},
+                "meta": {"size": {"w": 8, "h": 8}}}"#,
+            r#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}},
+                "meta": {"size": {"w": 8, "h": 8}, "frameTags": [{"name": "t", "from": 0, "to": 1}]}}"#,
+            r#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}},
+                "animations": {"x": ["b"]}, "meta": {"size": {"w": 8, "h": 8}}}"#,
+        ];
+        for source in sources {
+            assert!(SpriteSheet::from_json(source).is_err(), "{}", source);
+        }
+    }
+}
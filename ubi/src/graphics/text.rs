@@ -0,0 +1,351 @@
+use crate::core::custom_error::UbiError;
+use crate::core::math::transform::Mat4;
+
+use super::buffer::{Ibo, Vao, Vbo, VertexLayout};
+use super::font::{Font, FontRenderMode};
+use super::pipeline::PipelineState;
+use super::shader::Program;
+
+// Glyphs drawn by a single draw call at most
+const MAX_GLYPHS: usize = 4096;
+// position(3) + uv(2) + color(4)
+const FLOATS_PER_VERTEX: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    // Font size in layout pixels
+    pub size: f32,
+    pub color: [f32; 4],
+    pub align: TextAlign,
+    // Lines are wrapped at word boundaries to fit, in layout pixels
+    pub max_width: Option<f32>,
+    // Multiplier of the font line height
+    pub line_spacing: f32,
+    // Units per layout pixel, 1.0 for screen space text
+    pub scale: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            align: TextAlign::Left,
+            max_width: None,
+            line_spacing: 1.0,
+            scale: 1.0,
+        }
+    }
+}
+
+// A glyph placed by `layout_text`, the pen position is on the baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutGlyph {
+    pub glyph: u16,
+    // Byte offset of the character in the source text
+    pub byte_index: usize,
+    pub x: f32,
+    // Relative to the top of the text block, grows upwards so lines go negative
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<LayoutGlyph>,
+    pub width: f32,
+    pub height: f32,
+    pub lines: usize,
+}
+
+#[derive(Default)]
+struct Line {
+    glyphs: Vec<LayoutGlyph>,
+    // Pen position after each glyph, parallel to `glyphs`
+    ends: Vec<f32>,
+}
+
+impl Line {
+    fn push(&mut self, glyph: LayoutGlyph, end: f32) {
+        self.glyphs.push(glyph);
+        self.ends.push(end);
+    }
+
+    // Trailing whitespace is never pushed so it does not count
+    fn width(&self) -> f32 {
+        self.ends.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+// Position the glyphs of `text` in lines, applying kerning, wrapping and alignment
+pub fn layout_text(font: &Font, text: &str, style: &TextStyle) -> TextLayout {
+    let size = style.size;
+    let mut lines = Vec::new();
+
+    for (paragraph_start, paragraph) in split_paragraphs(text) {
+        let mut line = Line::default();
+        let mut pen = 0.0;
+        let mut previous: Option<u16> = None;
+        // Index into line.glyphs and pen position right after the last whitespace
+        let mut break_at: Option<(usize, f32)> = None;
+
+        for (offset, c) in paragraph.char_indices() {
+            let glyph = font.glyph_id(c);
+
+            // Combining marks are drawn over the previous glyph without advancing
+            if is_combining_mark(c) {
+                let mark = LayoutGlyph {
+                    glyph,
+                    byte_index: paragraph_start + offset,
+                    x: pen,
+                    y: 0.0,
+                };
+                line.push(mark, pen);
+                continue;
+            }
+
+            if let Some(previous) = previous {
+                pen += font.kerning(previous, glyph, size);
+            }
+            let advance = font.advance(glyph, size);
+
+            if c.is_whitespace() {
+                pen += advance;
+                previous = Some(glyph);
+                break_at = Some((line.glyphs.len(), pen));
+                continue;
+            }
+
+            if let Some(max_width) = style.max_width {
+                if pen + advance > max_width && !line.glyphs.is_empty() {
+                    let (split, shift) = break_at.unwrap_or((line.glyphs.len(), pen));
+                    let mut next = Line {
+                        glyphs: line.glyphs.split_off(split),
+                        ends: line.ends.split_off(split),
+                    };
+                    for moved in &mut next.glyphs {
+                        moved.x -= shift;
+                    }
+                    for end in &mut next.ends {
+                        *end -= shift;
+                    }
+                    lines.push(line);
+                    line = next;
+                    pen -= shift;
+                    break_at = None;
+                }
+            }
+
+            let placed = LayoutGlyph {
+                glyph,
+                byte_index: paragraph_start + offset,
+                x: pen,
+                y: 0.0,
+            };
+            pen += advance;
+            line.push(placed, pen);
+            previous = Some(glyph);
+        }
+        lines.push(line);
+    }
+
+    let metrics = font.metrics(size);
+    let line_height = metrics.line_height() * style.line_spacing;
+    let widest = lines.iter().map(Line::width).fold(0.0, f32::max);
+    let block_width = style.max_width.unwrap_or(widest);
+
+    let mut layout = TextLayout {
+        glyphs: Vec::new(),
+        width: widest,
+        height: line_height * lines.len() as f32,
+        lines: lines.len(),
+    };
+    for (index, line) in lines.into_iter().enumerate() {
+        let offset = match style.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (block_width - line.width()) * 0.5,
+            TextAlign::Right => block_width - line.width(),
+        };
+        let baseline = -(metrics.ascent + index as f32 * line_height);
+        for mut glyph in line.glyphs {
+            glyph.x += offset;
+            glyph.y = baseline;
+            layout.glyphs.push(glyph);
+        }
+    }
+    layout
+}
+
+fn split_paragraphs(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut start = 0;
+    text.split('\n').map(move |paragraph| {
+        let item = (start, paragraph.strip_suffix('\r').unwrap_or(paragraph));
+        start += paragraph.len() + 1;
+        item
+    })
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F)
+}
+
+// Draws laid out text with the glyph atlas of a `Font`
+//
+// Use an orthographic projection in pixels for screen space text, or a camera
+// view projection together with `TextStyle::scale` for text in the world.
+pub struct TextRenderer {
+    program: Program,
+    vao: Vao,
+    vbo: Vbo,
+    _ibo: Ibo,
+    projection: Mat4,
+    vertices: Vec<f32>,
+    pipeline: PipelineState,
+    draw_calls: u32,
+}
+
+impl TextRenderer {
+    pub fn new() -> Result<Self, UbiError> {
+        let program = Program::from_sources(
+            include_str!("../../../assets/shaders/text.vert"),
+            include_str!("../../../assets/shaders/text.frag"),
+        )?;
+
+        let layout = VertexLayout::new().with(0, 3).with(1, 2).with(2, 4);
+        debug_assert_eq!(layout.floats_per_vertex(), FLOATS_PER_VERTEX);
+
+        let vao = Vao::gen();
+        let vbo = Vbo::gen();
+        let ibo = Ibo::gen();
+        vao.bind();
+        vbo.allocate(MAX_GLYPHS * 4 * layout.stride());
+        vao.set_layout(&layout);
+        let mut indices = Vec::with_capacity(MAX_GLYPHS * 6);
+        for quad in 0..MAX_GLYPHS as u32 {
+            let base = quad * 4;
+            indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+        }
+        ibo.set(&indices);
+        vao.unbind();
+
+        program.set();
+        program.set_int("u_atlas", 0);
+
+        Ok(Self {
+            program,
+            vao,
+            vbo,
+            _ibo: ibo,
+            projection: Mat4::new(),
+            vertices: Vec::with_capacity(MAX_GLYPHS * 4 * FLOATS_PER_VERTEX),
+            pipeline: PipelineState::OVERLAY,
+            draw_calls: 0,
+        })
+    }
+
+    pub fn begin(&mut self, projection: Mat4) {
+        self.projection = projection;
+        self.draw_calls = 0;
+    }
+
+    // Draw calls issued since `begin`
+    pub fn draw_calls(&self) -> u32 {
+        self.draw_calls
+    }
+
+    // `PipelineState::OVERLAY` by default
+    pub fn set_pipeline(&mut self, pipeline: PipelineState) {
+        self.pipeline = pipeline;
+    }
+
+    // Draw `text` with the top left corner of its block at `position`
+    pub fn draw_text(
+        &mut self,
+        font: &mut Font,
+        text: &str,
+        position: [f32; 3],
+        style: &TextStyle,
+    ) -> Result<TextLayout, UbiError> {
+        let layout = layout_text(font, text, style);
+
+        // Rasterize first, the atlas may grow and move every uv
+        let mut quads = Vec::with_capacity(layout.glyphs.len());
+        for placed in &layout.glyphs {
+            if let Some(glyph) = font.glyph(placed.glyph, style.size)? {
+                quads.push((placed, glyph));
+            }
+        }
+        font.flush_atlas()?;
+
+        let (atlas_width, atlas_height) = font.atlas_size();
+        let (atlas_width, atlas_height) = (atlas_width as f32, atlas_height as f32);
+        let sdf = matches!(font.render_mode(), FontRenderMode::Sdf { .. });
+
+        self.program.set();
+        self.program.set_mat4("u_projection", &self.projection);
+        self.program.set_int("u_sdf", sdf as i32);
+        font.texture().bind_unit(0);
+        self.vao.bind();
+        self.pipeline.apply();
+
+        self.vertices.clear();
+        let scale = style.scale;
+        for (placed, glyph) in quads {
+            let left = position[0] + (placed.x + glyph.left) * scale;
+            let top = position[1] + (placed.y + glyph.top) * scale;
+            let right = left + glyph.width as f32 / glyph.scale * scale;
+            let bottom = top - glyph.height as f32 / glyph.scale * scale;
+
+            let u0 = glyph.x as f32 / atlas_width;
+            let v0 = glyph.y as f32 / atlas_height;
+            let u1 = (glyph.x + glyph.width) as f32 / atlas_width;
+            let v1 = (glyph.y + glyph.height) as f32 / atlas_height;
+
+            let [r, g, b, a] = style.color;
+            let z = position[2];
+            for (x, y, u, v) in [
+                (left, bottom, u0, v0),
+                (right, bottom, u1, v0),
+                (right, top, u1, v1),
+                (left, top, u0, v1),
+            ] {
+                self.vertices
+                    .extend_from_slice(&[x, y, z, u, v, r, g, b, a]);
+            }
+
+            if self.vertices.len() == MAX_GLYPHS * 4 * FLOATS_PER_VERTEX {
+                self.flush();
+            }
+        }
+        self.flush();
+        self.vao.unbind();
+
+        Ok(layout)
+    }
+
+    fn flush(&mut self) {
+        let quads = self.vertices.len() / (4 * FLOATS_PER_VERTEX);
+        if quads == 0 {
+            return;
+        }
+        self.vbo.sub_data(0, &self.vertices);
+        unsafe {
+            gl::DrawElements(
+                gl::TRIANGLES,
+                (quads * 6) as i32,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+        self.draw_calls += 1;
+        self.vertices.clear();
+    }
+}
@@ -1,10 +1,67 @@
 //use image::{imageops, DynamicImage, GenericImageView, ImageResult};
 
-use gl::types::{GLint, GLsizei, GLuint};
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
 use std::ffi::c_void;
+use std::ptr::null;
+
+use crate::core::custom_error::UbiError;
+
+use super::gl_state;
+
+// Pixel formats of textures that are not loaded from 8 bit images, e.g. render targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    Rgba8,
+    Rg16F,
+    Rgb16F,
+    Rgba16F,
+    // 32 bit float depth, for shadow maps
+    Depth32F,
+}
+
+impl TextureFormat {
+    pub fn channels(&self) -> usize {
+        match self {
+            TextureFormat::Depth32F => 1,
+            TextureFormat::Rg16F => 2,
+            TextureFormat::Rgb16F => 3,
+            TextureFormat::Rgba8 | TextureFormat::Rgba16F => 4,
+        }
+    }
+
+    // internal format, format and type for glTexImage2D
+    pub(crate) fn gl_formats(&self) -> (GLint, GLenum, GLenum) {
+        match self {
+            TextureFormat::Rgba8 => (gl::RGBA8 as GLint, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::Rg16F => (gl::RG16F as GLint, gl::RG, gl::FLOAT),
+            TextureFormat::Rgb16F => (gl::RGB16F as GLint, gl::RGB, gl::FLOAT),
+            TextureFormat::Rgba16F => (gl::RGBA16F as GLint, gl::RGBA, gl::FLOAT),
+            TextureFormat::Depth32F => (
+                gl::DEPTH_COMPONENT32F as GLint,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+            ),
+        }
+    }
+
+    // format passed to glGetTexImage/glTexImage2D with float data
+    pub(crate) fn gl_float_format(&self) -> GLenum {
+        match self {
+            TextureFormat::Rg16F => gl::RG,
+            TextureFormat::Rgb16F => gl::RGB,
+            TextureFormat::Rgba8 | TextureFormat::Rgba16F => gl::RGBA,
+            TextureFormat::Depth32F => gl::DEPTH_COMPONENT,
+        }
+    }
+}
 
 pub struct Texture {
     pub id: GLuint,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    // Regenerated by `set_rgba`, off once `set_sampling` picked a filter without them
+    mipmaps: bool,
 }
 
 impl Texture {
@@ -13,29 +70,160 @@ impl Texture {
         unsafe {
             gl::GenTextures(1, &mut id);
         }
-        Ok(Texture { id })
+        Ok(Texture {
+            id,
+            width: 0,
+            height: 0,
+            format: TextureFormat::Rgba8,
+            mipmaps: true,
+        })
     }
 
-    pub fn setup(&self, image_path: &str) {
+    pub fn setup(&mut self, image_path: &str) {
         self.set_params();
         self.load_image_file(image_path).unwrap();
     }
 
-    pub fn bind(&self) {
+    // Load an image file keeping its alpha channel
+    pub fn from_file(image_path: &str) -> Result<Self, UbiError> {
+        let img = image::open(image_path)
+            .map_err(|e| UbiError::TextureError(format!("{}: {}", image_path, e)))?
+            .to_rgba8();
+        let img = image::imageops::flip_vertical(&img);
+        let (width, height) = img.dimensions();
+        Self::from_rgba(width, height, &img.into_raw())
+    }
+
+    // Create a texture from tightly packed RGBA8 pixels, first row is the bottom one
+    pub fn from_rgba(width: u32, height: u32, data: &[u8]) -> Result<Self, UbiError> {
+        let mut texture = Self::gen().map_err(UbiError::TextureError)?;
+        texture.set_params();
+        texture.set_rgba(width, height, data)?;
+        Ok(texture)
+    }
+
+    // Uninitialised texture without mipmaps, clamped and linearly filtered
+    pub fn with_format(width: u32, height: u32, format: TextureFormat) -> Result<Self, UbiError> {
+        Self::allocate(width, height, format, null())
+    }
+
+    // Float pixels with `format.channels()` values each, first row is the bottom one
+    pub fn from_f32(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &[f32],
+    ) -> Result<Self, UbiError> {
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(format.channels()))
+            .ok_or_else(|| {
+                UbiError::TextureError(format!(
+                    "{}x{} {:?} texture is too large",
+                    width, height, format
+                ))
+            })?;
+        if data.len() != expected {
+            return Err(UbiError::TextureError(format!(
+                "expected {} floats for a {}x{} {:?} texture, got {}",
+                expected,
+                width,
+                height,
+                format,
+                data.len()
+            )));
+        }
+        Self::allocate(width, height, format, data.as_ptr() as *const c_void)
+    }
+
+    fn allocate(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: *const c_void,
+    ) -> Result<Self, UbiError> {
+        let mut texture = Self::gen().map_err(UbiError::TextureError)?;
+        texture.width = width;
+        texture.height = height;
+        texture.format = format;
+        let (internal, pixel_format, kind) = format.gl_formats();
+        // Data always comes as floats, GL converts it to the internal format
+        let kind = if data.is_null() { kind } else { gl::FLOAT };
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl_state::bind_texture(gl::TEXTURE_2D, texture.id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                pixel_format,
+                kind,
+                data,
+            );
+            for (param, value) in [
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+                (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+            ] {
+                gl::TexParameteri(gl::TEXTURE_2D, param, value as GLint);
+            }
+            gl_state::bind_texture(gl::TEXTURE_2D, 0);
         }
+        Ok(texture)
     }
 
-    pub fn unbind(&self) {
+    // Read back the first mip level as floats, `format().channels()` per pixel
+    pub fn read_f32(&self) -> Vec<f32> {
+        let mut data = vec![0.0f32; (self.width * self.height) as usize * self.format.channels()];
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl_state::bind_texture(gl::TEXTURE_2D, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTexImage(
+                gl::TEXTURE_2D,
+                0,
+                self.format.gl_float_format(),
+                gl::FLOAT,
+                data.as_mut_ptr() as *mut c_void,
+            );
+            // Restore the default alignment
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl_state::bind_texture(gl::TEXTURE_2D, 0);
         }
+        data
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn bind(&self) {
+        gl_state::bind_texture(gl::TEXTURE_2D, self.id);
+    }
+
+    // Bind to texture unit `unit`, leaves that unit active
+    pub fn bind_unit(&self, unit: u32) {
+        gl_state::active_texture(unit);
+        gl_state::bind_texture(gl::TEXTURE_2D, self.id);
+    }
+
+    pub fn unbind(&self) {
+        gl_state::bind_texture(gl::TEXTURE_2D, 0);
     }
 
     fn set_params(&self) {
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl_state::bind_texture(gl::TEXTURE_2D, self.id);
             gl::TexParameteri(
                 gl::TEXTURE_2D,
                 gl::TEXTURE_WRAP_S,
@@ -52,11 +240,71 @@ impl Texture {
                 gl::LINEAR_MIPMAP_LINEAR as GLint,
             );
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl_state::bind_texture(gl::TEXTURE_2D, 0);
         }
     }
 
-    fn load_image_file(&self, filepath: &str) -> Result<(), String> {
+    // Clamped to the edge without mipmaps, so the cells of an atlas do not bleed into each
+    // other. `filter` is gl::NEAREST or gl::LINEAR
+    pub fn set_sampling(&mut self, filter: GLenum) {
+        self.mipmaps = false;
+        unsafe {
+            gl_state::bind_texture(gl::TEXTURE_2D, self.id);
+            for (param, value) in [
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_MIN_FILTER, filter),
+                (gl::TEXTURE_MAG_FILTER, filter),
+            ] {
+                gl::TexParameteri(gl::TEXTURE_2D, param, value as GLint);
+            }
+            gl_state::bind_texture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    // Replace the whole image with tightly packed RGBA8 pixels, first row is the bottom one
+    pub fn set_rgba(&mut self, width: u32, height: u32, data: &[u8]) -> Result<(), UbiError> {
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or_else(|| {
+                UbiError::TextureError(format!("{}x{} RGBA texture is too large", width, height))
+            })?;
+        if data.len() != expected {
+            return Err(UbiError::TextureError(format!(
+                "expected {} bytes for a {}x{} RGBA texture, got {}",
+                expected,
+                width,
+                height,
+                data.len()
+            )));
+        }
+
+        self.width = width;
+        self.height = height;
+        self.format = TextureFormat::Rgba8;
+        unsafe {
+            gl_state::bind_texture(gl::TEXTURE_2D, self.id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const c_void,
+            );
+            if self.mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+            gl_state::bind_texture(gl::TEXTURE_2D, 0);
+        }
+        Ok(())
+    }
+
+    fn load_image_file(&mut self, filepath: &str) -> Result<(), String> {
         let img = match image::open(filepath) {
             Ok(img) => img.to_rgb8(),
             Err(e) => return Err(format!("Failed to load image: {}", e)),
@@ -68,9 +316,11 @@ impl Texture {
         let data = img.into_raw();
 
         println!("Loaded texture dimensions: {}x{}", width, height);
+        self.width = width;
+        self.height = height;
 
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl_state::bind_texture(gl::TEXTURE_2D, self.id);
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
@@ -83,8 +333,15 @@ impl Texture {
                 data.as_ptr() as *const c_void,
             );
             gl::GenerateMipmap(gl::TEXTURE_2D);
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl_state::bind_texture(gl::TEXTURE_2D, 0);
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        gl_state::forget_texture(self.id);
+        unsafe { gl::DeleteTextures(1, &self.id) }
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use image::RgbaImage;
+
+use crate::core::custom_error::UbiError;
+
+use super::texture::Texture;
+
+// Where an image ended up inside the atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    // Pixel rectangle, (0, 0) is the top left corner of the atlas image
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Normalized (u, v, width, height) ready to be used as `Sprite::uv_rect`
+    pub uv_rect: [f32; 4],
+}
+
+// Many images packed into a single texture
+pub struct TextureAtlas {
+    pub texture: Texture,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    pub fn regions(&self) -> &HashMap<String, AtlasRegion> {
+        &self.regions
+    }
+}
+
+// Collects images and packs them into a `TextureAtlas`
+//
+// Every image is surrounded by `extrude` pixels copied from its own border,
+// so linear filtering at the edges samples the image itself, and `padding`
+// transparent pixels separate it from its neighbours.
+pub struct AtlasBuilder {
+    images: Vec<(String, RgbaImage)>,
+    padding: u32,
+    extrude: u32,
+    max_size: u32,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            padding: 1,
+            extrude: 1,
+            max_size: 4096,
+        }
+    }
+
+    pub fn padding(mut self, pixels: u32) -> Self {
+        self.padding = pixels;
+        self
+    }
+
+    pub fn extrude(mut self, pixels: u32) -> Self {
+        self.extrude = pixels;
+        self
+    }
+
+    // Largest width or height the atlas may grow to
+    pub fn max_size(mut self, pixels: u32) -> Self {
+        self.max_size = pixels;
+        self
+    }
+
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        self.images.push((name.to_string(), image));
+    }
+
+    pub fn add_file(&mut self, name: &str, path: &str) -> Result<(), UbiError> {
+        let image = image::open(path)
+            .map_err(|e| UbiError::TextureError(format!("{}: {}", path, e)))?
+            .to_rgba8();
+        self.add_image(name, image);
+        Ok(())
+    }
+
+    // Pack on the CPU only, returns the atlas image (top row first) and the regions
+    pub fn pack(&self) -> Result<(RgbaImage, HashMap<String, AtlasRegion>), UbiError> {
+        let border = self.extrude + self.padding;
+        let cells: Vec<(u32, u32)> = self
+            .images
+            .iter()
+            .map(|(_, image)| (image.width() + 2 * border, image.height() + 2 * border))
+            .collect();
+
+        // Shelf packing works best with the tallest cells first
+        let mut order: Vec<usize> = (0..cells.len()).collect();
+        order.sort_by(|&a, &b| cells[b].1.cmp(&cells[a].1).then(cells[b].0.cmp(&cells[a].0)));
+
+        let widest = cells.iter().map(|c| c.0).max().unwrap_or(1);
+        let tallest = cells.iter().map(|c| c.1).max().unwrap_or(1);
+        let mut width = widest.next_power_of_two();
+        let mut height = tallest.next_power_of_two();
+
+        let positions = loop {
+            if width > self.max_size || height > self.max_size {
+                return Err(UbiError::TextureError(format!(
+                    "{} images do not fit in a {}x{} atlas",
+                    self.images.len(),
+                    self.max_size,
+                    self.max_size
+                )));
+            }
+            if let Some(positions) = shelf_pack(&cells, &order, width, height) {
+                break positions;
+            }
+            if width <= height {
+                width *= 2;
+            } else {
+                height *= 2;
+            }
+        };
+
+        let mut atlas = RgbaImage::new(width, height);
+        let mut regions = HashMap::new();
+        for (index, (name, image)) in self.images.iter().enumerate() {
+            let (cell_x, cell_y) = positions[index];
+            let x = cell_x + border;
+            let y = cell_y + border;
+            blit_extruded(&mut atlas, image, x, y, self.extrude);
+
+            let (w, h) = image.dimensions();
+            // Textures are uploaded bottom row first, so v grows upwards
+            let uv_rect = [
+                x as f32 / width as f32,
+                (height - y - h) as f32 / height as f32,
+                w as f32 / width as f32,
+                h as f32 / height as f32,
+            ];
+            regions.insert(
+                name.clone(),
+                AtlasRegion {
+                    x,
+                    y,
+                    width: w,
+                    height: h,
+                    uv_rect,
+                },
+            );
+        }
+
+        Ok((atlas, regions))
+    }
+
+    pub fn build(&self) -> Result<TextureAtlas, UbiError> {
+        let (image, regions) = self.pack()?;
+        let image = image::imageops::flip_vertical(&image);
+        let mut texture = Texture::from_rgba(image.width(), image.height(), &image.into_raw())?;
+        // Mipmaps would blend neighbouring regions past the padding and extrusion
+        texture.set_sampling(gl::LINEAR);
+        Ok(TextureAtlas { texture, regions })
+    }
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Place cells left to right in rows as tall as their first cell
+fn shelf_pack(
+    cells: &[(u32, u32)],
+    order: &[usize],
+    width: u32,
+    height: u32,
+) -> Option<Vec<(u32, u32)>> {
+    let mut positions = vec![(0, 0); cells.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+
+    for &index in order {
+        let (w, h) = cells[index];
+        if w > width {
+            return None;
+        }
+        if x + w > width {
+            y += shelf_height;
+            x = 0;
+            shelf_height = 0;
+        }
+        if y + h > height {
+            return None;
+        }
+        positions[index] = (x, y);
+        x += w;
+        shelf_height = shelf_height.max(h);
+    }
+
+    Some(positions)
+}
+
+// Copy `image` to (x, y) and repeat its outermost pixels `extrude` times around it
+fn blit_extruded(atlas: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, extrude: u32) {
+    let (w, h) = image.dimensions();
+    if w == 0 || h == 0 {
+        return;
+    }
+    let extrude = extrude as i64;
+    for dy in -extrude..h as i64 + extrude {
+        for dx in -extrude..w as i64 + extrude {
+            let src_x = dx.clamp(0, w as i64 - 1) as u32;
+            let src_y = dy.clamp(0, h as i64 - 1) as u32;
+            let dst_x = (x as i64 + dx) as u32;
+            let dst_y = (y as i64 + dy) as u32;
+            atlas.put_pixel(dst_x, dst_y, *image.get_pixel(src_x, src_y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn filled(width: u32, height: u32, color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(color))
+    }
+
+    fn overlap(a: &AtlasRegion, b: &AtlasRegion, border: u32) -> bool {
+        a.x < b.x + b.width + 2 * border
+            && b.x < a.x + a.width + 2 * border
+            && a.y < b.y + b.height + 2 * border
+            && b.y < a.y + a.height + 2 * border
+    }
+
+    #[test]
+    fn regions_keep_their_border_apart() {
+        let mut builder = AtlasBuilder::new().padding(2).extrude(1);
+        for i in 0..12 {
+            builder.add_image(&i.to_string(), filled(10 + i, 20 - i, [i as u8, 0, 0, 255]));
+        }
+        let (atlas, regions) = builder.pack().unwrap();
+        assert_eq!(regions.len(), 12);
+        assert!(atlas.width().is_power_of_two() && atlas.height().is_power_of_two());
+
+        let regions: Vec<&AtlasRegion> = (0..12).map(|i| &regions[&i.to_string()]).collect();
+        for (i, region) in regions.iter().enumerate() {
+            assert_eq!(
+                (region.width, region.height),
+                (10 + i as u32, 20 - i as u32)
+            );
+            assert!(region.x >= 3 && region.y >= 3);
+            assert!(region.x + region.width + 3 <= atlas.width());
+            assert!(region.y + region.height + 3 <= atlas.height());
+            assert_eq!(atlas.get_pixel(region.x, region.y).0, [i as u8, 0, 0, 255]);
+            for other in &regions[i + 1..] {
+                // Cells hold the image, its extrusion and its padding on each side
+                assert!(!overlap(region, other, 3));
+            }
+        }
+    }
+
+    #[test]
+    fn borders_are_extruded_then_padded() {
+        let mut image = filled(2, 2, [0, 0, 255, 255]);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let mut builder = AtlasBuilder::new().padding(1).extrude(2);
+        builder.add_image("a", image);
+        let (atlas, regions) = builder.pack().unwrap();
+        let region = regions["a"];
+        assert_eq!((region.x, region.y), (3, 3));
+        // The top left pixel repeated up and to the left, then transparent padding
+        assert_eq!(atlas.get_pixel(1, 1).0, [255, 0, 0, 255]);
+        assert_eq!(atlas.get_pixel(1, 3).0, [255, 0, 0, 255]);
+        assert_eq!(atlas.get_pixel(6, 3).0, [0, 0, 255, 255]);
+        assert_eq!(atlas.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(atlas.get_pixel(7, 4).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn uv_rects_count_from_the_bottom() {
+        let mut builder = AtlasBuilder::new().padding(0).extrude(0);
+        builder.add_image("tall", filled(4, 8, [0; 4]));
+        builder.add_image("small", filled(4, 4, [0; 4]));
+        let (atlas, regions) = builder.pack().unwrap();
+        assert_eq!(atlas.dimensions(), (8, 8));
+        assert_eq!(regions["tall"].uv_rect, [0.0, 0.0, 0.5, 1.0]);
+        assert_eq!(regions["small"].uv_rect, [0.5, 0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn atlas_grows_up_to_the_max_size() {
+        let mut builder = AtlasBuilder::new().padding(0).extrude(0).max_size(64);
+        for i in 0..4 {
+            builder.add_image(&i.to_string(), filled(32, 32, [0; 4]));
+        }
+        assert_eq!(builder.pack().unwrap().0.dimensions(), (64, 64));
+        builder.add_image("one more", filled(32, 32, [0; 4]));
+        assert!(builder.pack().is_err());
+    }
+}
@@ -0,0 +1,725 @@
+// Importer for maps made with the Tiled editor, see https://doc.mapeditor.org/en/stable/reference/
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+use base64::Engine;
+use roxmltree::Node;
+use serde_json::Value;
+
+use crate::core::custom_error::UbiError;
+
+use super::tilemap::{
+    MapLayer, MapOrientation, ObjectLayer, ObjectShape, Properties, PropertyValue,
+    TileAnimationFrame, TileLayer, TiledObject, Tilemap, Tileset,
+};
+
+pub fn load_tmx(path: &Path) -> Result<Tilemap, UbiError> {
+    let source = std::fs::read_to_string(path)?;
+    parse_tmx(&source, path.parent().unwrap_or(Path::new(""))).map_err(|e| with_path(path, e))
+}
+
+pub fn load_tmj(path: &Path) -> Result<Tilemap, UbiError> {
+    let source = std::fs::read_to_string(path)?;
+    parse_tmj(&source, path.parent().unwrap_or(Path::new(""))).map_err(|e| with_path(path, e))
+}
+
+// `base_dir` resolves external tilesets and images
+pub fn parse_tmx(source: &str, base_dir: &Path) -> Result<Tilemap, UbiError> {
+    let document = roxmltree::Document::parse(source).map_err(|e| error(&e.to_string()))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "map" {
+        return Err(error("root element is not <map>"));
+    }
+    if attr(&root, "infinite").unwrap_or("0") == "1" {
+        return Err(error("infinite maps are not supported"));
+    }
+
+    let mut map = Tilemap {
+        orientation: parse_orientation(attr(&root, "orientation").unwrap_or("orthogonal"))?,
+        width: attr_num(&root, "width")?,
+        height: attr_num(&root, "height")?,
+        tile_width: attr_num(&root, "tilewidth")?,
+        tile_height: attr_num(&root, "tileheight")?,
+        ..Default::default()
+    };
+
+    for child in elements(&root) {
+        match child.tag_name().name() {
+            "properties" => map.properties = xml_properties(&child)?,
+            "tileset" => {
+                let first_gid = attr_num(&child, "firstgid")?;
+                let tileset = match attr(&child, "source") {
+                    Some(source) => load_external_tileset(&base_dir.join(source), first_gid)?,
+                    None => xml_tileset(&child, first_gid, base_dir)?,
+                };
+                map.tilesets.push(tileset);
+            }
+            _ => {}
+        }
+    }
+    xml_layers(&root, &mut map.layers, LayerGroup::ROOT)?;
+
+    Ok(map)
+}
+
+pub fn parse_tmj(source: &str, base_dir: &Path) -> Result<Tilemap, UbiError> {
+    let root: Value = serde_json::from_str(source).map_err(|e| error(&e.to_string()))?;
+    if root["infinite"].as_bool().unwrap_or(false) {
+        return Err(error("infinite maps are not supported"));
+    }
+
+    let mut map = Tilemap {
+        orientation: parse_orientation(root["orientation"].as_str().unwrap_or("orthogonal"))?,
+        width: json_num(&root, "width")?,
+        height: json_num(&root, "height")?,
+        tile_width: json_num(&root, "tilewidth")?,
+        tile_height: json_num(&root, "tileheight")?,
+        properties: json_properties(&root["properties"])?,
+        ..Default::default()
+    };
+
+    for tileset in root["tilesets"].as_array().into_iter().flatten() {
+        let first_gid = json_num(tileset, "firstgid")?;
+        let tileset = match tileset["source"].as_str() {
+            Some(source) => load_external_tileset(&base_dir.join(source), first_gid)?,
+            None => json_tileset(tileset, first_gid, base_dir)?,
+        };
+        map.tilesets.push(tileset);
+    }
+    json_layers(&root["layers"], &mut map.layers, LayerGroup::ROOT)?;
+
+    Ok(map)
+}
+
+fn load_external_tileset(path: &Path, first_gid: u32) -> Result<Tileset, UbiError> {
+    let source = std::fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let tileset = if path.extension().and_then(|e| e.to_str()) == Some("tsx") {
+        let document = roxmltree::Document::parse(&source).map_err(|e| error(&e.to_string()))?;
+        xml_tileset(&document.root_element(), first_gid, base_dir)
+    } else {
+        let root: Value = serde_json::from_str(&source).map_err(|e| error(&e.to_string()))?;
+        json_tileset(&root, first_gid, base_dir)
+    };
+    tileset.map_err(|e| with_path(path, e))
+}
+
+fn parse_orientation(value: &str) -> Result<MapOrientation, UbiError> {
+    match value {
+        "orthogonal" => Ok(MapOrientation::Orthogonal),
+        "isometric" => Ok(MapOrientation::Isometric),
+        other => Err(error(&format!("{} maps are not supported", other))),
+    }
+}
+
+// Tile layer data is a list of little endian u32 once decoded and decompressed
+fn decode_base64_tiles(
+    data: &str,
+    compression: Option<&str>,
+    expected: usize,
+) -> Result<Vec<u32>, UbiError> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|e| error(&format!("invalid base64 tile data: {}", e)))?;
+
+    let bytes = match compression.unwrap_or("") {
+        "" => bytes,
+        "zlib" => decompress(flate2::read::ZlibDecoder::new(&bytes[..]))?,
+        "gzip" => decompress(flate2::read::GzDecoder::new(&bytes[..]))?,
+        other => return Err(error(&format!("{} compression is not supported", other))),
+    };
+
+    if bytes.len() != expected * 4 {
+        return Err(error(&format!(
+            "expected {} tiles, found {} bytes of data",
+            expected,
+            bytes.len()
+        )));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn decompress(mut reader: impl Read) -> Result<Vec<u8>, UbiError> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| error(&format!("corrupt compressed tile data: {}", e)))?;
+    Ok(bytes)
+}
+
+fn parse_color(value: &str) -> Result<[u8; 4], UbiError> {
+    // #AARRGGBB or #RRGGBB
+    let hex = value.trim_start_matches('#');
+    let parsed =
+        u32::from_str_radix(hex, 16).map_err(|_| error(&format!("invalid color {}", value)));
+    match hex.len() {
+        6 => {
+            let v = parsed?;
+            Ok([(v >> 16) as u8, (v >> 8) as u8, v as u8, 255])
+        }
+        8 => {
+            let v = parsed?;
+            Ok([(v >> 16) as u8, (v >> 8) as u8, v as u8, (v >> 24) as u8])
+        }
+        _ => Err(error(&format!("invalid color {}", value))),
+    }
+}
+
+fn property_value(kind: &str, value: &str) -> Result<PropertyValue, UbiError> {
+    let invalid = || error(&format!("invalid {} property value \"{}\"", kind, value));
+    Ok(match kind {
+        "bool" => PropertyValue::Bool(value == "true"),
+        "int" => PropertyValue::Int(value.parse().map_err(|_| invalid())?),
+        "float" => PropertyValue::Float(value.parse().map_err(|_| invalid())?),
+        "color" => PropertyValue::Color(if value.is_empty() {
+            [0; 4]
+        } else {
+            parse_color(value)?
+        }),
+        "file" => PropertyValue::File(value.to_string()),
+        "object" => PropertyValue::Object(value.parse().map_err(|_| invalid())?),
+        _ => PropertyValue::String(value.to_string()),
+    })
+}
+
+// XML
+
+fn elements<'a, 'input>(node: &Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(|child| child.is_element())
+}
+
+fn attr<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute(name)
+}
+
+fn attr_num<T: std::str::FromStr>(node: &Node, name: &str) -> Result<T, UbiError> {
+    let value = node.attribute(name).ok_or_else(|| {
+        error(&format!(
+            "<{}> is missing the \"{}\" attribute",
+            node.tag_name().name(),
+            name
+        ))
+    })?;
+    value.parse().map_err(|_| {
+        error(&format!(
+            "<{}> has an invalid \"{}\" attribute: {}",
+            node.tag_name().name(),
+            name,
+            value
+        ))
+    })
+}
+
+fn attr_or<T: std::str::FromStr>(node: &Node, name: &str, default: T) -> Result<T, UbiError> {
+    match node.attribute(name) {
+        Some(_) => attr_num(node, name),
+        None => Ok(default),
+    }
+}
+
+fn xml_properties(node: &Node) -> Result<Properties, UbiError> {
+    let mut properties = HashMap::new();
+    for property in elements(node).filter(|n| n.has_tag_name("property")) {
+        let name = attr(&property, "name").ok_or_else(|| error("property without a name"))?;
+        // Multi-line strings are stored as text instead of an attribute
+        let value = attr(&property, "value")
+            .or_else(|| property.text())
+            .unwrap_or("");
+        let kind = attr(&property, "type").unwrap_or("string");
+        properties.insert(name.to_string(), property_value(kind, value)?);
+    }
+    Ok(properties)
+}
+
+fn xml_tileset(node: &Node, first_gid: u32, base_dir: &Path) -> Result<Tileset, UbiError> {
+    let mut tileset = Tileset {
+        name: attr(node, "name").unwrap_or("").to_string(),
+        first_gid,
+        tile_width: attr_num(node, "tilewidth")?,
+        tile_height: attr_num(node, "tileheight")?,
+        tile_count: attr_num(node, "tilecount")?,
+        columns: attr_num(node, "columns")?,
+        margin: attr_or(node, "margin", 0)?,
+        spacing: attr_or(node, "spacing", 0)?,
+        ..Default::default()
+    };
+
+    for child in elements(node) {
+        match child.tag_name().name() {
+            "image" => {
+                let source =
+                    attr(&child, "source").ok_or_else(|| error("<image> without source"))?;
+                tileset.image = base_dir.join(source);
+                tileset.image_width = attr_num(&child, "width")?;
+                tileset.image_height = attr_num(&child, "height")?;
+            }
+            "properties" => tileset.properties = xml_properties(&child)?,
+            "tile" => {
+                let id: u32 = attr_num(&child, "id")?;
+                for data in elements(&child) {
+                    match data.tag_name().name() {
+                        "properties" => {
+                            tileset.tile_properties.insert(id, xml_properties(&data)?);
+                        }
+                        "animation" => {
+                            let mut frames = Vec::new();
+                            for frame in elements(&data).filter(|n| n.has_tag_name("frame")) {
+                                frames.push(TileAnimationFrame {
+                                    tile: attr_num(&frame, "tileid")?,
+                                    duration: attr_num::<f32>(&frame, "duration")? / 1000.0,
+                                });
+                            }
+                            tileset.animations.insert(id, frames);
+                        }
+                        "image" => {
+                            return Err(error(&format!(
+                                "tileset \"{}\" is an image collection, only single image tilesets are supported",
+                                tileset.name
+                            )))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if tileset.image.as_os_str().is_empty() {
+        return Err(error(&format!("tileset \"{}\" has no image", tileset.name)));
+    }
+    Ok(tileset)
+}
+
+// Offset, visibility and opacity of the enclosing group layers, applied to their children
+#[derive(Debug, Clone, Copy)]
+struct LayerGroup {
+    offset: [f32; 2],
+    visible: bool,
+    opacity: f32,
+}
+
+impl LayerGroup {
+    const ROOT: Self = Self {
+        offset: [0.0, 0.0],
+        visible: true,
+        opacity: 1.0,
+    };
+
+    fn child(&self, offset: [f32; 2], visible: bool, opacity: f32) -> Self {
+        Self {
+            offset: [self.offset[0] + offset[0], self.offset[1] + offset[1]],
+            visible: self.visible && visible,
+            opacity: self.opacity * opacity,
+        }
+    }
+
+    fn apply(&self, layer: MapLayer) -> MapLayer {
+        match layer {
+            MapLayer::Tiles(mut layer) => {
+                layer.offset[0] += self.offset[0];
+                layer.offset[1] += self.offset[1];
+                layer.visible &= self.visible;
+                layer.opacity *= self.opacity;
+                MapLayer::Tiles(layer)
+            }
+            MapLayer::Objects(mut layer) => {
+                for object in &mut layer.objects {
+                    object.x += self.offset[0];
+                    object.y += self.offset[1];
+                }
+                layer.visible &= self.visible;
+                MapLayer::Objects(layer)
+            }
+        }
+    }
+}
+
+fn xml_layers(node: &Node, layers: &mut Vec<MapLayer>, group: LayerGroup) -> Result<(), UbiError> {
+    for child in elements(node) {
+        match child.tag_name().name() {
+            "layer" => layers.push(group.apply(MapLayer::Tiles(xml_tile_layer(&child)?))),
+            "objectgroup" => {
+                let layer = MapLayer::Objects(xml_object_layer(&child)?);
+                // Object layers have no offset field, it moves the objects
+                let offset = [
+                    attr_or(&child, "offsetx", 0.0)?,
+                    attr_or(&child, "offsety", 0.0)?,
+                ];
+                layers.push(group.child(offset, true, 1.0).apply(layer));
+            }
+            "group" => {
+                let group = group.child(
+                    [
+                        attr_or(&child, "offsetx", 0.0)?,
+                        attr_or(&child, "offsety", 0.0)?,
+                    ],
+                    attr(&child, "visible").unwrap_or("1") != "0",
+                    attr_or(&child, "opacity", 1.0)?,
+                );
+                xml_layers(&child, layers, group)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn xml_tile_layer(node: &Node) -> Result<TileLayer, UbiError> {
+    let mut layer = TileLayer {
+        name: attr(node, "name").unwrap_or("").to_string(),
+        width: attr_num(node, "width")?,
+        height: attr_num(node, "height")?,
+        visible: attr(node, "visible").unwrap_or("1") != "0",
+        opacity: attr_or(node, "opacity", 1.0)?,
+        offset: [
+            attr_or(node, "offsetx", 0.0)?,
+            attr_or(node, "offsety", 0.0)?,
+        ],
+        ..Default::default()
+    };
+    let expected = tile_count(&layer)?;
+
+    for child in elements(node) {
+        match child.tag_name().name() {
+            "properties" => layer.properties = xml_properties(&child)?,
+            "data" => {
+                let text = child.text().unwrap_or("");
+                layer.tiles = match attr(&child, "encoding") {
+                    Some("csv") => text
+                        .split(',')
+                        .map(|v| v.trim().parse::<u32>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| {
+                            error(&format!("layer \"{}\" has invalid csv data", layer.name))
+                        })?,
+                    Some("base64") => {
+                        decode_base64_tiles(text, attr(&child, "compression"), expected)?
+                    }
+                    Some(other) => {
+                        return Err(error(&format!("{} encoding is not supported", other)))
+                    }
+                    None => elements(&child)
+                        .filter(|n| n.has_tag_name("tile"))
+                        .map(|tile| attr_or(&tile, "gid", 0))
+                        .collect::<Result<_, _>>()?,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    if layer.tiles.len() != expected {
+        return Err(error(&format!(
+            "layer \"{}\" has {} tiles, expected {}",
+            layer.name,
+            layer.tiles.len(),
+            expected
+        )));
+    }
+    Ok(layer)
+}
+
+fn xml_object_layer(node: &Node) -> Result<ObjectLayer, UbiError> {
+    let mut layer = ObjectLayer {
+        name: attr(node, "name").unwrap_or("").to_string(),
+        visible: attr(node, "visible").unwrap_or("1") != "0",
+        ..Default::default()
+    };
+
+    for child in elements(node) {
+        match child.tag_name().name() {
+            "properties" => layer.properties = xml_properties(&child)?,
+            "object" => {
+                let mut object = TiledObject {
+                    id: attr_num(&child, "id")?,
+                    name: attr(&child, "name").unwrap_or("").to_string(),
+                    class: attr(&child, "class")
+                        .or_else(|| attr(&child, "type"))
+                        .unwrap_or("")
+                        .to_string(),
+                    layer: layer.name.clone(),
+                    x: attr_or(&child, "x", 0.0)?,
+                    y: attr_or(&child, "y", 0.0)?,
+                    width: attr_or(&child, "width", 0.0)?,
+                    height: attr_or(&child, "height", 0.0)?,
+                    rotation: attr_or(&child, "rotation", 0.0)?,
+                    visible: attr(&child, "visible").unwrap_or("1") != "0",
+                    shape: match attr(&child, "gid") {
+                        Some(_) => ObjectShape::Tile(attr_num(&child, "gid")?),
+                        None => ObjectShape::Rectangle,
+                    },
+                    properties: HashMap::new(),
+                };
+                for data in elements(&child) {
+                    match data.tag_name().name() {
+                        "properties" => object.properties = xml_properties(&data)?,
+                        "ellipse" => object.shape = ObjectShape::Ellipse,
+                        "point" => object.shape = ObjectShape::Point,
+                        "polygon" => object.shape = ObjectShape::Polygon(xml_points(&data)?),
+                        "polyline" => object.shape = ObjectShape::Polyline(xml_points(&data)?),
+                        _ => {}
+                    }
+                }
+                layer.objects.push(object);
+            }
+            _ => {}
+        }
+    }
+    Ok(layer)
+}
+
+// "x1,y1 x2,y2 ..."
+fn xml_points(node: &Node) -> Result<Vec<[f32; 2]>, UbiError> {
+    let points = attr(node, "points").unwrap_or("");
+    points
+        .split_whitespace()
+        .map(|pair| {
+            let (x, y) = pair
+                .split_once(',')
+                .ok_or_else(|| error(&format!("invalid point \"{}\"", pair)))?;
+            let x = x
+                .parse()
+                .map_err(|_| error(&format!("invalid point \"{}\"", pair)))?;
+            let y = y
+                .parse()
+                .map_err(|_| error(&format!("invalid point \"{}\"", pair)))?;
+            Ok([x, y])
+        })
+        .collect()
+}
+
+// JSON
+
+fn json_num<T: TryFrom<u64>>(value: &Value, key: &str) -> Result<T, UbiError> {
+    value[key]
+        .as_u64()
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or_else(|| error(&format!("missing or invalid \"{}\"", key)))
+}
+
+fn json_f32(value: &Value, key: &str, default: f32) -> f32 {
+    value[key].as_f64().map(|v| v as f32).unwrap_or(default)
+}
+
+fn json_str(value: &Value, key: &str) -> String {
+    value[key].as_str().unwrap_or("").to_string()
+}
+
+fn json_properties(value: &Value) -> Result<Properties, UbiError> {
+    let mut properties = HashMap::new();
+    for property in value.as_array().into_iter().flatten() {
+        let name = property["name"]
+            .as_str()
+            .ok_or_else(|| error("property without a name"))?;
+        let kind = property["type"].as_str().unwrap_or("string");
+        let value = match &property["value"] {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        properties.insert(name.to_string(), property_value(kind, &value)?);
+    }
+    Ok(properties)
+}
+
+fn json_tileset(value: &Value, first_gid: u32, base_dir: &Path) -> Result<Tileset, UbiError> {
+    let name = json_str(value, "name");
+    let image = value["image"].as_str().ok_or_else(|| {
+        error(&format!(
+            "tileset \"{}\" has no image, only single image tilesets are supported",
+            name
+        ))
+    })?;
+
+    let mut tileset = Tileset {
+        name,
+        first_gid,
+        tile_width: json_num(value, "tilewidth")?,
+        tile_height: json_num(value, "tileheight")?,
+        tile_count: json_num(value, "tilecount")?,
+        columns: json_num(value, "columns")?,
+        margin: json_num(value, "margin").unwrap_or(0),
+        spacing: json_num(value, "spacing").unwrap_or(0),
+        image: base_dir.join(image),
+        image_width: json_num(value, "imagewidth")?,
+        image_height: json_num(value, "imageheight")?,
+        properties: json_properties(&value["properties"])?,
+        ..Default::default()
+    };
+
+    for tile in value["tiles"].as_array().into_iter().flatten() {
+        let id = json_num(tile, "id")?;
+        if tile["properties"].is_array() {
+            tileset
+                .tile_properties
+                .insert(id, json_properties(&tile["properties"])?);
+        }
+        if let Some(animation) = tile["animation"].as_array() {
+            let frames = animation
+                .iter()
+                .map(|frame| {
+                    Ok(TileAnimationFrame {
+                        tile: json_num(frame, "tileid")?,
+                        duration: json_num::<u32>(frame, "duration")? as f32 / 1000.0,
+                    })
+                })
+                .collect::<Result<_, UbiError>>()?;
+            tileset.animations.insert(id, frames);
+        }
+    }
+    Ok(tileset)
+}
+
+fn json_layers(
+    value: &Value,
+    layers: &mut Vec<MapLayer>,
+    group: LayerGroup,
+) -> Result<(), UbiError> {
+    for layer in value.as_array().into_iter().flatten() {
+        let offset = [
+            json_f32(layer, "offsetx", 0.0),
+            json_f32(layer, "offsety", 0.0),
+        ];
+        match layer["type"].as_str().unwrap_or("") {
+            "tilelayer" => layers.push(group.apply(MapLayer::Tiles(json_tile_layer(layer)?))),
+            "objectgroup" => {
+                // Object layers have no offset field, it moves the objects
+                let objects = MapLayer::Objects(json_object_layer(layer)?);
+                layers.push(group.child(offset, true, 1.0).apply(objects));
+            }
+            "group" => {
+                let group = group.child(
+                    offset,
+                    layer["visible"].as_bool().unwrap_or(true),
+                    json_f32(layer, "opacity", 1.0),
+                );
+                json_layers(&layer["layers"], layers, group)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn json_tile_layer(value: &Value) -> Result<TileLayer, UbiError> {
+    let mut layer = TileLayer {
+        name: json_str(value, "name"),
+        width: json_num(value, "width")?,
+        height: json_num(value, "height")?,
+        visible: value["visible"].as_bool().unwrap_or(true),
+        opacity: json_f32(value, "opacity", 1.0),
+        offset: [
+            json_f32(value, "offsetx", 0.0),
+            json_f32(value, "offsety", 0.0),
+        ],
+        properties: json_properties(&value["properties"])?,
+        ..Default::default()
+    };
+    let expected = tile_count(&layer)?;
+
+    layer.tiles = match &value["data"] {
+        Value::Array(tiles) => tiles
+            .iter()
+            .map(|tile| tile.as_u64().map(|gid| gid as u32))
+            .collect::<Option<_>>()
+            .ok_or_else(|| error(&format!("layer \"{}\" has invalid tile data", layer.name)))?,
+        Value::String(data) => decode_base64_tiles(data, value["compression"].as_str(), expected)?,
+        _ => return Err(error(&format!("layer \"{}\" has no tile data", layer.name))),
+    };
+
+    if layer.tiles.len() != expected {
+        return Err(error(&format!(
+            "layer \"{}\" has {} tiles, expected {}",
+            layer.name,
+            layer.tiles.len(),
+            expected
+        )));
+    }
+    Ok(layer)
+}
+
+fn json_object_layer(value: &Value) -> Result<ObjectLayer, UbiError> {
+    let name = json_str(value, "name");
+    let mut objects = Vec::new();
+    for object in value["objects"].as_array().into_iter().flatten() {
+        let points = |key: &str| -> Vec<[f32; 2]> {
+            object[key]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|p| [json_f32(p, "x", 0.0), json_f32(p, "y", 0.0)])
+                .collect()
+        };
+        let shape = if let Some(gid) = object["gid"].as_u64() {
+            ObjectShape::Tile(gid as u32)
+        } else if object["ellipse"].as_bool().unwrap_or(false) {
+            ObjectShape::Ellipse
+        } else if object["point"].as_bool().unwrap_or(false) {
+            ObjectShape::Point
+        } else if object["polygon"].is_array() {
+            ObjectShape::Polygon(points("polygon"))
+        } else if object["polyline"].is_array() {
+            ObjectShape::Polyline(points("polyline"))
+        } else {
+            ObjectShape::Rectangle
+        };
+
+        let class = match object["class"].as_str() {
+            Some(class) => class.to_string(),
+            None => json_str(object, "type"),
+        };
+        objects.push(TiledObject {
+            id: json_num(object, "id")?,
+            name: json_str(object, "name"),
+            class,
+            layer: name.clone(),
+            x: json_f32(object, "x", 0.0),
+            y: json_f32(object, "y", 0.0),
+            width: json_f32(object, "width", 0.0),
+            height: json_f32(object, "height", 0.0),
+            rotation: json_f32(object, "rotation", 0.0),
+            visible: object["visible"].as_bool().unwrap_or(true),
+            shape,
+            properties: json_properties(&object["properties"])?,
+        });
+    }
+
+    Ok(ObjectLayer {
+        name,
+        objects,
+        visible: value["visible"].as_bool().unwrap_or(true),
+        properties: json_properties(&value["properties"])?,
+    })
+}
+
+// Width times height, the sizes come from the file
+fn tile_count(layer: &TileLayer) -> Result<usize, UbiError> {
+    layer
+        .width
+        .checked_mul(layer.height)
+        .map(|count| count as usize)
+        .ok_or_else(|| {
+            error(&format!(
+                "layer \"{}\" is too large, {}x{} tiles",
+                layer.name, layer.width, layer.height
+            ))
+        })
+}
+
+fn error(message: &str) -> UbiError {
+    UbiError::ResourceError(format!("tiled map: {}", message))
+}
+
+fn with_path(path: &Path, e: UbiError) -> UbiError {
+    match e {
+        UbiError::ResourceError(message) => {
+            UbiError::ResourceError(format!("{}: {}", path.display(), message))
+        }
+        other => other,
+    }
+}
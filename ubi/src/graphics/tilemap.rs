@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::core::custom_error::UbiError;
+use crate::core::ecs::entity::Entity;
+use crate::core::ecs::world::World;
+
+use super::tiled;
+
+// Tiled stores flips in the top bits of every global tile id
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const FLIP_MASK: u32 = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | 0x1000_0000;
+
+// Global tile id without its flip flags, 0 means no tile
+pub fn tile_gid(raw: u32) -> u32 {
+    raw & !FLIP_MASK
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MapOrientation {
+    #[default]
+    Orthogonal,
+    Isometric,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    // RGBA
+    Color([u8; 4]),
+    File(String),
+    // Id of another object of the map
+    Object(u32),
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileAnimationFrame {
+    // Local id inside the tileset
+    pub tile: u32,
+    // Seconds
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Tileset {
+    pub name: String,
+    pub first_gid: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_count: u32,
+    pub columns: u32,
+    pub margin: u32,
+    pub spacing: u32,
+    // Resolved against the file that declared the tileset
+    pub image: PathBuf,
+    pub image_width: u32,
+    pub image_height: u32,
+    // Keyed by local tile id
+    pub animations: HashMap<u32, Vec<TileAnimationFrame>>,
+    pub tile_properties: HashMap<u32, Properties>,
+    pub properties: Properties,
+}
+
+impl Tileset {
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid < self.first_gid + self.tile_count
+    }
+
+    // Pixel rectangle (x, y, width, height) of a local tile id, (0, 0) is the top left of the image
+    pub fn tile_rect(&self, local_id: u32) -> [u32; 4] {
+        let columns = self.columns.max(1);
+        let (column, row) = (local_id % columns, local_id / columns);
+        [
+            self.margin + column * (self.tile_width + self.spacing),
+            self.margin + row * (self.tile_height + self.spacing),
+            self.tile_width,
+            self.tile_height,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    // Global tile ids with flip flags, row by row from the top
+    pub tiles: Vec<u32>,
+    pub visible: bool,
+    pub opacity: f32,
+    // Pixels, y down like in Tiled
+    pub offset: [f32; 2],
+    pub properties: Properties,
+}
+
+impl TileLayer {
+    // Raw global id at (column, row), 0 when empty or out of bounds
+    pub fn tile(&self, column: u32, row: u32) -> u32 {
+        if column >= self.width || row >= self.height {
+            return 0;
+        }
+        self.tiles
+            .get((row * self.width + column) as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    // Points relative to the object position
+    Polygon(Vec<[f32; 2]>),
+    Polyline(Vec<[f32; 2]>),
+    // A tile drawn as an object, raw global id with flip flags
+    Tile(u32),
+}
+
+// An object of an object layer, spawned as a component by `Tilemap::spawn_objects`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    // "class" since Tiled 1.9, "type" before
+    pub class: String,
+    pub layer: String,
+    // Pixels, y down like in Tiled
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // Degrees clockwise
+    pub rotation: f32,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<TiledObject>,
+    pub visible: bool,
+    pub properties: Properties,
+}
+
+// Layers keep the order of the file, group layers are flattened into their children
+#[derive(Debug, Clone)]
+pub enum MapLayer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Tilemap {
+    pub orientation: MapOrientation,
+    // In tiles
+    pub width: u32,
+    pub height: u32,
+    // In pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<MapLayer>,
+    pub properties: Properties,
+}
+
+impl Tilemap {
+    // Load a Tiled map, .tmx as XML and .tmj/.json as JSON
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, UbiError> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("tmx") => tiled::load_tmx(path),
+            Some("tmj") | Some("json") => tiled::load_tmj(path),
+            _ => Err(UbiError::ResourceError(format!(
+                "{}: unknown map format, expected .tmx or .tmj",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn tileset_for(&self, gid: u32) -> Option<(usize, &Tileset)> {
+        let gid = tile_gid(gid);
+        self.tilesets
+            .iter()
+            .enumerate()
+            .find(|(_, tileset)| tileset.contains(gid))
+    }
+
+    pub fn tile_layers(&self) -> impl Iterator<Item = &TileLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            MapLayer::Tiles(tiles) => Some(tiles),
+            MapLayer::Objects(_) => None,
+        })
+    }
+
+    pub fn object_layers(&self) -> impl Iterator<Item = &ObjectLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            MapLayer::Objects(objects) => Some(objects),
+            MapLayer::Tiles(_) => None,
+        })
+    }
+
+    // Bottom left corner of the bounding box of a map cell, in world space with y up
+    pub fn cell_position(&self, column: u32, row: u32) -> [f32; 2] {
+        let (tw, th) = (self.tile_width as f32, self.tile_height as f32);
+        let (column, row) = (column as f32, row as f32);
+        match self.orientation {
+            MapOrientation::Orthogonal => [column * tw, -(row + 1.0) * th],
+            MapOrientation::Isometric => [
+                (column - row) * tw * 0.5 - tw * 0.5,
+                -((column + row) * th * 0.5 + th),
+            ],
+        }
+    }
+
+    // Position of an object in world space with y up
+    pub fn object_position(&self, object: &TiledObject) -> [f32; 2] {
+        match self.orientation {
+            MapOrientation::Orthogonal => [object.x, -object.y],
+            // Isometric objects are measured in tile heights along both map axes
+            MapOrientation::Isometric => {
+                let th = self.tile_height.max(1) as f32;
+                let (column, row) = (object.x / th, object.y / th);
+                let tw = self.tile_width as f32;
+                [
+                    (column - row) * tw * 0.5,
+                    -(column + row) * self.tile_height as f32 * 0.5,
+                ]
+            }
+        }
+    }
+
+    // Spawn one entity per object with a `TiledObject` component
+    pub fn spawn_objects(&self, world: &mut World) -> Vec<Entity> {
+        self.object_layers()
+            .flat_map(|layer| layer.objects.iter())
+            .map(|object| world.spawn().with(object.clone()).build())
+            .collect()
+    }
+}
@@ -0,0 +1,320 @@
+use crate::core::custom_error::UbiError;
+use crate::core::math::transform::Mat4;
+use crate::ubiwarn;
+
+use super::buffer::{Ibo, Vao, Vbo, VertexLayout};
+use super::gl_state;
+use super::pipeline::PipelineState;
+use super::shader::Program;
+use super::texture::Texture;
+use super::tilemap::{
+    tile_gid, MapOrientation, Tilemap, Tileset, FLIPPED_DIAGONALLY, FLIPPED_HORIZONTALLY,
+    FLIPPED_VERTICALLY,
+};
+
+// Width and height in tiles of the meshes a layer is split into
+pub const CHUNK_SIZE: u32 = 32;
+
+// position(2) + uv(2)
+const FLOATS_PER_VERTEX: usize = 4;
+const FLOATS_PER_QUAD: usize = 4 * FLOATS_PER_VERTEX;
+
+struct AnimatedTile {
+    quad: usize,
+    // Local id of the tile that owns the animation
+    tile: u32,
+    // Raw global id, keeps the flip flags
+    raw: u32,
+    shown: u32,
+}
+
+// The tiles of one chunk of one layer that use the same tileset
+struct ChunkMesh {
+    vao: Vao,
+    vbo: Vbo,
+    _ibo: Ibo,
+    layer: usize,
+    tileset: usize,
+    quads: usize,
+    vertices: Vec<f32>,
+    animated: Vec<AnimatedTile>,
+}
+
+struct LayerState {
+    visible: bool,
+    opacity: f32,
+}
+
+// Draws the tile layers of a `Tilemap` with one static mesh per chunk and tileset.
+// Animated tiles only rewrite the uvs of their chunk when their frame changes.
+pub struct TilemapRenderer {
+    program: Program,
+    tilesets: Vec<(Tileset, Texture)>,
+    layers: Vec<LayerState>,
+    chunks: Vec<ChunkMesh>,
+    elapsed: f32,
+    pipeline: PipelineState,
+    draw_calls: u32,
+}
+
+impl TilemapRenderer {
+    pub fn new(map: &Tilemap) -> Result<Self, UbiError> {
+        let program = Program::from_sources(
+            include_str!("../../../assets/shaders/tilemap.vert"),
+            include_str!("../../../assets/shaders/tilemap.frag"),
+        )?;
+        program.set();
+        program.set_int("u_tileset", 0);
+
+        let mut tilesets = Vec::with_capacity(map.tilesets.len());
+        for tileset in &map.tilesets {
+            let path = tileset.image.to_string_lossy();
+            let mut texture = Texture::from_file(&path).map_err(|e| {
+                UbiError::ResourceError(format!("tileset \"{}\": {}", tileset.name, e))
+            })?;
+            // Filtering across tile edges shows seams between neighbouring tiles
+            texture.set_sampling(gl::NEAREST);
+            tilesets.push((tileset.clone(), texture));
+        }
+
+        let mut renderer = Self {
+            program,
+            tilesets,
+            layers: Vec::new(),
+            chunks: Vec::new(),
+            elapsed: 0.0,
+            pipeline: PipelineState::OVERLAY,
+            draw_calls: 0,
+        };
+        renderer.build_chunks(map);
+        Ok(renderer)
+    }
+
+    // Tile layers in file order, object layers are not counted
+    pub fn set_layer_visible(&mut self, layer: usize, visible: bool) {
+        if let Some(state) = self.layers.get_mut(layer) {
+            state.visible = visible;
+        }
+    }
+
+    pub fn set_layer_opacity(&mut self, layer: usize, opacity: f32) {
+        if let Some(state) = self.layers.get_mut(layer) {
+            state.opacity = opacity;
+        }
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    // Draw calls of the last `draw`
+    pub fn draw_calls(&self) -> u32 {
+        self.draw_calls
+    }
+
+    // `PipelineState::OVERLAY` by default
+    pub fn set_pipeline(&mut self, pipeline: PipelineState) {
+        self.pipeline = pipeline;
+    }
+
+    // Advance tile animations by `delta` seconds
+    pub fn update(&mut self, delta: f32) {
+        self.elapsed += delta;
+        for chunk in &mut self.chunks {
+            if chunk.animated.is_empty() {
+                continue;
+            }
+            let tileset = &self.tilesets[chunk.tileset].0;
+            let mut dirty = false;
+            for animated in &mut chunk.animated {
+                let frames = &tileset.animations[&animated.tile];
+                let frame = animation_frame(frames, self.elapsed);
+                if frame != animated.shown {
+                    animated.shown = frame;
+                    let uvs = tile_uvs(tileset, frame, animated.raw);
+                    let quad = &mut chunk.vertices
+                        [animated.quad * FLOATS_PER_QUAD..(animated.quad + 1) * FLOATS_PER_QUAD];
+                    for (corner, [u, v]) in uvs.into_iter().enumerate() {
+                        quad[corner * FLOATS_PER_VERTEX + 2] = u;
+                        quad[corner * FLOATS_PER_VERTEX + 3] = v;
+                    }
+                    dirty = true;
+                }
+            }
+            if dirty {
+                chunk.vbo.sub_data(0, &chunk.vertices);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, view_projection: &Mat4) {
+        self.draw_calls = 0;
+        self.program.set();
+        self.program.set_mat4("u_view_projection", view_projection);
+        self.pipeline.apply();
+
+        for chunk in &self.chunks {
+            let layer = &self.layers[chunk.layer];
+            if !layer.visible || layer.opacity <= 0.0 {
+                continue;
+            }
+            self.program.set_float("u_opacity", layer.opacity);
+            self.tilesets[chunk.tileset].1.bind_unit(0);
+            chunk.vao.bind();
+            unsafe {
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    (chunk.quads * 6) as i32,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                );
+            }
+            self.draw_calls += 1;
+        }
+        gl_state::bind_vertex_array(0);
+    }
+
+    fn build_chunks(&mut self, map: &Tilemap) {
+        let layout = VertexLayout::new().with(0, 2).with(1, 2);
+        for (layer_index, layer) in map.tile_layers().enumerate() {
+            self.layers.push(LayerState {
+                visible: layer.visible,
+                opacity: layer.opacity,
+            });
+
+            for chunk_y in (0..layer.height).step_by(CHUNK_SIZE as usize) {
+                for chunk_x in (0..layer.width).step_by(CHUNK_SIZE as usize) {
+                    // Vertices and animated tiles per tileset
+                    let mut meshes: Vec<(Vec<f32>, Vec<AnimatedTile>)> = (0..self.tilesets.len())
+                        .map(|_| Default::default())
+                        .collect();
+
+                    for row in chunk_y..(chunk_y + CHUNK_SIZE).min(layer.height) {
+                        for column in chunk_x..(chunk_x + CHUNK_SIZE).min(layer.width) {
+                            let raw = layer.tile(column, row);
+                            if tile_gid(raw) == 0 {
+                                continue;
+                            }
+                            let Some((index, tileset)) = map.tileset_for(raw) else {
+                                ubiwarn!(
+                                    "Tile {} of layer \"{}\" has no tileset",
+                                    tile_gid(raw),
+                                    layer.name
+                                );
+                                continue;
+                            };
+                            let local = tile_gid(raw) - tileset.first_gid;
+                            let (vertices, animated) = &mut meshes[index];
+
+                            let [mut left, bottom] = map.cell_position(column, row);
+                            if map.orientation == MapOrientation::Isometric {
+                                left += (map.tile_width as f32 - tileset.tile_width as f32) * 0.5;
+                            }
+                            let left = left + layer.offset[0];
+                            let bottom = bottom - layer.offset[1];
+                            let right = left + tileset.tile_width as f32;
+                            let top = bottom + tileset.tile_height as f32;
+
+                            if tileset.animations.contains_key(&local) {
+                                animated.push(AnimatedTile {
+                                    quad: vertices.len() / FLOATS_PER_QUAD,
+                                    tile: local,
+                                    raw,
+                                    shown: local,
+                                });
+                            }
+
+                            let uvs = tile_uvs(tileset, local, raw);
+                            let corners =
+                                [(left, bottom), (right, bottom), (right, top), (left, top)];
+                            for ((x, y), [u, v]) in corners.into_iter().zip(uvs) {
+                                vertices.extend_from_slice(&[x, y, u, v]);
+                            }
+                        }
+                    }
+
+                    for (tileset, (vertices, animated)) in meshes.into_iter().enumerate() {
+                        if vertices.is_empty() {
+                            continue;
+                        }
+                        let quads = vertices.len() / FLOATS_PER_QUAD;
+                        let vao = Vao::gen();
+                        let vbo = Vbo::gen();
+                        let ibo = Ibo::gen();
+                        vao.bind();
+                        vbo.allocate(vertices.len() * std::mem::size_of::<f32>());
+                        vbo.sub_data(0, &vertices);
+                        vao.set_layout(&layout);
+                        let mut indices = Vec::with_capacity(quads * 6);
+                        for quad in 0..quads as u32 {
+                            let base = quad * 4;
+                            indices.extend_from_slice(&[
+                                base,
+                                base + 1,
+                                base + 2,
+                                base + 2,
+                                base + 3,
+                                base,
+                            ]);
+                        }
+                        ibo.set(&indices);
+                        vao.unbind();
+
+                        self.chunks.push(ChunkMesh {
+                            vao,
+                            vbo,
+                            _ibo: ibo,
+                            layer: layer_index,
+                            tileset,
+                            quads,
+                            vertices,
+                            animated,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Local tile id shown `elapsed` seconds into a looping animation
+fn animation_frame(frames: &[super::tilemap::TileAnimationFrame], elapsed: f32) -> u32 {
+    let total: f32 = frames.iter().map(|f| f.duration).sum();
+    if frames.is_empty() || total <= 0.0 {
+        return frames.first().map(|f| f.tile).unwrap_or(0);
+    }
+    let mut time = elapsed % total;
+    for frame in frames {
+        if time < frame.duration {
+            return frame.tile;
+        }
+        time -= frame.duration;
+    }
+    frames[frames.len() - 1].tile
+}
+
+// Uvs of the bottom left, bottom right, top right and top left corners of a tile quad
+fn tile_uvs(tileset: &Tileset, local: u32, raw: u32) -> [[f32; 2]; 4] {
+    let [x, y, w, h] = tileset.tile_rect(local);
+    let (image_w, image_h) = (
+        tileset.image_width.max(1) as f32,
+        tileset.image_height.max(1) as f32,
+    );
+    let (u0, u1) = (x as f32 / image_w, (x + w) as f32 / image_w);
+    // Images are uploaded bottom row first
+    let (v0, v1) = (1.0 - (y + h) as f32 / image_h, 1.0 - y as f32 / image_h);
+
+    [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(mut cx, mut cy): (f32, f32)| {
+        // Tiled applies the diagonal flip first, so the uv lookup undoes them in reverse
+        if raw & FLIPPED_VERTICALLY != 0 {
+            cy = 1.0 - cy;
+        }
+        if raw & FLIPPED_HORIZONTALLY != 0 {
+            cx = 1.0 - cx;
+        }
+        if raw & FLIPPED_DIAGONALLY != 0 {
+            (cx, cy) = (1.0 - cy, 1.0 - cx);
+        }
+        [u0 + cx * (u1 - u0), v0 + cy * (v1 - v0)]
+    })
+}
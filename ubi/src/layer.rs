@@ -1,10 +1,24 @@
+use crate::core::time::Time;
+use crate::event::bus::EventBus;
 use crate::event::event::Event;
+use crate::event::input::Input;
+use crate::graphics::render_queue::RenderQueue;
 
 pub trait Layer {
     fn on_attach(&mut self);
     fn on_detach(&mut self);
+    // Events pushed here, `Event::Custom` too, go through `on_event` this frame
     fn on_update(&mut self, events: &mut Vec<Event>);
     fn on_event(&mut self, event: &mut Event);
+    // Submit the draws of the frame, executed by the renderer before `on_update`
+    fn on_render(&mut self, _queue: &mut RenderQueue) {}
+    // Frame clock, called first in the update of every layer
+    fn on_time(&mut self, _time: &Time) {}
+    // Input state after the events of the last frame, called before `on_update`
+    fn on_input(&mut self, _input: &Input) {}
+    // Send and read typed events, called after `on_update`. Events stay readable until the
+    // end of the next frame, so layers below the sender still see them once
+    fn on_event_bus(&mut self, _bus: &mut EventBus) {}
 }
 
 // stack example 
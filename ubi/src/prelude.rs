@@ -1,8 +1,13 @@
 // Core modules
 pub use crate::core::math::transform::*;
+pub use crate::core::math::bounds::*;
 pub use crate::core::application::application::Application;
 pub use crate::core::custom_error::UbiError;
+pub use crate::core::time::Time;
 pub use crate::core::logger::init as init_logger;
+pub use crate::core::ecs::entity::Entity;
+pub use crate::core::ecs::transform::{propagate_transforms, GlobalTransform, Parent, Transform};
+pub use crate::core::ecs::world::{EntityBuilder, World};
 pub use crate::appdebug;
 pub use crate::apperror;
 pub use crate::appinfo;
@@ -11,11 +16,43 @@ pub use crate::appwarn;
 
 // Graphics modules
 pub use crate::graphics::buffer::*;
+pub use crate::graphics::camera::*;
+pub use crate::graphics::cubemap::*;
+pub use crate::graphics::culling::*;
+pub use crate::graphics::deferred::*;
+pub use crate::graphics::environment::*;
+pub use crate::graphics::font::*;
+pub use crate::graphics::forward::*;
+pub use crate::graphics::framebuffer::*;
+pub use crate::graphics::instancing::*;
+pub use crate::graphics::light::*;
+pub use crate::graphics::material::*;
+pub use crate::graphics::mesh::*;
+pub use crate::graphics::pipeline::*;
+pub use crate::graphics::obj::*;
+pub use crate::graphics::render::{RenderPath, Renderer};
+pub use crate::graphics::render_graph::*;
+pub use crate::graphics::render_queue::*;
 pub use crate::graphics::shader::*;
+pub use crate::graphics::shadow::*;
+pub use crate::graphics::sprite_animation::*;
+pub use crate::graphics::sprite_batch::*;
+pub use crate::graphics::sprite_sheet::*;
+pub use crate::graphics::text::*;
 pub use crate::graphics::texture::*;
+pub use crate::graphics::texture_atlas::*;
+pub use crate::graphics::tilemap::*;
+pub use crate::graphics::tilemap_renderer::*;
 // Windows modules
 pub use crate::window::wind_sdl::SdlWindow;
-pub use crate::window::window_trait::{UBIWindow, WindowData};
+pub use crate::window::virtual_gamepad::VirtualGamepad;
+pub use crate::window::window_trait::{Clipboard, UBIWindow, WindowData};
 pub use crate::layer::Layer;
 pub use crate::egui::egui_layer::EguiLayer;
-pub use crate::event::event::Event;
+pub use crate::event::bus::{EventBus, EventReader, EventWriter, Events};
+pub use crate::event::event::{Event, EventCategory, EventData, EventDispatcher};
+pub use crate::event::action::*;
+pub use crate::event::record::{InputRecorder, RecordedFrame, Recording, Replay};
+pub use crate::event::input::{
+    GamepadAxis, GamepadButton, GamepadState, Input, KeyCode, Modifiers, MouseButton, ScanCode,
+};
@@ -1,2 +1,4 @@
 pub mod window_trait;
-pub mod wind_sdl;
\ No newline at end of file
+pub mod wind_sdl;
+pub mod sdl_input;
+pub mod virtual_gamepad;
\ No newline at end of file
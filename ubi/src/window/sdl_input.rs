@@ -0,0 +1,244 @@
+// SDL keyboard, mouse and game controller values to the engine ones
+use sdl2::controller::{Axis, Button};
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+
+use crate::event::input::{GamepadAxis, GamepadButton, KeyCode, Modifiers, MouseButton, ScanCode};
+
+// Engine key => SDL keycode, SDL scancode
+macro_rules! key_table {
+    ($($key:ident => $keycode:ident, $scancode:ident;)*) => {
+        pub(crate) fn key_code(keycode: Option<Keycode>) -> KeyCode {
+            match keycode {
+                $(Some(Keycode::$keycode) => KeyCode::$key,)*
+                _ => KeyCode::Unknown,
+            }
+        }
+
+        pub(crate) fn scan_code(scancode: Option<Scancode>) -> ScanCode {
+            match scancode {
+                $(Some(Scancode::$scancode) => ScanCode::$key,)*
+                _ => ScanCode::Unknown,
+            }
+        }
+    };
+}
+
+key_table! {
+    A => A, A;
+    B => B, B;
+    C => C, C;
+    D => D, D;
+    E => E, E;
+    F => F, F;
+    G => G, G;
+    H => H, H;
+    I => I, I;
+    J => J, J;
+    K => K, K;
+    L => L, L;
+    M => M, M;
+    N => N, N;
+    O => O, O;
+    P => P, P;
+    Q => Q, Q;
+    R => R, R;
+    S => S, S;
+    T => T, T;
+    U => U, U;
+    V => V, V;
+    W => W, W;
+    X => X, X;
+    Y => Y, Y;
+    Z => Z, Z;
+    Num0 => NUM_0, Num0;
+    Num1 => NUM_1, Num1;
+    Num2 => NUM_2, Num2;
+    Num3 => NUM_3, Num3;
+    Num4 => NUM_4, Num4;
+    Num5 => NUM_5, Num5;
+    Num6 => NUM_6, Num6;
+    Num7 => NUM_7, Num7;
+    Num8 => NUM_8, Num8;
+    Num9 => NUM_9, Num9;
+    Enter => RETURN, Return;
+    Escape => ESCAPE, Escape;
+    Backspace => BACKSPACE, Backspace;
+    Tab => TAB, Tab;
+    Space => SPACE, Space;
+    Minus => MINUS, Minus;
+    Equals => EQUALS, Equals;
+    LeftBracket => LEFTBRACKET, LeftBracket;
+    RightBracket => RIGHTBRACKET, RightBracket;
+    Backslash => BACKSLASH, Backslash;
+    Semicolon => SEMICOLON, Semicolon;
+    Apostrophe => QUOTE, Apostrophe;
+    Grave => BACKQUOTE, Grave;
+    Comma => COMMA, Comma;
+    Period => PERIOD, Period;
+    Slash => SLASH, Slash;
+    CapsLock => CAPSLOCK, CapsLock;
+    ScrollLock => SCROLLLOCK, ScrollLock;
+    NumLock => NUMLOCKCLEAR, NumLockClear;
+    PrintScreen => PRINTSCREEN, PrintScreen;
+    Pause => PAUSE, Pause;
+    Menu => APPLICATION, Application;
+    F1 => F1, F1;
+    F2 => F2, F2;
+    F3 => F3, F3;
+    F4 => F4, F4;
+    F5 => F5, F5;
+    F6 => F6, F6;
+    F7 => F7, F7;
+    F8 => F8, F8;
+    F9 => F9, F9;
+    F10 => F10, F10;
+    F11 => F11, F11;
+    F12 => F12, F12;
+    F13 => F13, F13;
+    F14 => F14, F14;
+    F15 => F15, F15;
+    F16 => F16, F16;
+    F17 => F17, F17;
+    F18 => F18, F18;
+    F19 => F19, F19;
+    F20 => F20, F20;
+    F21 => F21, F21;
+    F22 => F22, F22;
+    F23 => F23, F23;
+    F24 => F24, F24;
+    Insert => INSERT, Insert;
+    Delete => DELETE, Delete;
+    Home => HOME, Home;
+    End => END, End;
+    PageUp => PAGEUP, PageUp;
+    PageDown => PAGEDOWN, PageDown;
+    Left => LEFT, Left;
+    Right => RIGHT, Right;
+    Up => UP, Up;
+    Down => DOWN, Down;
+    Kp0 => KP_0, Kp0;
+    Kp1 => KP_1, Kp1;
+    Kp2 => KP_2, Kp2;
+    Kp3 => KP_3, Kp3;
+    Kp4 => KP_4, Kp4;
+    Kp5 => KP_5, Kp5;
+    Kp6 => KP_6, Kp6;
+    Kp7 => KP_7, Kp7;
+    Kp8 => KP_8, Kp8;
+    Kp9 => KP_9, Kp9;
+    KpDivide => KP_DIVIDE, KpDivide;
+    KpMultiply => KP_MULTIPLY, KpMultiply;
+    KpMinus => KP_MINUS, KpMinus;
+    KpPlus => KP_PLUS, KpPlus;
+    KpEnter => KP_ENTER, KpEnter;
+    KpPeriod => KP_PERIOD, KpPeriod;
+    KpEquals => KP_EQUALS, KpEquals;
+    LShift => LSHIFT, LShift;
+    RShift => RSHIFT, RShift;
+    LCtrl => LCTRL, LCtrl;
+    RCtrl => RCTRL, RCtrl;
+    LAlt => LALT, LAlt;
+    RAlt => RALT, RAlt;
+    LSuper => LGUI, LGui;
+    RSuper => RGUI, RGui;
+}
+
+pub(crate) fn mouse_button(button: sdl2::mouse::MouseButton) -> MouseButton {
+    match button {
+        sdl2::mouse::MouseButton::Left => MouseButton::Left,
+        sdl2::mouse::MouseButton::Middle => MouseButton::Middle,
+        sdl2::mouse::MouseButton::Right => MouseButton::Right,
+        sdl2::mouse::MouseButton::X1 => MouseButton::X1,
+        sdl2::mouse::MouseButton::X2 => MouseButton::X2,
+        sdl2::mouse::MouseButton::Unknown => MouseButton::Unknown,
+    }
+}
+
+pub(crate) fn modifiers(keymod: Mod) -> Modifiers {
+    let mut modifiers = Modifiers::NONE;
+    for (sdl, flag) in [
+        (Mod::LSHIFTMOD | Mod::RSHIFTMOD, Modifiers::SHIFT),
+        (Mod::LCTRLMOD | Mod::RCTRLMOD, Modifiers::CTRL),
+        (Mod::LALTMOD | Mod::RALTMOD, Modifiers::ALT),
+        (Mod::LGUIMOD | Mod::RGUIMOD, Modifiers::SUPER),
+        (Mod::CAPSMOD, Modifiers::CAPS_LOCK),
+        (Mod::NUMMOD, Modifiers::NUM_LOCK),
+    ] {
+        if keymod.intersects(sdl) {
+            modifiers |= flag;
+        }
+    }
+    modifiers
+}
+
+// Engine gamepad button <=> SDL game controller button
+macro_rules! gamepad_button_table {
+    ($($button:ident => $sdl:ident;)*) => {
+        pub(crate) fn gamepad_button(button: Button) -> GamepadButton {
+            match button {
+                $(Button::$sdl => GamepadButton::$button,)*
+            }
+        }
+
+        pub(crate) fn sdl_gamepad_button(button: GamepadButton) -> Button {
+            match button {
+                $(GamepadButton::$button => Button::$sdl,)*
+            }
+        }
+    };
+}
+
+gamepad_button_table! {
+    South => A;
+    East => B;
+    West => X;
+    North => Y;
+    Back => Back;
+    Guide => Guide;
+    Start => Start;
+    LeftStick => LeftStick;
+    RightStick => RightStick;
+    LeftShoulder => LeftShoulder;
+    RightShoulder => RightShoulder;
+    DPadUp => DPadUp;
+    DPadDown => DPadDown;
+    DPadLeft => DPadLeft;
+    DPadRight => DPadRight;
+    Misc => Misc1;
+    Paddle1 => Paddle1;
+    Paddle2 => Paddle2;
+    Paddle3 => Paddle3;
+    Paddle4 => Paddle4;
+    Touchpad => Touchpad;
+}
+
+pub(crate) fn gamepad_axis(axis: Axis) -> GamepadAxis {
+    match axis {
+        Axis::LeftX => GamepadAxis::LeftX,
+        Axis::LeftY => GamepadAxis::LeftY,
+        Axis::RightX => GamepadAxis::RightX,
+        Axis::RightY => GamepadAxis::RightY,
+        Axis::TriggerLeft => GamepadAxis::LeftTrigger,
+        Axis::TriggerRight => GamepadAxis::RightTrigger,
+    }
+}
+
+pub(crate) fn sdl_gamepad_axis(axis: GamepadAxis) -> Axis {
+    match axis {
+        GamepadAxis::LeftX => Axis::LeftX,
+        GamepadAxis::LeftY => Axis::LeftY,
+        GamepadAxis::RightX => Axis::RightX,
+        GamepadAxis::RightY => Axis::RightY,
+        GamepadAxis::LeftTrigger => Axis::TriggerLeft,
+        GamepadAxis::RightTrigger => Axis::TriggerRight,
+    }
+}
+
+// SDL axes go from -32768 to 32767
+pub(crate) fn gamepad_axis_value(value: i16) -> f32 {
+    (value as f32 / i16::MAX as f32).max(-1.0)
+}
+
+pub(crate) fn sdl_gamepad_axis_value(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
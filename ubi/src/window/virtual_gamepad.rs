@@ -0,0 +1,88 @@
+// Gamepad simulated by SDL, reported and mapped like a real one, to drive the gamepad code
+// from tests or scripts without hardware. Needs SDL initialized, so create the window first.
+use sdl2::sys;
+
+use crate::core::custom_error::UbiError;
+use crate::event::input::{GamepadAxis, GamepadButton};
+use crate::window::sdl_input::{sdl_gamepad_axis, sdl_gamepad_axis_value, sdl_gamepad_button};
+
+pub struct VirtualGamepad {
+    joystick: *mut sys::SDL_Joystick,
+    id: u32,
+}
+
+impl VirtualGamepad {
+    // Plug in the gamepad, the next `poll_events` reports it connected
+    pub fn attach() -> Result<Self, UbiError> {
+        unsafe {
+            if sys::SDL_WasInit(sys::SDL_INIT_GAMECONTROLLER) == 0 {
+                return Err(error("the game controller subsystem is not initialized"));
+            }
+            // Buttons and axes in SDL order get the default game controller mapping
+            let device_index = sys::SDL_JoystickAttachVirtual(
+                sys::SDL_JoystickType::SDL_JOYSTICK_TYPE_GAMECONTROLLER,
+                sys::SDL_GameControllerAxis::SDL_CONTROLLER_AXIS_MAX as i32,
+                sys::SDL_GameControllerButton::SDL_CONTROLLER_BUTTON_MAX as i32,
+                0,
+            );
+            if device_index < 0 {
+                return Err(error(&sdl2::get_error()));
+            }
+            let joystick = sys::SDL_JoystickOpen(device_index);
+            if joystick.is_null() {
+                let message = sdl2::get_error();
+                sys::SDL_JoystickDetachVirtual(device_index);
+                return Err(error(&message));
+            }
+            let id = sys::SDL_JoystickInstanceID(joystick) as u32;
+            Ok(Self { joystick, id })
+        }
+    }
+
+    // Same id as in the gamepad events
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn set_button(&mut self, button: GamepadButton, pressed: bool) -> Result<(), UbiError> {
+        let index = sdl_gamepad_button(button) as i32;
+        let result =
+            unsafe { sys::SDL_JoystickSetVirtualButton(self.joystick, index, pressed as u8) };
+        check(result)
+    }
+
+    // -1.0 to 1.0 for the sticks, 0.0 to 1.0 for the triggers
+    pub fn set_axis(&mut self, axis: GamepadAxis, value: f32) -> Result<(), UbiError> {
+        let index = sdl_gamepad_axis(axis) as i32;
+        let value = sdl_gamepad_axis_value(value);
+        let result = unsafe { sys::SDL_JoystickSetVirtualAxis(self.joystick, index, value) };
+        check(result)
+    }
+}
+
+impl Drop for VirtualGamepad {
+    // Unplug the gamepad, detaching goes by device index which changes as devices come and go
+    fn drop(&mut self) {
+        unsafe {
+            sys::SDL_JoystickClose(self.joystick);
+            for device_index in 0..sys::SDL_NumJoysticks() {
+                if sys::SDL_JoystickGetDeviceInstanceID(device_index) as u32 == self.id {
+                    sys::SDL_JoystickDetachVirtual(device_index);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn check(result: i32) -> Result<(), UbiError> {
+    if result < 0 {
+        Err(error(&sdl2::get_error()))
+    } else {
+        Ok(())
+    }
+}
+
+fn error(message: &str) -> UbiError {
+    UbiError::WindowError(format!("virtual gamepad: {}", message))
+}
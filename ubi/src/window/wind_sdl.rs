@@ -1,21 +1,88 @@
 use crate::core::custom_error::UbiError;
-use crate::event::event::Event::{WindowClose, WindowResize, MouseButtonPressed};
-use crate::event::event_data::{MouseButtonPressedEventData, WindowCloseEventData, WindowResizeEventData};
-use crate::ubiinfo;
-use crate::window::window_trait::{UBIWindow, WindowData};
+use crate::event::event::Event::{
+    DropBegin, DropComplete, FileDropped, GamepadAxisMoved, GamepadButtonPressed,
+    GamepadButtonReleased, GamepadConnected, GamepadDisconnected, KeyPressed, KeyReleased,
+    MouseButtonPressed, MouseButtonReleased, MouseEntered, MouseLeft, MouseMoved, MouseScroll,
+    TextEditing, TextInput, WindowClose, WindowFocus, WindowLostFocus, WindowMaximized,
+    WindowMinimized, WindowMoved, WindowResize, WindowRestored, WindowScaleChanged,
+};
+use crate::event::event_data::{
+    DropBeginEventData, DropCompleteEventData, FileDroppedEventData, GamepadAxisMovedEventData,
+    GamepadButtonPressedEventData, GamepadButtonReleasedEventData, GamepadConnectedEventData,
+    GamepadDisconnectedEventData, KeyPressedEventData, KeyReleasedEventData,
+    MouseButtonPressedEventData, MouseButtonReleasedEventData, MouseEnteredEventData,
+    MouseLeftEventData, MouseMovedEventData, MouseScrollEventData, TextEditingEventData,
+    TextInputEventData, WindowCloseEventData, WindowFocusEventData, WindowLostFocusEventData,
+    WindowMaximizedEventData, WindowMinimizedEventData, WindowMovedEventData,
+    WindowResizeEventData, WindowRestoredEventData, WindowScaleChangedEventData,
+};
+use crate::event::input::ScanCode;
+use crate::graphics::gl_state;
+use crate::window::sdl_input::{
+    gamepad_axis, gamepad_axis_value, gamepad_button, key_code, modifiers, mouse_button, scan_code,
+};
+use crate::window::window_trait::{Clipboard, UBIWindow, WindowData};
+use crate::{ubiinfo, ubiwarn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use sdl2::{
+    controller::GameController,
     video::{GLContext, SwapInterval, Window},
-    EventPump, Sdl,
+    EventPump, GameControllerSubsystem, Sdl, VideoSubsystem,
 };
 
+// Clipboard of the SDL video subsystem
+pub struct SdlClipboard {
+    video: VideoSubsystem,
+}
+
+impl Clipboard for SdlClipboard {
+    fn get_text(&self) -> Result<String, UbiError> {
+        self.video
+            .clipboard()
+            .clipboard_text()
+            .map_err(UbiError::WindowError)
+    }
+
+    fn set_text(&self, text: &str) -> Result<(), UbiError> {
+        self.video
+            .clipboard()
+            .set_clipboard_text(text)
+            .map_err(UbiError::WindowError)
+    }
+}
+
 pub struct SdlWindow {
     pub sdl: Sdl,
     pub window: Rc<Window>,
     pub gl_context: GLContext,
     pub gl: (),
     pub event_pump: EventPump,
+    pub controller: GameControllerSubsystem,
+    // Open gamepads by id, closed when dropped
+    gamepads: HashMap<u32, GameController>,
+    // Repeats of the keys held down, for `KeyPressedEventData::get_repeat_count`
+    key_repeats: HashMap<ScanCode, u32>,
+    // Display scale of the last `WindowScaleChanged`
+    scale: f32,
+}
+
+impl SdlWindow {
+    // HiDPI backends render to a larger drawable, the others report the DPI of the display
+    fn display_scale(&self) -> f32 {
+        let (width, _) = self.window.size();
+        let (drawable_width, _) = self.window.drawable_size();
+        if drawable_width > width {
+            return drawable_width as f32 / width.max(1) as f32;
+        }
+        self.window
+            .display_index()
+            .and_then(|index| self.window.subsystem().display_dpi(index))
+            .map(|(diagonal, _, _)| diagonal / 96.0)
+            .unwrap_or(1.0)
+    }
 }
 
 impl UBIWindow for SdlWindow {
@@ -67,21 +134,97 @@ impl UBIWindow for SdlWindow {
             .gl_set_swap_interval(SwapInterval::VSync)
             .unwrap();
 
+        // SDL starts text input with the video subsystem, keep the IME closed until asked
+        video_subsystem.text_input().stop();
+
         let event_pump: sdl2::EventPump = sdl.event_pump().unwrap();
+        // Gamepads already plugged in are reported as connected by the first poll
+        let controller = sdl.game_controller().map_err(UbiError::WindowError)?;
 
-        Ok(SdlWindow {
+        let mut window = SdlWindow {
             sdl,
             window: window.into(),
             gl_context,
             gl,
             event_pump,
-        })
+            controller,
+            gamepads: HashMap::new(),
+            key_repeats: HashMap::new(),
+            scale: 1.0,
+        };
+        window.scale = window.display_scale();
+        Ok(window)
     }
 
     fn get_size(&self) -> (u32, u32) {
         self.window.size()
     }
 
+    fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    fn rumble_gamepad(
+        &mut self,
+        id: u32,
+        low_frequency: f32,
+        high_frequency: f32,
+        duration_ms: u32,
+    ) -> Result<(), UbiError> {
+        let gamepad = self
+            .gamepads
+            .get_mut(&id)
+            .ok_or_else(|| UbiError::WindowError(format!("no gamepad {}", id)))?;
+        let intensity = |value: f32| (value.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        gamepad
+            .set_rumble(
+                intensity(low_frequency),
+                intensity(high_frequency),
+                duration_ms,
+            )
+            .map_err(|e| UbiError::WindowError(e.to_string()))
+    }
+
+    fn load_gamepad_mappings(&mut self, path: &Path) -> Result<u32, UbiError> {
+        self.controller
+            .load_mappings(path)
+            .map(|count| count.max(0) as u32)
+            .map_err(|e| UbiError::ResourceError(format!("{}: {}", path.display(), e)))
+    }
+
+    fn start_text_input(&self) {
+        self.window.subsystem().text_input().start();
+    }
+
+    fn stop_text_input(&self) {
+        self.window.subsystem().text_input().stop();
+    }
+
+    fn is_text_input_active(&self) -> bool {
+        self.window.subsystem().text_input().is_active()
+    }
+
+    fn set_text_input_rect(&self, x: i32, y: i32, width: u32, height: u32) {
+        self.window
+            .subsystem()
+            .text_input()
+            .set_rect(sdl2::rect::Rect::new(x, y, width, height));
+    }
+
+    fn get_clipboard_text(&self) -> Result<String, UbiError> {
+        self.clipboard().get_text()
+    }
+
+    fn set_clipboard_text(&self, text: &str) -> Result<(), UbiError> {
+        self.clipboard().set_text(text)
+    }
+
+    fn clipboard(&self) -> Rc<dyn Clipboard> {
+        Rc::new(SdlClipboard {
+            video: self.window.subsystem().clone(),
+        })
+    }
+
     fn swap_buffers(&self) {
         self.window.gl_swap_window()
     }
@@ -90,6 +233,8 @@ impl UBIWindow for SdlWindow {
         &mut self,
         events: &mut Vec<crate::event::event::Event>,
     ) -> Result<(), UbiError> {
+        // Moving between monitors may change the scale, checked once the events are read
+        let mut check_scale = false;
         for event in self.event_pump.poll_iter() {
             match event {
                 // Window close event
@@ -97,33 +242,231 @@ impl UBIWindow for SdlWindow {
                     events.push(WindowClose(WindowCloseEventData::default()));
                 }
 
-                // Window resize event
+                // Window lifecycle events
                 sdl2::event::Event::Window { win_event, .. } => match win_event {
                     sdl2::event::WindowEvent::Resized(width, height) => {
                         events.push(WindowResize(WindowResizeEventData::new(width, height)));
+                        check_scale = true;
+                    }
+                    sdl2::event::WindowEvent::Moved(x, y) => {
+                        events.push(WindowMoved(WindowMovedEventData::new(x, y)));
+                        check_scale = true;
+                    }
+                    sdl2::event::WindowEvent::DisplayChanged(_) => {
+                        check_scale = true;
+                    }
+                    sdl2::event::WindowEvent::FocusGained => {
+                        events.push(WindowFocus(WindowFocusEventData::default()));
+                    }
+                    sdl2::event::WindowEvent::FocusLost => {
+                        events.push(WindowLostFocus(WindowLostFocusEventData::default()));
+                    }
+                    sdl2::event::WindowEvent::Minimized => {
+                        events.push(WindowMinimized(WindowMinimizedEventData::default()));
+                    }
+                    sdl2::event::WindowEvent::Maximized => {
+                        events.push(WindowMaximized(WindowMaximizedEventData::default()));
+                    }
+                    sdl2::event::WindowEvent::Restored => {
+                        events.push(WindowRestored(WindowRestoredEventData::default()));
+                    }
+                    sdl2::event::WindowEvent::Enter => {
+                        events.push(MouseEntered(MouseEnteredEventData::default()));
+                    }
+                    sdl2::event::WindowEvent::Leave => {
+                        events.push(MouseLeft(MouseLeftEventData::default()));
                     }
                     _ => {}
                 },
 
+                // `which` is the device index when added, the instance id afterwards
+                sdl2::event::Event::ControllerDeviceAdded { which, .. } => {
+                    match self.controller.open(which) {
+                        Ok(gamepad) => {
+                            let id = gamepad.instance_id();
+                            let name = gamepad.name();
+                            ubiinfo!("Gamepad {} connected: {}", id, name);
+                            self.gamepads.insert(id, gamepad);
+                            events.push(GamepadConnected(GamepadConnectedEventData::new(id, name)));
+                        }
+                        Err(e) => {
+                            ubiwarn!("Could not open gamepad {}: {}", which, e);
+                        }
+                    }
+                }
+
+                sdl2::event::Event::ControllerDeviceRemoved { which, .. } => {
+                    // Unknown ids are gamepads that failed to open
+                    if self.gamepads.remove(&which).is_none() {
+                        continue;
+                    }
+                    ubiinfo!("Gamepad {} disconnected", which);
+                    events.push(GamepadDisconnected(GamepadDisconnectedEventData::new(
+                        which,
+                    )));
+                }
+
+                sdl2::event::Event::ControllerButtonDown { which, button, .. } => {
+                    events.push(GamepadButtonPressed(GamepadButtonPressedEventData::new(
+                        which,
+                        gamepad_button(button),
+                    )));
+                }
+
+                sdl2::event::Event::ControllerButtonUp { which, button, .. } => {
+                    events.push(GamepadButtonReleased(GamepadButtonReleasedEventData::new(
+                        which,
+                        gamepad_button(button),
+                    )));
+                }
+
+                sdl2::event::Event::ControllerAxisMotion {
+                    which, axis, value, ..
+                } => {
+                    events.push(GamepadAxisMoved(GamepadAxisMovedEventData::new(
+                        which,
+                        gamepad_axis(axis),
+                        gamepad_axis_value(value),
+                    )));
+                }
+
+                sdl2::event::Event::KeyDown {
+                    keycode,
+                    scancode,
+                    keymod,
+                    repeat,
+                    ..
+                } => {
+                    let scan_code = scan_code(scancode);
+                    let repeat_count = self.key_repeats.entry(scan_code).or_insert(0);
+                    if repeat {
+                        *repeat_count += 1;
+                    } else {
+                        *repeat_count = 0;
+                    }
+                    events.push(KeyPressed(KeyPressedEventData::new(
+                        key_code(keycode),
+                        scan_code,
+                        modifiers(keymod),
+                        *repeat_count,
+                    )));
+                }
+
+                sdl2::event::Event::KeyUp {
+                    keycode,
+                    scancode,
+                    keymod,
+                    ..
+                } => {
+                    let scan_code = scan_code(scancode);
+                    self.key_repeats.remove(&scan_code);
+                    events.push(KeyReleased(KeyReleasedEventData::new(
+                        key_code(keycode),
+                        scan_code,
+                        modifiers(keymod),
+                    )));
+                }
+
+                sdl2::event::Event::TextInput { text, .. } => {
+                    events.push(TextInput(TextInputEventData::new(text)));
+                }
+
+                sdl2::event::Event::TextEditing {
+                    text,
+                    start,
+                    length,
+                    ..
+                } => {
+                    events.push(TextEditing(TextEditingEventData::new(text, start, length)));
+                }
+
+                sdl2::event::Event::DropBegin { .. } => {
+                    events.push(DropBegin(DropBeginEventData::default()));
+                }
+
+                sdl2::event::Event::DropFile { filename, .. } => {
+                    events.push(FileDropped(FileDroppedEventData::new(PathBuf::from(
+                        filename,
+                    ))));
+                }
+
+                sdl2::event::Event::DropComplete { .. } => {
+                    events.push(DropComplete(DropCompleteEventData::default()));
+                }
+
+                sdl2::event::Event::MouseMotion {
+                    x, y, xrel, yrel, ..
+                } => {
+                    events.push(MouseMoved(MouseMovedEventData::new(
+                        x as f32,
+                        y as f32,
+                        xrel as f32,
+                        yrel as f32,
+                    )));
+                }
+
                 sdl2::event::Event::MouseButtonDown {
-                    timestamp: _,
-                    window_id: _,
-                    which: _,
                     mouse_btn,
                     clicks,
                     x,
                     y,
+                    ..
+                } => {
+                    events.push(MouseButtonPressed(MouseButtonPressedEventData::new(
+                        mouse_button(mouse_btn),
+                        x,
+                        y,
+                        clicks as u32,
+                    )));
+                }
+
+                sdl2::event::Event::MouseButtonUp {
+                    mouse_btn, x, y, ..
                 } => {
-                    events.push(MouseButtonPressed(MouseButtonPressedEventData::new( mouse_btn as u32, x, y, clicks as u32 )));
+                    events.push(MouseButtonReleased(MouseButtonReleasedEventData::new(
+                        mouse_button(mouse_btn),
+                        x,
+                        y,
+                    )));
+                }
+
+                sdl2::event::Event::MouseWheel {
+                    direction,
+                    precise_x,
+                    precise_y,
+                    ..
+                } => {
+                    // Natural scrolling reports flipped offsets
+                    let sign = match direction {
+                        sdl2::mouse::MouseWheelDirection::Flipped => -1.0,
+                        _ => 1.0,
+                    };
+                    events.push(MouseScroll(MouseScrollEventData::new(
+                        precise_x * sign,
+                        precise_y * sign,
+                    )));
                 }
 
                 _ => {}
             }
         }
+
+        if check_scale {
+            let scale = self.display_scale();
+            if (scale - self.scale).abs() > f32::EPSILON {
+                self.scale = scale;
+                events.push(WindowScaleChanged(WindowScaleChangedEventData::new(scale)));
+            }
+        }
         Ok(())
     }
 
     fn clear(&self) {
+        // Passes can end with depth writes off or the scissor on, e.g. after sprites, text or egui,
+        // both apply to the clear
+        gl_state::bind_framebuffer(0);
+        gl_state::depth_mask(true);
+        gl_state::set_enabled(gl::SCISSOR_TEST, false);
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
             //51, 102, 204
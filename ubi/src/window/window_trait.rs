@@ -1,3 +1,6 @@
+use std::path::Path;
+use std::rc::Rc;
+
 use crate::core::custom_error::UbiError;
 
 pub struct WindowData<'a> {
@@ -16,12 +19,42 @@ impl Default for WindowData<'_> {
     }
 }
 
+// System clipboard, shared with the layers which do not own the window
+pub trait Clipboard {
+    fn get_text(&self) -> Result<String, UbiError>;
+    fn set_text(&self, text: &str) -> Result<(), UbiError>;
+}
+
 pub trait UBIWindow {
     fn create(window_data: WindowData) -> Result<Self, UbiError>
     where
         Self: Sized;
-    fn poll_events(&mut self, events: &mut Vec<crate::event::event::Event>) -> Result<(), UbiError>;
+    fn poll_events(&mut self, events: &mut Vec<crate::event::event::Event>)
+        -> Result<(), UbiError>;
     fn get_size(&self) -> (u32, u32);
+    // Display scale of the window, 1.0 at 96 DPI
+    fn get_scale(&self) -> f32;
+    // Intensities from 0.0 to 1.0 for the low and high frequency motors
+    fn rumble_gamepad(
+        &mut self,
+        id: u32,
+        low_frequency: f32,
+        high_frequency: f32,
+        duration_ms: u32,
+    ) -> Result<(), UbiError>;
+    // Add the mappings of a gamecontrollerdb.txt file, returns how many were added
+    fn load_gamepad_mappings(&mut self, path: &Path) -> Result<u32, UbiError>;
+    // Text input sends `TextInput` and `TextEditing` events and opens the IME, start it while
+    // a text field has the focus
+    fn start_text_input(&self);
+    fn stop_text_input(&self);
+    fn is_text_input_active(&self) -> bool;
+    // Area of the text being typed in window pixels, the IME shows its candidates next to it
+    fn set_text_input_rect(&self, x: i32, y: i32, width: u32, height: u32);
+    fn get_clipboard_text(&self) -> Result<String, UbiError>;
+    fn set_clipboard_text(&self, text: &str) -> Result<(), UbiError>;
+    // Handle for the `EguiLayer` and other layers
+    fn clipboard(&self) -> Rc<dyn Clipboard>;
     fn swap_buffers(&self);
     fn clear(&self);
     fn resize(&self, width: i32, height: i32);